@@ -0,0 +1,245 @@
+//! # Game integration example
+//!
+//! Reference program showing how a partner program deposits into the pro-balance vault through CPI.
+//!
+//! There are two ways of funding a deposit:
+//!
+//! * `deposit_for_player`: the player signs the outer transaction and the signature is forwarded to
+//!   pro-balance. `DepositEvent.user` is the player, so the backend credits the player directly.
+//! * `deposit_from_treasury`: the game pays from the treasury PDA of its authority, signing with its seeds.
+//!   `DepositEvent.user` is the treasury PDA, so the game emits `PlayerDepositEvent` in the same
+//!   instruction to attribute the deposit to a player.
+//!
+//! A deposit pro-balance rejects (paused deposits, deposit limits, deposit velocity limits) fails
+//! the CPI, so both instructions fail with it and the game never credits a deposit which has not
+//! moved funds.
+//!
+//! The treasury PDA must stay a plain system account (no data), otherwise the system program
+//! refuses to transfer lamports out of it.
+
+use anchor_lang::prelude::*;
+use pro_balance::{
+    cpi::accounts::Deposit, program::ProBalance, BLOCKED_SEED, DEPOSIT_WHITELIST_SEED,
+    EXCHANGE_WALLET_SEED, PROGRAM_CONFIG_SEED, USER_SEED, VAULT_CONFIG_SEED,
+};
+
+declare_id!("6C1Fh39PBtF91KJXjFmvdDHuvJ3koXAT3N99WXTqVcsz");
+
+/// Seed of the game treasury PDA
+pub const TREASURY_SEED: &str = "treasury";
+
+#[program]
+mod game_integration {
+    use super::*;
+
+    /// Deposits `amount` SOL of the player into the vault, the player is the depositor of record
+    pub fn deposit_for_player(ctx: Context<DepositForPlayer>, amount: u64) -> Result<()> {
+        let cpi_ctx = CpiContext::new(
+            ctx.accounts.pro_balance_program.to_account_info(),
+            Deposit {
+                master: ctx.accounts.master.to_account_info(),
+                vault_config: ctx.accounts.vault_config.to_account_info(),
+                program_config: ctx.accounts.program_config.to_account_info(),
+                user: ctx.accounts.player.to_account_info(),
+                user_blocked: ctx.accounts.player_blocked.to_account_info(),
+                user_whitelist: ctx.accounts.player_whitelist.to_account_info(),
+                user_state: ctx.accounts.player_state.to_account_info(),
+                user_exchange: ctx.accounts.player_exchange.to_account_info(),
+                product_ledger: None,
+                user_deposits: None,
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+        );
+
+        pro_balance::cpi::deposit(cpi_ctx, amount, None)
+    }
+
+    /// Deposits `amount` SOL from the game treasury into the vault on behalf of `player`
+    pub fn deposit_from_treasury(ctx: Context<DepositFromTreasury>, amount: u64) -> Result<()> {
+        let game_authority = ctx.accounts.game_authority.key();
+        let seeds: &[&[&[u8]]] = &[&[
+            TREASURY_SEED.as_bytes(),
+            game_authority.as_ref(),
+            &[ctx.bumps.treasury],
+        ]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.pro_balance_program.to_account_info(),
+            Deposit {
+                master: ctx.accounts.master.to_account_info(),
+                vault_config: ctx.accounts.vault_config.to_account_info(),
+                program_config: ctx.accounts.program_config.to_account_info(),
+                user: ctx.accounts.treasury.to_account_info(),
+                user_blocked: ctx.accounts.treasury_blocked.to_account_info(),
+                user_whitelist: ctx.accounts.treasury_whitelist.to_account_info(),
+                user_state: ctx.accounts.treasury_state.to_account_info(),
+                user_exchange: ctx.accounts.treasury_exchange.to_account_info(),
+                product_ledger: None,
+                user_deposits: None,
+                system_program: ctx.accounts.system_program.to_account_info(),
+            },
+            seeds,
+        );
+
+        pro_balance::cpi::deposit(cpi_ctx, amount, None)?;
+
+        emit!(PlayerDepositEvent {
+            player: ctx.accounts.player.key(),
+            treasury: ctx.accounts.treasury.key(),
+            amount,
+            time: Clock::get()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+}
+
+/// Event attributing a treasury funded deposit to a player.
+#[event]
+pub struct PlayerDepositEvent {
+    /// Player the deposit has been made for.
+    pub player: Pubkey,
+    /// Treasury which has paid for the deposit, `DepositEvent.user` of the same instruction.
+    pub treasury: Pubkey,
+    /// Amount of SOL.
+    pub amount: u64,
+    /// When does the deposit event has happened.
+    pub time: i64,
+}
+
+/// Accounts for `DepositForPlayer` instruction.
+#[derive(Accounts)]
+pub struct DepositForPlayer<'info> {
+    /// CHECK: validated by pro-balance, whose seeds include the vault_id
+    #[account(mut)]
+    pub master: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub vault_config: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub program_config: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub player: Signer<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), player.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub player_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [DEPOSIT_WHITELIST_SEED.as_bytes(), master.key().as_ref(), player.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub player_whitelist: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), player.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub player_state: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [EXCHANGE_WALLET_SEED.as_bytes(), master.key().as_ref(), player.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub player_exchange: UncheckedAccount<'info>,
+
+    pub pro_balance_program: Program<'info, ProBalance>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `DepositFromTreasury` instruction.
+#[derive(Accounts)]
+pub struct DepositFromTreasury<'info> {
+    /// CHECK: validated by pro-balance, whose seeds include the vault_id
+    #[account(mut)]
+    pub master: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub vault_config: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub program_config: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [TREASURY_SEED.as_bytes(), game_authority.key().as_ref()],
+        bump,
+    )]
+    pub treasury: SystemAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), treasury.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub treasury_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [DEPOSIT_WHITELIST_SEED.as_bytes(), master.key().as_ref(), treasury.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub treasury_whitelist: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), treasury.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub treasury_state: UncheckedAccount<'info>,
+
+    /// CHECK: validated by pro-balance
+    #[account(
+        seeds = [EXCHANGE_WALLET_SEED.as_bytes(), master.key().as_ref(), treasury.key().as_ref()],
+        bump,
+        seeds::program = pro_balance_program.key(),
+    )]
+    pub treasury_exchange: UncheckedAccount<'info>,
+
+    /// CHECK: only used for event attribution
+    pub player: UncheckedAccount<'info>,
+
+    pub game_authority: Signer<'info>,
+
+    pub pro_balance_program: Program<'info, ProBalance>,
+
+    pub system_program: Program<'info, System>,
+}
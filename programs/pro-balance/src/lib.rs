@@ -2,25 +2,38 @@
 
 //! # Contract Summary:
 //!
-//! This is a balance depositing contract for users. Users can deposit SOL or USDT into the contract.
-//! Then they add withdraw requests on a backend and only the operator sends these balances to the users.
-//! Admin wallet can withdraw any amount of SOL or USDT to his wallet.
+//! This is a balance depositing contract for users. Users can deposit SOL or any supported SPL token (e.g. USDT) into
+//! the contract. Then they add withdraw requests on a backend, or on-chain in the withdraw queue, and only the operator
+//! sends these balances to the users. The admin wallet can withdraw tokens, and SOL from the hot portion of the balance
+//! below the hot balance threshold, to their own wallet; admin SOL withdrawals stop once claim mode is triggered.
 //!
 //! Deposit events emit an event so the backend can sync these and create balances on a centralized database accordingly.
 //!
-//! users can not call withdraw or sendWithdraw functions. Their requests are handled off chain and handled by master.operator wallet
+//! On contract creation, the upgrade authority runs init_program_config, whose super admin sets protocol-wide
+//! guardrails and runs init_master(vault_id) once per vault. The master PDA holds the admin, operator and guardian
+//! wallets and the SOL balance; master PDA ATAs hold the token balances, tracked per mint in vault token PDAs.
+//! The tunables of a vault (pause flags, limits, fees, reserves) live on its vault config PDA and are changed with
+//! update_config or the dedicated setters, each emitting a ConfigChangedEvent.
 //!
-//! On contract creation, the deployer runs init_master to create master PDA. This PDA holds admin and operator wallets
-//! The deployer then runs init_ata to create USDT ATA for master PDA.
-//!
-//! Master PDA keeps the SOL balance. Master PDA ATA keeps the USDT balance.
+//! Each instruction documents its own behavior; see README.md for a walkthrough of the whole vault.
 //!
 
 use anchor_lang::{
     prelude::*,
-    solana_program::{clock::Clock, program::invoke, pubkey::Pubkey, system_instruction::transfer},
+    solana_program::{
+        address_lookup_table,
+        clock::Clock,
+        ed25519_program,
+        hash::hashv,
+        program::{invoke, invoke_signed},
+        pubkey::Pubkey,
+        secp256k1_program,
+        system_instruction::transfer,
+        sysvar::instructions::{load_current_index_checked, load_instruction_at_checked},
+    },
 };
 use anchor_spl::associated_token::AssociatedToken;
+use anchor_spl::memo::{build_memo, BuildMemo, Memo};
 use anchor_spl::token::*;
 use std::mem::size_of;
 
@@ -29,602 +42,11239 @@ declare_id!("8ZwcssGn5vKE1d6oBNNTTjDsFyTDKSuPtoooZQe9MHXb");
 /// Master seed for the smart contract
 pub const MASTER_SEED: &str = "master";
 
+/// Seed for the PDA holding the tunables of a vault
+pub const VAULT_CONFIG_SEED: &str = "vault_config";
+
+/// Seed for the program-wide config PDA
+pub const PROGRAM_CONFIG_SEED: &str = "program_config";
+
+/// Maximum number of mints the program config can allow
+pub const MAX_ALLOWED_MINTS: usize = 16;
+
+/// Seed for the registry PDA enumerating all vaults
+pub const VAULT_REGISTRY_SEED: &str = "vault_registry";
+
+/// Maximum number of vaults the registry can hold
+pub const MAX_VAULTS: usize = 64;
+
+/// Basis points making up 100%
+pub const MAX_BPS: u16 = 10_000;
+
+/// Seed for the PDAs marking receivers allowed to get operator payouts
+pub const RECEIVER_ALLOWLIST_SEED: &str = "receiver_allowlist";
+
+/// Seed for the PDAs marking blocked (sanctioned) addresses
+pub const BLOCKED_SEED: &str = "blocked";
+
+/// Seed for the PDAs marking users allowed to deposit while the deposit whitelist is enabled
+pub const DEPOSIT_WHITELIST_SEED: &str = "deposit_whitelist";
+
+/// Seed for the per-user state PDAs
+pub const USER_SEED: &str = "user";
+
+/// Seed for the PDAs marking canary receivers
+pub const CANARY_SEED: &str = "canary";
+
+/// Seed for the per-mint vault token PDAs
+pub const VAULT_TOKEN_SEED: &str = "vault_token";
+
+/// Seed for the per-product sub-ledger PDAs
+pub const PRODUCT_SEED: &str = "product";
+
+/// Seed for the per-operator penalty ledger PDAs
+pub const PENALTY_SEED: &str = "penalty";
+
+/// Seed for the per-receiver payout lock PDAs
+pub const RECEIVER_LOCK_SEED: &str = "receiver_lock";
+
+/// Seed for the temporary wSOL account used to unwrap SOL
+pub const UNWRAP_SEED: &str = "unwrap";
+
+/// Seed for the PDAs tagging known exchange hot wallets
+pub const EXCHANGE_WALLET_SEED: &str = "exchange_wallet";
+
+/// Seed for the PDA owning the address lookup table of a vault
+pub const LOOKUP_TABLE_SEED: &str = "lookup_table";
+
+/// Prefix of the seeds of withdraw request PDAs
+pub const WITHDRAW_REQUEST_SEED: &str = "withdraw_request";
+
+/// Prefix of the seeds of the withdraw queue PDA of a vault
+pub const WITHDRAW_QUEUE_SEED: &str = "withdraw_queue";
+
+/// Prefix of the seeds of payout receipt PDAs
+pub const RECEIPT_SEED: &str = "receipt";
+
+/// Prefix of the seeds of the merkle payout root PDA of an epoch
+pub const PAYOUT_ROOT_SEED: &str = "payout_root";
+
+/// Prefix of the seeds of the PDA recording a merkle payout claim
+pub const CLAIM_SEED: &str = "claim";
+
+/// Prefix of the seeds of scheduled payout PDAs
+pub const SCHEDULED_PAYOUT_SEED: &str = "scheduled_payout";
+
+/// Prefix of the seeds of the PDA recording the refund of a deposit
+pub const DEPOSIT_REFUND_SEED: &str = "deposit_refund";
+
+/// Prefix of the seeds of the escrow PDAs holding deposits during the escrow window
+pub const DEPOSIT_ESCROW_SEED: &str = "deposit_escrow";
+
+/// Epoch of the payout root published balances are claimed against once the vault is in claim mode
+pub const CLAIM_MODE_EPOCH: u64 = u64::MAX;
+
+/// Prefix of the seeds of the PDA tracking the self-service withdrawals of a user
+pub const SELF_WITHDRAWAL_SEED: &str = "self_withdrawal";
+
+/// Prefix of the seeds of announced payout batch PDAs
+pub const ANNOUNCED_BATCH_SEED: &str = "announced_batch";
+
+/// Prefix of the seeds of the PDA tracking the lifetime deposits of a user in one asset
+pub const USER_DEPOSITS_SEED: &str = "user_deposits";
+
+/// Seed for the per-receiver PDAs recording the last operator payout
+pub const RECEIVER_COOLDOWN_SEED: &str = "receiver_cooldown";
+
+/// Seed for the PDAs marking trusted receivers exempt from payout limits
+pub const LIMIT_EXEMPT_SEED: &str = "limit_exempt";
+
+/// Seconds a payout receipt is kept before `close_receipt` can recover its rent
+pub const RECEIPT_RETENTION: i64 = 30 * SECONDS_PER_DAY;
+
+/// Seconds a withdraw request stays payable, afterwards anyone can close it with `expire_request`
+pub const WITHDRAW_REQUEST_TTL: i64 = 7 * SECONDS_PER_DAY;
+
+/// Current layout version of the master account, bumped whenever fields are appended to `Master`
+pub const MASTER_VERSION: u8 = 1;
+
+/// Remaining accounts `send_withdraw_batch` takes per payout: receiver, allowlist, blocked, user state and canary PDA
+pub const BATCH_ACCOUNTS_PER_PAYOUT: usize = 5;
+
+/// Remaining accounts `send_withdraw_token_batch` takes per payout: receiver, its ATA, allowlist, blocked, user state
+/// and canary PDA
+pub const TOKEN_BATCH_ACCOUNTS_PER_PAYOUT: usize = 6;
+
+/// Decimals of SOL amounts, which are in lamports
+pub const SOL_DECIMALS: u8 = 9;
+
+/// Maximum length in bytes of an operator fault reason
+pub const MAX_FAULT_REASON_LEN: usize = 64;
+
+/// Maximum length in bytes of a payout memo
+pub const MAX_MEMO_LEN: usize = 64;
+
 #[program]
 mod pro_balance {
     use super::*;
 
-    /// Will be run once after the deployment to set master PDA and setting admin operator wallets
-    pub fn init_master(ctx: Context<InitMaster>) -> Result<()> {
-        let master = &mut ctx.accounts.master;
-        let operator = &ctx.accounts.operator;
+    /// Will be run by program_config.super_admin once per vault to set master PDA and setting admin operator wallets.
+    /// The vault is recorded in the vault registry so it can be discovered.
+    pub fn init_master(
+        ctx: Context<InitMaster>,
+        vault_id: u64,
+        metadata: VaultMetadata,
+    ) -> Result<()> {
+        create_vault(
+            &mut ctx.accounts.master,
+            &mut ctx.accounts.vault_config,
+            &mut ctx.accounts.vault_registry,
+            vault_id,
+            ctx.accounts.admin.key(),
+            ctx.accounts.operator.key(),
+            metadata,
+        )
+    }
+
+    /// This function can be called by master.admin after an upgrade that appended fields to `Master`. It grows the
+    /// master account of `vault_id` to the current layout, the admin paying the extra rent, so the appended fields
+    /// read as their zero defaults, and sets its version to `MASTER_VERSION`.
+    pub fn migrate_master(ctx: Context<MigrateMaster>, _vault_id: u64) -> Result<()> {
+        let master_info = ctx.accounts.master.to_account_info();
         let admin = &ctx.accounts.admin;
 
-        master.operator = operator.key();
-        master.admin = admin.key();
+        require_keys_eq!(
+            *master_info.owner,
+            crate::ID,
+            ErrorCode::AccountOwnedByWrongProgram
+        );
+
+        let old_len = master_info.data_len();
+        if old_len < MASTER_SIZE {
+            let rent = get_rent()?;
+            let top_up = rent
+                .minimum_balance(MASTER_SIZE)
+                .saturating_sub(rent.minimum_balance(old_len));
+
+            invoke(
+                &transfer(&admin.key(), master_info.key, top_up),
+                &[
+                    admin.to_account_info(),
+                    master_info.clone(),
+                    ctx.accounts.system_program.to_account_info(),
+                ],
+            )?;
+            master_info.realloc(MASTER_SIZE, true)?;
+        }
+
+        let mut master = Master::try_deserialize(&mut &master_info.try_borrow_data()?[..])?;
+        require_keys_eq!(master.admin, admin.key(), Errors::Unauthorized);
+
+        let from_version = master.version;
+        master.version = MASTER_VERSION;
+        master.try_serialize(&mut &mut master_info.try_borrow_mut_data()?[..])?;
+
+        emit!(MasterMigratedEvent {
+            master: master_info.key(),
+            from_version,
+            to_version: MASTER_VERSION,
+            time: get_clock()?.unix_timestamp,
+        });
 
         Ok(())
     }
 
-    /// Will be run once to set USDT ATA
-    pub fn init_ata(ctx: Context<InitAta>) -> Result<()> {
+    /// Will be run by program_config.super_admin once to move the master deployed before vaults and versioning, the
+    /// single master PDA at seeds `[MASTER_SEED]`, into the new vault `vault_id`. The vault takes over its admin,
+    /// operator and SOL balance. Without a token account the old master is closed, its rent going to the super admin,
+    /// otherwise it stays until its tokens are moved with `migrate_legacy_token`.
+    pub fn migrate_legacy_master(
+        ctx: Context<MigrateLegacyMaster>,
+        vault_id: u64,
+        metadata: VaultMetadata,
+    ) -> Result<()> {
+        let legacy_info = ctx.accounts.legacy_master.to_account_info();
+        let mut legacy = load_legacy_master(&legacy_info)?;
+
         let master = &mut ctx.accounts.master;
+        create_vault(
+            master,
+            &mut ctx.accounts.vault_config,
+            &mut ctx.accounts.vault_registry,
+            vault_id,
+            legacy.admin,
+            legacy.operator,
+            metadata,
+        )?;
+        master.last_withdraw_time = legacy.last_withdraw_time;
 
-        if master.token_account.is_some() {
-            return Err(Errors::TokenAccountAlreadyCreated.into());
+        let amount = legacy.balance;
+        legacy_info.sub_lamports(amount)?;
+        master.add_lamports(amount)?;
+        master.balance = amount;
+
+        if legacy.token_account.is_some() {
+            legacy.balance = 0;
+            legacy.serialize(&mut &mut legacy_info.try_borrow_mut_data()?[8..])?;
+        } else {
+            close_legacy_master(&legacy_info, &ctx.accounts.payer.to_account_info())?;
         }
 
-        master.token_account = Some(ctx.accounts.master_ata.key());
+        let clock = get_clock()?;
+
+        emit!(MasterMigratedEvent {
+            master: master.key(),
+            from_version: 0,
+            to_version: MASTER_VERSION,
+            time: clock.unix_timestamp,
+        });
+        emit!(LegacyMigratedEvent {
+            legacy_master: legacy_info.key(),
+            master: master.key(),
+            mint: None,
+            amount,
+            time: clock.unix_timestamp,
+        });
 
         Ok(())
     }
 
-    /// this function is run by users to deposit SOL into the contract (master PDA balance)
-    pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
-        let master = &mut ctx.accounts.master;
-        let user = &ctx.accounts.user;
+    /// This function can be called by master.admin, who has to be the admin of the master deployed before vaults, once
+    /// `migrate_legacy_master` has run and the mint of its token account is a supported mint of the vault. It moves
+    /// the tokens of that account to the vault, closes it and the old master, their rent going to the admin.
+    pub fn migrate_legacy_token(ctx: Context<MigrateLegacyToken>) -> Result<()> {
+        let legacy_info = ctx.accounts.legacy_master.to_account_info();
+        let legacy = load_legacy_master(&legacy_info)?;
+        let legacy_ata = &ctx.accounts.legacy_ata;
+        let admin = &ctx.accounts.admin;
 
-        invoke(
-            &transfer(&user.key(), &master.key(), amount),
-            &[
-                user.to_account_info(),
-                master.to_account_info(),
-                ctx.accounts.system_program.to_account_info(),
-            ],
+        require_keys_eq!(legacy.admin, admin.key(), Errors::Unauthorized);
+        require!(
+            legacy.token_account == Some(legacy_ata.key()),
+            Errors::LegacyTokenAccountMismatch
+        );
+
+        let amount = legacy_ata.amount;
+        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &[ctx.bumps.legacy_master]]];
+
+        anchor_spl::token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: legacy_ata.to_account_info(),
+                    to: ctx.accounts.master_ata.to_account_info(),
+                    authority: legacy_info.clone(),
+                },
+                seeds,
+            ),
+            amount,
         )?;
+        anchor_spl::token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: legacy_ata.to_account_info(),
+                destination: admin.to_account_info(),
+                authority: legacy_info.clone(),
+            },
+            seeds,
+        ))?;
+        close_legacy_master(&legacy_info, &admin.to_account_info())?;
 
-        master.balance = master
+        let vault_token = &mut ctx.accounts.vault_token;
+        vault_token.balance = vault_token
             .balance
             .checked_add(amount)
             .map(Ok)
             .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
 
-        let clock = Clock::get()?;
-
-        emit!(DepositEvent {
-            user: ctx.accounts.user.key(),
-            holder: master.key(),
+        emit!(LegacyMigratedEvent {
+            legacy_master: legacy_info.key(),
+            master: ctx.accounts.master.key(),
+            mint: Some(vault_token.mint),
             amount,
-            time: clock.unix_timestamp,
+            time: get_clock()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    /// This function is run by users to deposit USDT into the contract (master PDA ATA balance)
-    pub fn deposit_token(ctx: Context<DepositToken>, amount: u64) -> Result<()> {
-        let master = &mut ctx.accounts.master;
-        let from = &ctx.accounts.from;
-        let to = &ctx.accounts.master_ata;
-        let user = &ctx.accounts.user;
+    /// Will be run once by the program upgrade authority to create the program config holding protocol-wide limits
+    pub fn init_program_config(ctx: Context<InitProgramConfig>, max_fee_bps: u16) -> Result<()> {
+        require!(max_fee_bps <= MAX_BPS, Errors::FeeTooHigh);
 
-        let transfer_instruction = Transfer {
-            from: from.to_account_info(),
-            to: to.to_account_info(),
-            authority: user.to_account_info(),
-        };
+        let program_config = &mut ctx.accounts.program_config;
+        program_config.super_admin = ctx.accounts.authority.key();
+        program_config.max_fee_bps = max_fee_bps;
 
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-        let cpi_ctx = CpiContext::new(cpi_program, transfer_instruction);
+        Ok(())
+    }
 
-        anchor_spl::token::transfer(cpi_ctx, amount)?;
+    /// This function can be called by program_config.super_admin to transfer super admin rights to a new wallet
+    pub fn set_super_admin(ctx: Context<SetSuperAdmin>) -> Result<()> {
+        let program_config = &mut ctx.accounts.program_config;
 
-        master.token_balance = master
-            .token_balance
-            .checked_add(amount)
-            .map(Ok)
-            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        program_config.super_admin = ctx.accounts.new_super_admin.key();
+        Ok(())
+    }
 
-        let clock = Clock::get()?;
+    /// This function can be called by program_config.super_admin to change the highest fee any vault may charge
+    pub fn set_max_fee_bps(ctx: Context<UpdateProgramConfig>, max_fee_bps: u16) -> Result<()> {
+        require!(max_fee_bps <= MAX_BPS, Errors::FeeTooHigh);
 
-        emit!(DepositEvent {
-            user: ctx.accounts.user.key(),
-            holder: to.key(),
-            amount,
-            time: clock.unix_timestamp,
+        let program_config = &mut ctx.accounts.program_config;
+        program_config.max_fee_bps = max_fee_bps;
+
+        emit!(ProgramConfigEvent {
+            max_fee_bps,
+            paused: program_config.paused,
+            allowed_mints: program_config.allowed_mints.clone(),
+            time: get_clock()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    /// This function can be called by master.admin to set a new operator
-    pub fn set_operator(ctx: Context<SetOperator>) -> Result<()> {
-        let master = &mut ctx.accounts.master;
-        let operator = &ctx.accounts.new_operator;
+    /// This function can be called by program_config.super_admin to stop or resume deposits and payouts of every
+    /// vault at once. The pause flags of each vault are kept and apply again once the program is resumed.
+    pub fn set_program_paused(ctx: Context<UpdateProgramConfig>, paused: bool) -> Result<()> {
+        let program_config = &mut ctx.accounts.program_config;
+        program_config.paused = paused;
+
+        emit!(ProgramConfigEvent {
+            max_fee_bps: program_config.max_fee_bps,
+            paused,
+            allowed_mints: program_config.allowed_mints.clone(),
+            time: get_clock()?.unix_timestamp,
+        });
 
-        master.operator = operator.key();
         Ok(())
     }
 
-    /// This function can be called by master.admin to transfer admin rights to a new wallet
-    pub fn set_admin(ctx: Context<SetAdmin>) -> Result<()> {
-        let master = &mut ctx.accounts.master;
-        let admin = &ctx.accounts.new_admin;
+    /// This function can be called by program_config.super_admin to allow vaults to hold `mint`
+    pub fn add_allowed_mint(ctx: Context<UpdateProgramConfig>, mint: Pubkey) -> Result<()> {
+        let program_config = &mut ctx.accounts.program_config;
+
+        if !program_config.allowed_mints.contains(&mint) {
+            require!(
+                program_config.allowed_mints.len() < MAX_ALLOWED_MINTS,
+                Errors::TooManyAllowedMints
+            );
+            program_config.allowed_mints.push(mint);
+        }
+
+        emit!(ProgramConfigEvent {
+            max_fee_bps: program_config.max_fee_bps,
+            paused: program_config.paused,
+            allowed_mints: program_config.allowed_mints.clone(),
+            time: get_clock()?.unix_timestamp,
+        });
 
-        master.admin = admin.key();
         Ok(())
     }
 
-    /// This function can be called by master.admin to withdraw any SOL amount to his wallet
-    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
-        let master = &mut ctx.accounts.master;
-        let admin = &mut ctx.accounts.admin;
+    /// This function can be called by program_config.super_admin to stop vaults from registering `mint`
+    pub fn remove_allowed_mint(ctx: Context<UpdateProgramConfig>, mint: Pubkey) -> Result<()> {
+        let program_config = &mut ctx.accounts.program_config;
+        program_config
+            .allowed_mints
+            .retain(|allowed| allowed != &mint);
 
-        let rent_exemption = Rent::get()?.minimum_balance(MASTER_SIZE);
-        require!(
-            master.balance
-                > amount
-                    .checked_add(rent_exemption)
-                    .map(Ok)
-                    .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?,
-            Errors::NotEnoughBalance
-        );
+        emit!(ProgramConfigEvent {
+            max_fee_bps: program_config.max_fee_bps,
+            paused: program_config.paused,
+            allowed_mints: program_config.allowed_mints.clone(),
+            time: get_clock()?.unix_timestamp,
+        });
 
-        master.sub_lamports(amount)?;
-        admin.add_lamports(amount)?;
+        Ok(())
+    }
 
-        master.balance = master
-            .balance
-            .checked_sub(amount)
-            .map(Ok)
-            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+    /// This function can be called by master.admin once per mint to create its vault token PDA and master ATA.
+    /// The mint has to be allowed by the program config.
+    pub fn add_supported_mint(ctx: Context<AddSupportedMint>) -> Result<()> {
+        let accounts = ctx.accounts;
+        register_mint(
+            &accounts.program_config,
+            &mut accounts.master,
+            &mut accounts.vault_token,
+            &accounts.token_mint,
+            accounts.master_ata.key(),
+        )
+    }
 
-        let clock = Clock::get()?;
+    /// This function can be called by master.admin instead of `add_supported_mint` when custody requires a specific,
+    /// non-associated token account. The existing `token_account` has to be owned by master PDA, with no delegate
+    /// and no close authority, and is registered as the vault's account for its mint.
+    pub fn add_supported_mint_account(ctx: Context<AddSupportedMintAccount>) -> Result<()> {
+        let accounts = ctx.accounts;
+        register_mint(
+            &accounts.program_config,
+            &mut accounts.master,
+            &mut accounts.vault_token,
+            &accounts.token_mint,
+            accounts.token_account.key(),
+        )
+    }
 
-        emit!(AdminWithdrawEvent {
-            user: admin.key(),
-            holder: master.key(),
-            amount,
-            time: clock.unix_timestamp,
+    /// This function can be called by master.admin to stop accepting deposits of `mint`. Payouts keep working.
+    pub fn disable_mint(ctx: Context<UpdateVaultToken>, _mint: Pubkey) -> Result<()> {
+        let vault_token = &mut ctx.accounts.vault_token;
+        vault_token.enabled = false;
+        ctx.accounts.master.bump_config_generation();
+
+        emit!(SupportedMintEvent {
+            mint: vault_token.mint,
+            enabled: false,
+            removed: false,
+            time: get_clock()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    /// This function can be called by master.admin to withdraw any USDT amount to his wallet
-    pub fn withdraw_token(ctx: Context<WithdrawToken>, amount: u64) -> Result<()> {
-        let master = &mut ctx.accounts.master;
-        let admin = &mut ctx.accounts.admin_ata;
-        let from = &mut ctx.accounts.master_ata;
+    /// This function can be called by master.admin to accept deposits of a disabled `mint` again
+    pub fn enable_mint(ctx: Context<UpdateVaultToken>, _mint: Pubkey) -> Result<()> {
+        let vault_token = &mut ctx.accounts.vault_token;
+        vault_token.enabled = true;
+        ctx.accounts.master.bump_config_generation();
 
-        let cpi_program = ctx.accounts.token_program.to_account_info();
+        emit!(SupportedMintEvent {
+            mint: vault_token.mint,
+            enabled: true,
+            removed: false,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
 
-        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &[ctx.bumps.master]]];
+    /// This function can be called by master.admin to drop `mint` from the vault once its ATA is empty.
+    /// The vault token PDA and the master ATA are closed and their rent goes back to master.admin.
+    pub fn remove_mint(ctx: Context<RemoveMint>, mint: Pubkey) -> Result<()> {
+        require!(ctx.accounts.master_ata.amount == 0, Errors::MintNotEmpty);
+
+        let vault_id = ctx.accounts.master.vault_id.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &vault_id, &[ctx.bumps.master]]];
 
         let cpi_ctx = CpiContext::new_with_signer(
-            cpi_program,
-            Transfer {
-                from: from.to_account_info(),
-                to: admin.to_account_info(),
-                authority: master.to_account_info(),
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: ctx.accounts.master_ata.to_account_info(),
+                destination: ctx.accounts.admin.to_account_info(),
+                authority: ctx.accounts.master.to_account_info(),
             },
             seeds,
         );
 
-        anchor_spl::token::transfer(cpi_ctx, amount)?;
-
-        master.token_balance = master
-            .token_balance
-            .checked_sub(amount)
-            .map(Ok)
-            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
-
-        let clock = Clock::get()?;
+        anchor_spl::token::close_account(cpi_ctx)?;
+        ctx.accounts.master.bump_config_generation();
 
-        emit!(AdminWithdrawEvent {
-            user: admin.key(),
-            holder: from.key(),
-            amount,
-            time: clock.unix_timestamp,
+        emit!(SupportedMintEvent {
+            mint,
+            enabled: false,
+            removed: true,
+            time: get_clock()?.unix_timestamp,
         });
 
         Ok(())
     }
 
-    /// This function can be called by master.operator to send withdraw SOL amount to user wallet
-    pub fn send_withdraw(ctx: Context<SendWithdraw>, amount: u64) -> Result<()> {
+    /// this function is run by users to deposit SOL into the contract (master PDA balance)
+    /// `quote` optionally records the fiat value the frontend has shown for this deposit.
+    /// The deposit has to be between the minimum and maximum deposit of the vault and within the user deposit cap and
+    /// the vault cap. A deposit exceeding the deposit velocity limits fails with `DepositVelocityExceeded`. The deposit event
+    /// classifies the depositor as a wallet, a PDA (a program depositing through CPI) or a tagged exchange wallet.
+    pub fn deposit(ctx: Context<Deposit>, amount: u64, quote: Option<FiatQuote>) -> Result<()> {
+        require!(
+            !ctx.accounts.vault_config.deposits_paused,
+            Errors::DepositsPaused
+        );
+        require!(
+            ctx.accounts.vault_config.deposit_escrow_window == 0,
+            Errors::DepositEscrowRequired
+        );
+
         let master = &mut ctx.accounts.master;
-        let receiver = &mut ctx.accounts.receiver;
 
-        let clock = Clock::get()?;
-        master.last_withdraw_time = clock.unix_timestamp;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let user = &ctx.accounts.user;
 
-        let rent_exemption = Rent::get()?.minimum_balance(MASTER_SIZE);
+        require_not_blocked(&ctx.accounts.user_blocked, user.key())?;
+        require_not_frozen(&ctx.accounts.user_state)?;
         require!(
-            master.balance
-                > amount
-                    .checked_add(rent_exemption)
-                    .map(Ok)
-                    .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?,
-            Errors::NotEnoughBalance
+            !vault_config.deposit_whitelist_enabled || pda_exists(&ctx.accounts.user_whitelist),
+            Errors::DepositorNotWhitelisted
         );
+        require_deposit_size(vault_config.min_deposit, vault_config.max_deposit, amount)?;
+        record_deposit_velocity(master, vault_config, None, amount)?;
+        record_user_deposit(
+            ctx.accounts.user_deposits.as_deref_mut(),
+            vault_config.user_deposit_cap,
+            amount,
+        )?;
+        let source = deposit_source(&user.key(), &ctx.accounts.user_exchange);
+        require_valid_quote(&quote)?;
+        let limit = vault_config.max_daily_net_inflow;
+        record_inflow(&mut master.net_flow, limit, amount)?;
+        master.lifetime.record_deposit(amount);
+        require_rent_exempt(
+            &master.to_account_info(),
+            master
+                .get_lamports()
+                .checked_add(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?,
+        )?;
 
-        master.sub_lamports(amount)?;
-        receiver.add_lamports(amount)?;
+        invoke(
+            &transfer(&user.key(), &master.key(), amount),
+            &[
+                user.to_account_info(),
+                master.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
 
         master.balance = master
             .balance
-            .checked_sub(amount)
+            .checked_add(amount)
             .map(Ok)
             .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        require_within_vault_cap(vault_config.vault_cap, master.balance)?;
+        let product_id = credit_product(ctx.accounts.product_ledger.as_deref_mut(), amount)?;
 
-        emit!(WithdrawEvent {
-            user: receiver.key(),
+        let clock = get_clock()?;
+
+        emit!(DepositEvent {
+            user: ctx.accounts.user.key(),
             holder: master.key(),
             amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
             time: clock.unix_timestamp,
+            quote,
+            product_id,
+            source,
+            config_generation: master.config_generation,
         });
 
         Ok(())
     }
 
-    /// This function can be called by master.operator to send withdraw USDT amount to user wallet
-    pub fn send_withdraw_token(ctx: Context<SendWithdrawToken>, amount: u64) -> Result<()> {
+    /// This function is run by users to deposit SOL while the vault holds deposits in escrow. The SOL is kept in the
+    /// deposit escrow PDA `escrow_id` of the user until the escrow window has passed and the operator merges it into
+    /// the master PDA balance with `release_deposit`, or sends it back with `reject_deposit`.
+    pub fn deposit_escrowed(
+        ctx: Context<DepositEscrowed>,
+        escrow_id: u64,
+        amount: u64,
+    ) -> Result<()> {
         let master = &mut ctx.accounts.master;
-        let receiver = &mut ctx.accounts.receiver_ata;
-        let from = &mut ctx.accounts.master_ata;
-
-        let clock = Clock::get()?;
-        master.last_withdraw_time = clock.unix_timestamp;
-
-        let cpi_program = ctx.accounts.token_program.to_account_info();
-
-        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &[ctx.bumps.master]]];
+        let vault_config = &mut ctx.accounts.vault_config;
+        let user = &ctx.accounts.user;
 
-        let cpi_ctx = CpiContext::new_with_signer(
-            cpi_program,
-            Transfer {
-                from: from.to_account_info(),
-                to: receiver.to_account_info(),
-                authority: master.to_account_info(),
-            },
-            seeds,
+        require!(!vault_config.deposits_paused, Errors::DepositsPaused);
+        require!(
+            vault_config.deposit_escrow_window > 0,
+            Errors::DepositEscrowDisabled
+        );
+        require_not_blocked(&ctx.accounts.user_blocked, user.key())?;
+        require_not_frozen(&ctx.accounts.user_state)?;
+        require!(
+            !vault_config.deposit_whitelist_enabled || pda_exists(&ctx.accounts.user_whitelist),
+            Errors::DepositorNotWhitelisted
         );
+        require_deposit_size(vault_config.min_deposit, vault_config.max_deposit, amount)?;
+        record_deposit_velocity(master, vault_config, None, amount)?;
+        record_user_deposit(
+            ctx.accounts.user_deposits.as_deref_mut(),
+            vault_config.user_deposit_cap,
+            amount,
+        )?;
+        let source = deposit_source(&user.key(), &ctx.accounts.user_exchange);
 
-        anchor_spl::token::transfer(cpi_ctx, amount)?;
+        invoke(
+            &transfer(&user.key(), &ctx.accounts.escrow.key(), amount),
+            &[
+                user.to_account_info(),
+                ctx.accounts.escrow.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
 
-        master.token_balance = master
-            .token_balance
-            .checked_sub(amount)
+        let clock = get_clock()?;
+        let escrow = &mut ctx.accounts.escrow;
+        escrow.master = master.key();
+        escrow.user = user.key();
+        escrow.escrow_id = escrow_id;
+        escrow.amount = amount;
+        escrow.source = source;
+        escrow.release_after = clock
+            .unix_timestamp
+            .checked_add(vault_config.deposit_escrow_window)
             .map(Ok)
             .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
 
-        emit!(WithdrawEvent {
-            user: receiver.key(),
-            holder: from.key(),
+        emit!(DepositEscrowedEvent {
+            user: user.key(),
+            holder: master.key(),
+            escrow: escrow.key(),
             amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            release_after: escrow.release_after,
             time: clock.unix_timestamp,
+            source,
+            config_generation: master.config_generation,
         });
 
         Ok(())
     }
-}
 
-/// Errors of this smart contract.
-#[error_code]
-pub enum Errors {
-    /// Unathorized access of the smart contract.
-    #[msg("Unauthorized")]
-    Unauthorized,
-    /// Not enough balance
-    #[msg("Not Enough Balance")]
-    NotEnoughBalance,
-    /// Token account has already been created.
-    #[msg("Token account has already been created")]
-    TokenAccountAlreadyCreated,
+    /// This function can be called by master.operator once the escrow window of an escrowed deposit has passed. It
+    /// merges the SOL into the master PDA balance, emits the `DepositEvent` the backend credits the user on and
+    /// returns the rent of the escrow PDA to the user.
+    pub fn release_deposit(ctx: Context<ReleaseDeposit>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &ctx.accounts.vault_config;
+        let escrow = &ctx.accounts.escrow;
+        let amount = escrow.amount;
+
+        require!(!vault_config.deposits_paused, Errors::DepositsPaused);
+        let clock = get_clock()?;
+        require!(
+            clock.unix_timestamp >= escrow.release_after,
+            Errors::DepositInEscrowWindow
+        );
+
+        let limit = vault_config.max_daily_net_inflow;
+        record_inflow(&mut master.net_flow, limit, amount)?;
+        master.lifetime.record_deposit(amount);
+
+        escrow.sub_lamports(amount)?;
+        master.add_lamports(amount)?;
+        require_rent_exempt(&master.to_account_info(), master.get_lamports())?;
+
+        master.balance = master
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        require_within_vault_cap(vault_config.vault_cap, master.balance)?;
+
+        emit!(DepositEvent {
+            user: escrow.user,
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+            quote: None,
+            product_id: None,
+            source: escrow.source,
+            config_generation: master.config_generation,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to send an escrowed deposit back to the user before it has
+    /// been released, e.g. once its funding source has been charged back. The escrow PDA is closed to the user and
+    /// the amount no longer counts against the lifetime deposits of the user.
+    pub fn reject_deposit(ctx: Context<RejectDeposit>) -> Result<()> {
+        let escrow = &ctx.accounts.escrow;
+
+        unrecord_user_deposit(
+            ctx.accounts.user_deposits.as_deref_mut(),
+            ctx.accounts.vault_config.user_deposit_cap,
+            escrow.amount,
+        )?;
+
+        emit!(DepositRejectedEvent {
+            user: escrow.user,
+            holder: ctx.accounts.master.key(),
+            escrow: escrow.key(),
+            amount: escrow.amount,
+            time: get_clock()?.unix_timestamp,
+            config_generation: ctx.accounts.master.config_generation,
+        });
+
+        Ok(())
+    }
+
+    /// This function is run by users to deposit any supported token into the contract (master PDA ATA balance)
+    /// `quote` optionally records the fiat value the frontend has shown for this deposit.
+    /// The limits of the mint's vault token apply like the SOL limits apply to `deposit`.
+    pub fn deposit_token(
+        ctx: Context<DepositToken>,
+        amount: u64,
+        quote: Option<FiatQuote>,
+    ) -> Result<()> {
+        require!(
+            !ctx.accounts.vault_config.deposits_paused,
+            Errors::DepositsPaused
+        );
+
+        let master = &mut ctx.accounts.master;
+
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+        let from = &ctx.accounts.from;
+        let to = &ctx.accounts.master_ata;
+        let user = &ctx.accounts.user;
+
+        require!(vault_token.enabled, Errors::MintDisabled);
+        require_not_blocked(&ctx.accounts.user_blocked, user.key())?;
+        require_not_frozen(&ctx.accounts.user_state)?;
+        require!(
+            !vault_config.deposit_whitelist_enabled || pda_exists(&ctx.accounts.user_whitelist),
+            Errors::DepositorNotWhitelisted
+        );
+        require_deposit_size(vault_token.min_deposit, vault_token.max_deposit, amount)?;
+        record_deposit_velocity(master, vault_config, Some(vault_token), amount)?;
+        record_user_deposit(
+            ctx.accounts.user_deposits.as_deref_mut(),
+            vault_token.user_deposit_cap,
+            amount,
+        )?;
+        let source = deposit_source(&user.key(), &ctx.accounts.user_exchange);
+        require_valid_quote(&quote)?;
+        let limit = vault_token.max_daily_net_inflow;
+        record_inflow(&mut vault_token.net_flow, limit, amount)?;
+        vault_token.lifetime.record_deposit(amount);
+
+        let transfer_instruction = Transfer {
+            from: from.to_account_info(),
+            to: to.to_account_info(),
+            authority: user.to_account_info(),
+        };
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        let cpi_ctx = CpiContext::new(cpi_program, transfer_instruction);
+
+        anchor_spl::token::transfer(cpi_ctx, amount)?;
+
+        vault_token.balance = vault_token
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        require_within_vault_cap(vault_token.vault_cap, vault_token.balance)?;
+        let product_id = credit_product(ctx.accounts.product_ledger.as_deref_mut(), amount)?;
+
+        let clock = get_clock()?;
+
+        emit!(DepositEvent {
+            user: ctx.accounts.user.key(),
+            holder: to.key(),
+            amount,
+            decimals: vault_token.decimals,
+            ui_amount: ui_amount(amount, vault_token.decimals),
+            time: clock.unix_timestamp,
+            quote,
+            product_id,
+            source,
+            config_generation: master.config_generation,
+        });
+
+        Ok(())
+    }
+
+    /// This function is run by users to deposit native SOL, which is wrapped into the wSOL ATA of master PDA in the same
+    /// instruction and booked like a token deposit. The native mint has to be a supported mint of the vault.
+    pub fn deposit_wrapped(ctx: Context<DepositWrapped>, amount: u64) -> Result<()> {
+        require!(
+            !ctx.accounts.vault_config.deposits_paused,
+            Errors::DepositsPaused
+        );
+
+        let master = &mut ctx.accounts.master;
+
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+        let to = &ctx.accounts.master_ata;
+        let user = &ctx.accounts.user;
+
+        require!(vault_token.enabled, Errors::MintDisabled);
+        require_not_blocked(&ctx.accounts.user_blocked, user.key())?;
+        require_not_frozen(&ctx.accounts.user_state)?;
+        require!(
+            !vault_config.deposit_whitelist_enabled || pda_exists(&ctx.accounts.user_whitelist),
+            Errors::DepositorNotWhitelisted
+        );
+        require_deposit_size(vault_token.min_deposit, vault_token.max_deposit, amount)?;
+        record_deposit_velocity(master, vault_config, Some(vault_token), amount)?;
+        record_user_deposit(
+            ctx.accounts.user_deposits.as_deref_mut(),
+            vault_token.user_deposit_cap,
+            amount,
+        )?;
+        let source = deposit_source(&user.key(), &ctx.accounts.user_exchange);
+        let limit = vault_token.max_daily_net_inflow;
+        record_inflow(&mut vault_token.net_flow, limit, amount)?;
+        vault_token.lifetime.record_deposit(amount);
+
+        invoke(
+            &transfer(&user.key(), &to.key(), amount),
+            &[
+                user.to_account_info(),
+                to.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        anchor_spl::token::sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: to.to_account_info(),
+            },
+        ))?;
+
+        vault_token.balance = vault_token
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        require_within_vault_cap(vault_token.vault_cap, vault_token.balance)?;
+        let product_id = credit_product(ctx.accounts.product_ledger.as_deref_mut(), amount)?;
+
+        let clock = get_clock()?;
+
+        emit!(DepositEvent {
+            user: user.key(),
+            holder: to.key(),
+            amount,
+            decimals: vault_token.decimals,
+            ui_amount: ui_amount(amount, vault_token.decimals),
+            time: clock.unix_timestamp,
+            quote: None,
+            product_id,
+            source,
+            config_generation: master.config_generation,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by anyone to add `amount` of SOL to the vault (master PDA balance) without
+    /// crediting any user, e.g. for treasury top-ups. It emits a `DonationEvent` instead of a `DepositEvent`.
+    pub fn donate(ctx: Context<Donate>, amount: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &ctx.accounts.vault_config;
+        let donor = &ctx.accounts.donor;
+
+        require_rent_exempt(
+            &master.to_account_info(),
+            master
+                .get_lamports()
+                .checked_add(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?,
+        )?;
+
+        invoke(
+            &transfer(&donor.key(), &master.key(), amount),
+            &[
+                donor.to_account_info(),
+                master.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+        )?;
+
+        master.balance = master
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        require_within_vault_cap(vault_config.vault_cap, master.balance)?;
+
+        emit!(DonationEvent {
+            donor: donor.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by anyone to add `amount` of a supported token to the vault (master PDA ATA
+    /// balance) without crediting any user. It emits a `DonationEvent` instead of a `DepositEvent`.
+    pub fn donate_token(ctx: Context<DonateToken>, amount: u64) -> Result<()> {
+        let vault_token = &mut ctx.accounts.vault_token;
+        let to = &ctx.accounts.master_ata;
+        let donor = &ctx.accounts.donor;
+
+        require!(vault_token.enabled, Errors::MintDisabled);
+
+        anchor_spl::token::transfer(
+            CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.from.to_account_info(),
+                    to: to.to_account_info(),
+                    authority: donor.to_account_info(),
+                },
+            ),
+            amount,
+        )?;
+
+        vault_token.balance = vault_token
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        require_within_vault_cap(vault_token.vault_cap, vault_token.balance)?;
+
+        emit!(DonationEvent {
+            donor: donor.key(),
+            holder: to.key(),
+            amount,
+            decimals: vault_token.decimals,
+            ui_amount: ui_amount(amount, vault_token.decimals),
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to set a new operator
+    pub fn set_operator(ctx: Context<SetOperator>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let operator = &ctx.accounts.new_operator;
+
+        master.operator = operator.key();
+        master.bump_config_generation();
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to transfer admin rights to a new wallet
+    pub fn set_admin(ctx: Context<SetAdmin>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let admin = &ctx.accounts.new_admin;
+
+        master.admin = admin.key();
+        master.bump_config_generation();
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to appoint the guardian used for emergency actions
+    pub fn set_guardian(ctx: Context<SetGuardian>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let guardian = &ctx.accounts.new_guardian;
+
+        master.guardian = guardian.key();
+        master.bump_config_generation();
+        Ok(())
+    }
+
+    /// This function can be called by master.admin or master.guardian to revoke the operator and stop payouts.
+    /// The operator is replaced by a sentinel key nobody can sign for, so master.admin has to appoint a new one
+    /// before payouts are resumed with `set_withdrawals_paused`.
+    pub fn revoke_all_operators(ctx: Context<EmergencyAction>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let revoked = master.operator;
+
+        master.operator = Pubkey::default();
+        change_config(
+            master,
+            &mut ctx.accounts.vault_config,
+            None,
+            ctx.accounts.authority.key(),
+            ConfigSetting::WithdrawalsPaused(true),
+        )?;
+
+        let clock = get_clock()?;
+
+        emit!(OperatorsRevokedEvent {
+            authority: ctx.accounts.authority.key(),
+            operator: revoked,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin or master.guardian to revoke the operator without stopping payouts
+    /// or appointing a new operator. Only master.admin can appoint a new one with `set_operator`.
+    pub fn revoke_operator(ctx: Context<EmergencyAction>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let revoked = master.operator;
+
+        master.operator = Pubkey::default();
+        master.bump_config_generation();
+
+        let clock = get_clock()?;
+
+        emit!(OperatorsRevokedEvent {
+            authority: ctx.accounts.authority.key(),
+            operator: revoked,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin or master.guardian to stop deposits and payouts
+    pub fn pause(ctx: Context<EmergencyAction>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let authority = ctx.accounts.authority.key();
+
+        for setting in [
+            ConfigSetting::DepositsPaused(true),
+            ConfigSetting::WithdrawalsPaused(true),
+        ] {
+            change_config(master, vault_config, None, authority, setting)?;
+        }
+
+        emit_pause_event(authority, master, vault_config)
+    }
+
+    /// This function can be called by master.admin or master.guardian to resume deposits and payouts
+    pub fn unpause(ctx: Context<EmergencyAction>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let authority = ctx.accounts.authority.key();
+
+        for setting in [
+            ConfigSetting::DepositsPaused(false),
+            ConfigSetting::WithdrawalsPaused(false),
+        ] {
+            change_config(master, vault_config, None, authority, setting)?;
+        }
+
+        emit_pause_event(authority, master, vault_config)
+    }
+
+    /// This function can be called by master.admin or master.guardian to stop or resume deposits only
+    pub fn set_deposits_paused(ctx: Context<EmergencyAction>, paused: bool) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let authority = ctx.accounts.authority.key();
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            authority,
+            ConfigSetting::DepositsPaused(paused),
+        )?;
+
+        emit_pause_event(authority, master, vault_config)
+    }
+
+    /// This function can be called by master.admin or master.guardian to stop or resume operator payouts only
+    pub fn set_withdrawals_paused(ctx: Context<EmergencyAction>, paused: bool) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let authority = ctx.accounts.authority.key();
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            authority,
+            ConfigSetting::WithdrawalsPaused(paused),
+        )?;
+
+        emit_pause_event(authority, master, vault_config)
+    }
+
+    /// This function can be called by master.admin to configure the circuit breaker on operator payouts.
+    /// Payouts are paused automatically once more than the limit would go out within `window_secs`. A limit of 0 disables it.
+    pub fn set_circuit_breaker(
+        ctx: Context<SetCircuitBreaker>,
+        window_secs: i64,
+        outflow_limit: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let admin = ctx.accounts.admin.key();
+
+        for setting in [
+            ConfigSetting::OutflowWindowSecs(window_secs),
+            ConfigSetting::OutflowLimit(outflow_limit),
+        ] {
+            change_config(master, vault_config, None, admin, setting)?;
+        }
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to set the circuit breaker limit of `mint` payouts, 0 disables it
+    pub fn set_token_outflow_limit(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        outflow_limit: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::TokenOutflowLimit(outflow_limit),
+        )
+    }
+
+    /// This function can be called by master.admin to cap the SOL a single operator transaction pays out, so a
+    /// compromised operator key cannot move the whole vault at once. 0 disables it.
+    pub fn set_max_sol_per_payout(ctx: Context<SetExposureLimit>, cap: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::MaxSolPerPayout(cap),
+        )
+    }
+
+    /// This function can be called by master.admin to limit the SOL operator payouts send within 24 hours. Payouts
+    /// over the limit fail with `DailyWithdrawLimitExceeded` instead of tripping the circuit breaker. 0 disables it.
+    pub fn set_daily_withdraw_limit(ctx: Context<SetExposureLimit>, limit: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::DailyWithdrawLimit(limit),
+        )
+    }
+
+    /// This function can be called by master.admin to limit the `mint` operator payouts send within 24 hours like
+    /// `set_daily_withdraw_limit` does for SOL, 0 disables it
+    pub fn set_token_daily_withdraw_limit(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        limit: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::TokenDailyWithdrawLimit(limit),
+        )
+    }
+
+    /// This function can be called by master.admin to register the cold wallet of the vault and the hot balance
+    /// threshold. SOL above the threshold is reserved for the cold wallet: operator payouts and admin withdraws only
+    /// draw from the hot portion, the rest leaves through `withdraw_to_cold`. A threshold of 0 disables the split
+    pub fn set_cold_wallet(
+        ctx: Context<SetExposureLimit>,
+        cold_wallet: Pubkey,
+        hot_balance_threshold: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let admin = ctx.accounts.admin.key();
+
+        // The threshold is lowered before the wallet is cleared and raised after it is set, so no step leaves a
+        // threshold without a cold wallet.
+        let settings = if hot_balance_threshold == 0 {
+            [
+                ConfigSetting::HotBalanceThreshold(hot_balance_threshold),
+                ConfigSetting::ColdWallet(cold_wallet),
+            ]
+        } else {
+            [
+                ConfigSetting::ColdWallet(cold_wallet),
+                ConfigSetting::HotBalanceThreshold(hot_balance_threshold),
+            ]
+        };
+        for setting in settings {
+            change_config(master, vault_config, None, admin, setting)?;
+        }
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to set the lamports SOL payouts have to leave in master PDA, so an
+    /// operational buffer for ATA creation and fees stays on top of rent exemption. 0 falls back to rent exemption
+    pub fn set_min_sol_reserve(ctx: Context<SetExposureLimit>, min_sol_reserve: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::MinSolReserve(min_sol_reserve),
+        )
+    }
+
+    /// This function can be called by master.admin to set the minimum interval between two operator payouts to the
+    /// same receiver, so a scripted drain through many small sends is slowed down. 0 disables it
+    pub fn set_payout_cooldown(ctx: Context<SetExposureLimit>, cooldown_secs: i64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::PayoutCooldown(cooldown_secs),
+        )
+    }
+
+    /// This function can be called by master.admin to restrict operator payouts to operational hours, bit `h` of
+    /// `payout_hours` allowing hour `h` UTC. Payouts outside of them need master.admin as co-signer. 0 allows any hour
+    pub fn set_payout_hours(ctx: Context<SetExposureLimit>, payout_hours: u32) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::PayoutHours(payout_hours),
+        )
+    }
+
+    /// This function can be called by master.admin to cap the `mint` tokens a single operator transaction pays out,
+    /// 0 disables it
+    pub fn set_max_token_per_payout(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        cap: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::MaxTokenPerPayout(cap),
+        )
+    }
+
+    /// This function can be called by master.admin to cap how much SOL deposits may exceed outflows per day, 0 disables it
+    pub fn set_max_daily_net_inflow(ctx: Context<SetExposureLimit>, limit: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::MaxDailyNetInflow(limit),
+        )
+    }
+
+    /// This function can be called by master.admin to cap how much `mint` deposits may exceed outflows per day, 0 disables it
+    pub fn set_token_max_daily_net_inflow(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        limit: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::TokenMaxDailyNetInflow(limit),
+        )
+    }
+
+    /// This function can be called by master.admin to set the smallest SOL deposit accepted, so dust deposits which
+    /// cost more to account for than they are worth are rejected
+    pub fn set_min_deposit(ctx: Context<SetExposureLimit>, min_deposit: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::MinDeposit(min_deposit),
+        )
+    }
+
+    /// This function can be called by master.admin to set the smallest `mint` deposit accepted
+    pub fn set_token_min_deposit(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        min_deposit: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::TokenMinDeposit(min_deposit),
+        )
+    }
+
+    /// This function can be called by master.admin to set the largest SOL deposit accepted in one transaction, so
+    /// fat-finger and laundering-scale deposits are rejected on-chain. 0 disables it
+    pub fn set_max_deposit(ctx: Context<SetExposureLimit>, max_deposit: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::MaxDeposit(max_deposit),
+        )
+    }
+
+    /// This function can be called by master.admin to set the largest `mint` deposit accepted in one transaction, 0
+    /// disables it
+    pub fn set_token_max_deposit(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        max_deposit: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::TokenMaxDeposit(max_deposit),
+        )
+    }
+
+    /// This function can be called by master.admin to cap the lifetime SOL deposits of users, e.g. at the limit of the
+    /// basic KYC tier. Depositors then pass their user deposits PDA. 0 disables it
+    pub fn set_default_user_deposit_cap(ctx: Context<SetExposureLimit>, cap: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::UserDepositCap(cap),
+        )
+    }
+
+    /// This function can be called by master.admin to cap the lifetime `mint` deposits of users, 0 disables it
+    pub fn set_token_default_user_deposit_cap(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        cap: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::TokenUserDepositCap(cap),
+        )
+    }
+
+    /// This function can be called by master.admin to cap the SOL balance of the vault, e.g. to limit exposure during
+    /// the launch phase. Deposits and donations taking master.balance above it fail, 0 disables it
+    pub fn set_vault_cap(ctx: Context<SetExposureLimit>, cap: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::VaultCap(cap),
+        )
+    }
+
+    /// This function can be called by master.admin to cap the `mint` balance of the vault, 0 disables it
+    pub fn set_token_vault_cap(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        cap: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::TokenVaultCap(cap),
+        )
+    }
+
+    /// This function can be called by master.admin to reject deposits once more than `max_count` deposits of any
+    /// asset, or more than `max_volume` SOL, would arrive within `window_secs` (likely an attack or an integration
+    /// bug). 0 disables a threshold
+    pub fn set_deposit_velocity_limits(
+        ctx: Context<SetExposureLimit>,
+        window_secs: i64,
+        max_count: u64,
+        max_volume: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let admin = ctx.accounts.admin.key();
+
+        // The window is closed after the thresholds are lowered and opened before they are raised, so no step leaves
+        // a threshold without a window.
+        let window = ConfigSetting::DepositVelocityWindowSecs(window_secs);
+        let thresholds = [
+            ConfigSetting::DepositVelocityMaxCount(max_count),
+            ConfigSetting::DepositVelocityMaxVolume(max_volume),
+        ];
+        let settings = if window_secs == 0 {
+            [thresholds[0], thresholds[1], window]
+        } else {
+            [window, thresholds[0], thresholds[1]]
+        };
+        for setting in settings {
+            change_config(master, vault_config, None, admin, setting)?;
+        }
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to reject deposits once more than `max_volume` of `mint` would
+    /// arrive within the deposit velocity window of the vault, 0 disables it
+    pub fn set_token_deposit_velocity_limit(
+        ctx: Context<UpdateVaultToken>,
+        _mint: Pubkey,
+        max_volume: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        change_config(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.admin.key(),
+            ConfigSetting::TokenDepositVelocityMaxVolume(max_volume),
+        )
+    }
+
+    /// This function can be called by master.operator after a deposit of `user` has failed with
+    /// `DepositVelocityExceeded`. If a deposit of `amount` SOL, or of the mint of `vault_token` if given, still
+    /// exceeds the deposit velocity limits, deposits are paused (likely an attack or an integration bug) and a
+    /// `DepositVelocityAlertEvent` is emitted, the admin unpauses after review.
+    pub fn trip_deposit_velocity(
+        ctx: Context<TripDepositVelocity>,
+        user: Pubkey,
+        amount: u64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = ctx.accounts.vault_token.as_deref();
+
+        let (count, volume, within) =
+            next_deposit_velocity(master, vault_config, vault_token, amount)?;
+        require!(!within, Errors::DepositVelocityWithinLimits);
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.operator.key(),
+            ConfigSetting::DepositsPaused(true),
+        )?;
+
+        emit!(DepositVelocityAlertEvent {
+            user,
+            amount,
+            mint: vault_token.map(|vault_token| vault_token.mint),
+            window_count: count.amount,
+            window_volume: volume.amount,
+            max_count: vault_config.deposit_velocity_max_count,
+            max_volume: vault_token
+                .map_or(vault_config.deposit_velocity_max_volume, |vault_token| {
+                    vault_token.deposit_velocity_max_volume
+                }),
+            config_generation: master.config_generation,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to set the lifetime deposit cap of `user` in `asset` (the mint, or
+    /// the default pubkey for SOL), e.g. raise it after enhanced KYC. 0 falls back to the cap of the asset.
+    pub fn set_user_deposit_cap(
+        ctx: Context<SetUserDepositCap>,
+        user: Pubkey,
+        asset: Pubkey,
+        cap: u64,
+    ) -> Result<()> {
+        let user_deposits = &mut ctx.accounts.user_deposits;
+        user_deposits.master = ctx.accounts.master.key();
+        user_deposits.user = user;
+        user_deposits.asset = asset;
+        user_deposits.cap = cap;
+
+        emit!(UserDepositCapEvent {
+            user,
+            asset,
+            cap,
+            deposited: user_deposits.deposited,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function is run by users to create their user deposits PDA of `asset` (the mint, or the default pubkey
+    /// for SOL), which deposits of an asset with a user deposit cap take.
+    pub fn open_user_deposits(ctx: Context<OpenUserDeposits>, asset: Pubkey) -> Result<()> {
+        let user_deposits = &mut ctx.accounts.user_deposits;
+        user_deposits.master = ctx.accounts.master.key();
+        user_deposits.user = ctx.accounts.user.key();
+        user_deposits.asset = asset;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to update the label of the vault, so indexers can map its master
+    /// PDA to a product without an out-of-band list.
+    pub fn set_vault_metadata(
+        ctx: Context<SetVaultMetadata>,
+        metadata: VaultMetadata,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        master.metadata = metadata;
+
+        emit!(VaultMetadataEvent {
+            master: master.key(),
+            metadata,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to declare the accounting currency of the vault, e.g. `*b"USD"`,
+    /// and the decimals its amounts are normalized to, so figures of vaults with different base currencies add up.
+    pub fn set_accounting_currency(
+        ctx: Context<SetAccountingCurrency>,
+        currency: [u8; 3],
+        decimals: u8,
+    ) -> Result<()> {
+        require_valid_currency(&currency)?;
+
+        let master = &mut ctx.accounts.master;
+
+        master.accounting_currency = currency;
+        master.accounting_decimals = decimals;
+        master.bump_config_generation();
+
+        emit!(AccountingCurrencyEvent {
+            master: master.key(),
+            currency,
+            decimals,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to let the secp256k1 key of `eth_signer`, an Ethereum address,
+    /// sign vouchers besides the operator, so existing EVM signing infrastructure can authorize payouts. All zero
+    /// revokes it.
+    pub fn set_voucher_eth_signer(
+        ctx: Context<SetVoucherEthSigner>,
+        eth_signer: [u8; 20],
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+
+        master.voucher_eth_signer = eth_signer;
+        master.bump_config_generation();
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to set the fee of express payouts in basis points, at most the
+    /// highest fee of the program config.
+    pub fn set_express_fee_bps(ctx: Context<SetExpressFeeBps>, fee_bps: u16) -> Result<()> {
+        require!(
+            fee_bps <= ctx.accounts.program_config.max_fee_bps,
+            Errors::FeeTooHigh
+        );
+
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::ExpressFeeBps(fee_bps),
+        )
+    }
+
+    /// This function can be called by master.admin to change any tunable of the vault in `ConfigSetting` with one
+    /// instruction, passing the vault token PDA of the mint for a setting of a mint. Like the dedicated setters it
+    /// emits a `ConfigChangedEvent` with the old and the new value, so config history can be read from the event
+    /// stream alone.
+    pub fn update_config(ctx: Context<UpdateConfig>, setting: ConfigSetting) -> Result<()> {
+        if let ConfigSetting::ExpressFeeBps(fee_bps) = setting {
+            require!(
+                fee_bps <= ctx.accounts.program_config.max_fee_bps,
+                Errors::FeeTooHigh
+            );
+        }
+
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let admin = ctx.accounts.admin.key();
+
+        change_config(
+            master,
+            vault_config,
+            ctx.accounts.vault_token.as_deref_mut(),
+            admin,
+            setting,
+        )?;
+
+        if matches!(
+            setting,
+            ConfigSetting::DepositsPaused(_) | ConfigSetting::WithdrawalsPaused(_)
+        ) {
+            emit_pause_event(admin, master, vault_config)?;
+        }
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to let token payouts reimburse the operator for the rent of the
+    /// receiver ATAs they create
+    pub fn set_reimburse_ata_rent(ctx: Context<SetReimburseAtaRent>, enabled: bool) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::ReimburseAtaRent(enabled),
+        )
+    }
+
+    /// This function can be called by master.admin to set the escrow window of SOL deposits in seconds. While it is
+    /// not 0 deposits go through `deposit_escrowed` and are held for the window, 0 turns escrow off.
+    pub fn set_deposit_escrow_window(
+        ctx: Context<SetDepositEscrowWindow>,
+        window_secs: i64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::DepositEscrowWindow(window_secs),
+        )
+    }
+
+    /// This function can be called by master.admin to set the dead man period in seconds. Once neither a payout nor a
+    /// heartbeat of the operator has happened for longer, anyone can switch the vault to claim mode with
+    /// `trigger_claim_mode`. 0 turns the switch off. Setting it counts as a heartbeat.
+    pub fn set_dead_man_period(ctx: Context<SetDeadManPeriod>, period_secs: i64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::DeadManPeriod(period_secs),
+        )
+    }
+
+    /// This function can be called by master.admin to let users withdraw up to `threshold` of SOL themselves with
+    /// `self_withdraw`, once per `cooldown_secs`. A threshold of 0 turns self-service withdrawals off.
+    pub fn set_self_withdraw_limits(
+        ctx: Context<SetSelfWithdrawLimits>,
+        threshold: u64,
+        cooldown_secs: i64,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let admin = ctx.accounts.admin.key();
+
+        for setting in [
+            ConfigSetting::SelfWithdrawThreshold(threshold),
+            ConfigSetting::SelfWithdrawCooldown(cooldown_secs),
+        ] {
+            change_config(master, vault_config, None, admin, setting)?;
+        }
+
+        Ok(())
+    }
+
+    /// This function is called by master.operator regularly to show it is alive and to publish the merkle root of the
+    /// SOL balances of the users, which they claim in claim mode. Leaves are payout leaves of `CLAIM_MODE_EPOCH`.
+    pub fn heartbeat(ctx: Context<Heartbeat>, balance_root: [u8; 32]) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        require!(!master.claim_mode, Errors::ClaimModeActive);
+
+        master.heartbeat_time = get_clock()?.unix_timestamp;
+        master.balance_root = balance_root;
+        Ok(())
+    }
+
+    /// This function can be called by anyone once the operator has been silent, signing neither a heartbeat nor a
+    /// payout, for longer than the dead man period.
+    /// It stops deposits and operator payouts for good and posts the last published balance root as the payout root
+    /// of `CLAIM_MODE_EPOCH`, capped by the SOL balance, so users recover their balances with `claim`.
+    pub fn trigger_claim_mode(ctx: Context<TriggerClaimMode>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        require!(
+            vault_config.dead_man_period > 0,
+            Errors::DeadManSwitchDisabled
+        );
+        require!(
+            master.balance_root != [0; 32],
+            Errors::DeadManSwitchDisabled
+        );
+        let clock = get_clock()?;
+        let last_heartbeat = master.heartbeat_time;
+        require_operator_silent(
+            last_heartbeat,
+            vault_config.dead_man_period,
+            clock.unix_timestamp,
+        )?;
+
+        master.claim_mode = true;
+        for setting in [
+            ConfigSetting::DepositsPaused(true),
+            ConfigSetting::WithdrawalsPaused(true),
+        ] {
+            change_config(
+                master,
+                vault_config,
+                None,
+                ctx.accounts.payer.key(),
+                setting,
+            )?;
+        }
+
+        let payout_root = &mut ctx.accounts.payout_root;
+        payout_root.master = master.key();
+        payout_root.epoch = CLAIM_MODE_EPOCH;
+        payout_root.root = master.balance_root;
+        payout_root.total = master.balance;
+
+        emit!(ClaimModeEvent {
+            triggered_by: ctx.accounts.payer.key(),
+            holder: master.key(),
+            balance_root: master.balance_root,
+            total: master.balance,
+            last_heartbeat,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to restrict operator payouts to allowlisted receivers
+    pub fn set_receiver_allowlist_enabled(
+        ctx: Context<SetReceiverAllowlistEnabled>,
+        enabled: bool,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::ReceiverAllowlistEnabled(enabled),
+        )
+    }
+
+    /// This function can be called by master.admin to add a receiver to the payout allowlist
+    pub fn add_allowed_receiver(ctx: Context<AddAllowedReceiver>) -> Result<()> {
+        let allowed_receiver = &mut ctx.accounts.allowed_receiver;
+        allowed_receiver.receiver = ctx.accounts.receiver.key();
+
+        let clock = get_clock()?;
+
+        emit!(ReceiverAllowlistEvent {
+            receiver: allowed_receiver.receiver,
+            allowed: true,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to remove a receiver from the payout allowlist
+    pub fn remove_allowed_receiver(ctx: Context<RemoveAllowedReceiver>) -> Result<()> {
+        let clock = get_clock()?;
+
+        emit!(ReceiverAllowlistEvent {
+            receiver: ctx.accounts.receiver.key(),
+            allowed: false,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to exempt a trusted receiver (e.g. our treasury or a market maker)
+    /// from the per-payout cap and the daily withdraw limit of `send_withdraw` and `send_withdraw_token`
+    pub fn add_limit_exempt_receiver(ctx: Context<AddLimitExemptReceiver>) -> Result<()> {
+        let limit_exempt = &mut ctx.accounts.limit_exempt;
+        limit_exempt.receiver = ctx.accounts.receiver.key();
+
+        let clock = get_clock()?;
+
+        emit!(LimitExemptionEvent {
+            receiver: limit_exempt.receiver,
+            exempt: true,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to remove a receiver from the payout limit exemptions
+    pub fn remove_limit_exempt_receiver(ctx: Context<RemoveLimitExemptReceiver>) -> Result<()> {
+        let clock = get_clock()?;
+
+        emit!(LimitExemptionEvent {
+            receiver: ctx.accounts.receiver.key(),
+            exempt: false,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to only accept deposits from whitelisted users
+    pub fn set_deposit_whitelist_enabled(
+        ctx: Context<SetDepositWhitelistEnabled>,
+        enabled: bool,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        change_config(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.admin.key(),
+            ConfigSetting::DepositWhitelistEnabled(enabled),
+        )
+    }
+
+    /// This function can be called by master.admin or master.operator to whitelist a depositor
+    pub fn add_whitelisted_depositor(ctx: Context<AddWhitelistedDepositor>) -> Result<()> {
+        let whitelisted_depositor = &mut ctx.accounts.whitelisted_depositor;
+        whitelisted_depositor.user = ctx.accounts.user.key();
+
+        let clock = get_clock()?;
+
+        emit!(DepositWhitelistEvent {
+            user: whitelisted_depositor.user,
+            whitelisted: true,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin or master.operator to remove a depositor from the whitelist
+    pub fn remove_whitelisted_depositor(ctx: Context<RemoveWhitelistedDepositor>) -> Result<()> {
+        let clock = get_clock()?;
+
+        emit!(DepositWhitelistEvent {
+            user: ctx.accounts.user.key(),
+            whitelisted: false,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator or master.admin to freeze a user,
+    /// blocking deposits from and payouts to that wallet
+    pub fn freeze_user(ctx: Context<FreezeUser>) -> Result<()> {
+        let user_state = &mut ctx.accounts.user_state;
+        user_state.user = ctx.accounts.user.key();
+        user_state.frozen = true;
+
+        let clock = get_clock()?;
+
+        emit!(UserFrozenEvent {
+            user: user_state.user,
+            frozen: true,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator or master.admin to unfreeze a user
+    pub fn unfreeze_user(ctx: Context<UnfreezeUser>) -> Result<()> {
+        let user_state = &mut ctx.accounts.user_state;
+        user_state.frozen = false;
+
+        let clock = get_clock()?;
+
+        emit!(UserFrozenEvent {
+            user: user_state.user,
+            frozen: false,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to record a fault (e.g. a mis-payout) of an operator.
+    /// The amount is added to the operator's outstanding penalty, which is netted against future fee reimbursements.
+    pub fn record_operator_fault(
+        ctx: Context<RecordOperatorFault>,
+        amount: u64,
+        reason: String,
+    ) -> Result<()> {
+        require!(
+            reason.len() <= MAX_FAULT_REASON_LEN,
+            Errors::FaultReasonTooLong
+        );
+
+        let clock = get_clock()?;
+
+        let ledger = &mut ctx.accounts.penalty_ledger;
+        ledger.operator = ctx.accounts.operator.key();
+        ledger.record_fault(amount, clock.unix_timestamp)?;
+
+        emit!(OperatorFaultEvent {
+            operator: ledger.operator,
+            amount,
+            outstanding: ledger.outstanding,
+            reason,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to open the sub-ledger of `product_id` for SOL (`asset` is the
+    /// default pubkey) or for the token `asset`. Deposits and payouts passing the ledger are booked to the product.
+    pub fn init_product_ledger(
+        ctx: Context<InitProductLedger>,
+        product_id: u64,
+        asset: Pubkey,
+    ) -> Result<()> {
+        let product_ledger = &mut ctx.accounts.product_ledger;
+        product_ledger.master = ctx.accounts.master.key();
+        product_ledger.product_id = product_id;
+        product_ledger.asset = asset;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to plant a canary receiver. Any payout to a canary stops all payouts.
+    pub fn add_canary(ctx: Context<AddCanary>, receiver: Pubkey) -> Result<()> {
+        let canary = &mut ctx.accounts.canary;
+        canary.receiver = receiver;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to remove a canary receiver
+    pub fn remove_canary(_ctx: Context<RemoveCanary>, _receiver: Pubkey) -> Result<()> {
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to take the payout lock of a receiver before fulfilling its
+    /// requests. Fails while another operator instance holds the lock. A payout passing the lock releases it.
+    pub fn lock_receiver(ctx: Context<LockReceiver>, receiver: Pubkey) -> Result<()> {
+        let clock = get_clock()?;
+
+        let receiver_lock = &mut ctx.accounts.receiver_lock;
+        receiver_lock.master = ctx.accounts.master.key();
+        receiver_lock.receiver = receiver;
+        receiver_lock.locked_at = clock.unix_timestamp;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator or master.admin to release a payout lock without a payout
+    pub fn unlock_receiver(_ctx: Context<UnlockReceiver>, _receiver: Pubkey) -> Result<()> {
+        Ok(())
+    }
+
+    /// This function is run by users to request a payout of `amount` of SOL, or of `mint` if passed, on-chain. The
+    /// request is appended to the withdraw queue of the vault. master.operator fulfills requests in queue order by
+    /// passing them to `send_withdraw` or `send_withdraw_token`, which close them. A request can be paid for
+    /// `WITHDRAW_REQUEST_TTL`, disputed requests not until the dispute is closed.
+    pub fn request_withdraw(
+        ctx: Context<RequestWithdraw>,
+        amount: u64,
+        mint: Option<Pubkey>,
+    ) -> Result<()> {
+        require!(amount > 0, Errors::ZeroAmount);
+
+        let clock = get_clock()?;
+        let withdraw_queue = &mut ctx.accounts.withdraw_queue;
+
+        let withdraw_request = &mut ctx.accounts.withdraw_request;
+        withdraw_request.master = ctx.accounts.master.key();
+        withdraw_request.user = ctx.accounts.user.key();
+        withdraw_request.mint = mint;
+        withdraw_request.amount = amount;
+        withdraw_request.created_at = clock.unix_timestamp;
+        withdraw_request.expires_at = clock
+            .unix_timestamp
+            .checked_add(WITHDRAW_REQUEST_TTL)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        withdraw_request.sequence = withdraw_queue.tail;
+
+        withdraw_queue.tail = withdraw_queue
+            .tail
+            .checked_add(1)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+
+        emit!(WithdrawRequestedEvent {
+            request: withdraw_request.key(),
+            user: withdraw_request.user,
+            sequence: withdraw_request.sequence,
+            mint,
+            amount,
+            expires_at: withdraw_request.expires_at,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function is run by users to cancel their pending withdraw request. The request PDA is closed and its rent
+    /// goes back to the user.
+    pub fn cancel_withdraw_request(ctx: Context<CancelWithdrawRequest>) -> Result<()> {
+        let withdraw_request = &ctx.accounts.withdraw_request;
+
+        emit!(WithdrawCancelledEvent {
+            request: withdraw_request.key(),
+            user: withdraw_request.user,
+            mint: withdraw_request.mint,
+            amount: withdraw_request.amount,
+            expired: false,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by anyone to close a withdraw request which has expired unfulfilled. Its rent goes
+    /// back to the user.
+    pub fn expire_request(ctx: Context<ExpireRequest>) -> Result<()> {
+        let withdraw_request = &ctx.accounts.withdraw_request;
+        let clock = get_clock()?;
+
+        require!(
+            clock.unix_timestamp >= withdraw_request.expires_at,
+            Errors::WithdrawRequestNotExpired
+        );
+
+        emit!(WithdrawCancelledEvent {
+            request: withdraw_request.key(),
+            user: withdraw_request.user,
+            mint: withdraw_request.mint,
+            amount: withdraw_request.amount,
+            expired: true,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by anyone to move the head of the withdraw queue past a request which has been
+    /// cancelled or expired, so the next request becomes payable.
+    pub fn skip_closed_request(ctx: Context<SkipClosedRequest>) -> Result<()> {
+        let withdraw_queue = &mut ctx.accounts.withdraw_queue;
+
+        require!(
+            withdraw_queue.head < withdraw_queue.tail,
+            Errors::WithdrawQueueEmpty
+        );
+        require!(
+            !pda_exists(&ctx.accounts.head_request),
+            Errors::WithdrawRequestPending
+        );
+
+        withdraw_queue.head += 1;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator or master.admin to open (`disputed` set) or close a dispute on
+    /// a pending withdraw request. A disputed request cannot be fulfilled, so it holds the withdraw queue until the
+    /// dispute is closed, cancelled by its user or expired.
+    pub fn set_request_disputed(ctx: Context<SetRequestDisputed>, disputed: bool) -> Result<()> {
+        let withdraw_request = &mut ctx.accounts.withdraw_request;
+        withdraw_request.disputed = disputed;
+
+        emit!(WithdrawDisputeEvent {
+            request: withdraw_request.key(),
+            user: withdraw_request.user,
+            authority: ctx.accounts.authority.key(),
+            disputed,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to set the priority tier of a pending withdraw request, e.g.
+    /// for VIP users. A request with a priority above 0 can be paid ahead of the head of the withdraw queue.
+    pub fn set_request_priority(ctx: Context<SetRequestPriority>, priority: u8) -> Result<()> {
+        let withdraw_request = &mut ctx.accounts.withdraw_request;
+        withdraw_request.priority = priority;
+
+        emit!(WithdrawPriorityEvent {
+            request: withdraw_request.key(),
+            user: withdraw_request.user,
+            sequence: withdraw_request.sequence,
+            priority,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to close a payout receipt older than `RECEIPT_RETENTION` and
+    /// recover its rent. The request id of a closed receipt could be paid again, so backend ids must never be reused.
+    pub fn close_receipt(ctx: Context<CloseReceipt>) -> Result<()> {
+        let receipt = &ctx.accounts.receipt;
+
+        require!(
+            get_clock()?.unix_timestamp
+                >= receipt
+                    .paid_at
+                    .checked_add(RECEIPT_RETENTION)
+                    .map(Ok)
+                    .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?,
+            Errors::ReceiptRetained
+        );
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to block a sanctioned address from deposits and payouts
+    pub fn block_address(ctx: Context<BlockAddress>, address: Pubkey) -> Result<()> {
+        let blocked = &mut ctx.accounts.blocked;
+        blocked.address = address;
+
+        let clock = get_clock()?;
+
+        emit!(AddressBlockedEvent {
+            address,
+            blocked: true,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to lift the block of an address
+    pub fn unblock_address(_ctx: Context<UnblockAddress>, address: Pubkey) -> Result<()> {
+        let clock = get_clock()?;
+
+        emit!(AddressBlockedEvent {
+            address,
+            blocked: false,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to tag a known exchange hot wallet, deposits from it are reported
+    /// with the `Exchange` source
+    pub fn add_exchange_wallet(ctx: Context<AddExchangeWallet>, address: Pubkey) -> Result<()> {
+        let exchange_wallet = &mut ctx.accounts.exchange_wallet;
+        exchange_wallet.address = address;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to remove the exchange tag of an address
+    pub fn remove_exchange_wallet(
+        _ctx: Context<RemoveExchangeWallet>,
+        _address: Pubkey,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to create the address lookup table of the vault, owned by the lookup
+    /// table PDA. `recent_slot` is a recent slot the table address is derived from.
+    pub fn create_lookup_table(ctx: Context<CreateLookupTable>, recent_slot: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        require!(
+            master.lookup_table == Pubkey::default(),
+            Errors::LookupTableAlreadyCreated
+        );
+
+        let (instruction, lookup_table) = address_lookup_table::instruction::create_lookup_table(
+            ctx.accounts.lookup_table_authority.key(),
+            ctx.accounts.admin.key(),
+            recent_slot,
+        );
+
+        let master_key = master.key();
+        let seeds: &[&[&[u8]]] = &[&[
+            LOOKUP_TABLE_SEED.as_bytes(),
+            master_key.as_ref(),
+            &[ctx.bumps.lookup_table_authority],
+        ]];
+
+        invoke_signed(
+            &instruction,
+            &[
+                ctx.accounts.lookup_table.to_account_info(),
+                ctx.accounts.lookup_table_authority.to_account_info(),
+                ctx.accounts.admin.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            seeds,
+        )?;
+
+        master.lookup_table = lookup_table;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to add `addresses` to the address lookup table of the vault
+    pub fn extend_lookup_table(
+        ctx: Context<UpdateLookupTable>,
+        addresses: Vec<Pubkey>,
+    ) -> Result<()> {
+        let instruction = address_lookup_table::instruction::extend_lookup_table(
+            ctx.accounts.lookup_table.key(),
+            ctx.accounts.lookup_table_authority.key(),
+            Some(ctx.accounts.admin.key()),
+            addresses,
+        );
+
+        let master_key = ctx.accounts.master.key();
+        let seeds: &[&[&[u8]]] = &[&[
+            LOOKUP_TABLE_SEED.as_bytes(),
+            master_key.as_ref(),
+            &[ctx.bumps.lookup_table_authority],
+        ]];
+
+        invoke_signed(
+            &instruction,
+            &[
+                ctx.accounts.lookup_table.to_account_info(),
+                ctx.accounts.lookup_table_authority.to_account_info(),
+                ctx.accounts.admin.to_account_info(),
+                ctx.accounts.system_program.to_account_info(),
+            ],
+            seeds,
+        )?;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to deactivate the address lookup table of the vault. It can be
+    /// closed once the deactivation has cooled down.
+    pub fn deactivate_lookup_table(ctx: Context<UpdateLookupTable>) -> Result<()> {
+        let instruction = address_lookup_table::instruction::deactivate_lookup_table(
+            ctx.accounts.lookup_table.key(),
+            ctx.accounts.lookup_table_authority.key(),
+        );
+
+        let master_key = ctx.accounts.master.key();
+        let seeds: &[&[&[u8]]] = &[&[
+            LOOKUP_TABLE_SEED.as_bytes(),
+            master_key.as_ref(),
+            &[ctx.bumps.lookup_table_authority],
+        ]];
+
+        invoke_signed(
+            &instruction,
+            &[
+                ctx.accounts.lookup_table.to_account_info(),
+                ctx.accounts.lookup_table_authority.to_account_info(),
+            ],
+            seeds,
+        )?;
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to close the deactivated address lookup table of the vault, the
+    /// rent goes back to the admin and a new table can be created afterwards.
+    pub fn close_lookup_table(ctx: Context<UpdateLookupTable>) -> Result<()> {
+        let instruction = address_lookup_table::instruction::close_lookup_table(
+            ctx.accounts.lookup_table.key(),
+            ctx.accounts.lookup_table_authority.key(),
+            ctx.accounts.admin.key(),
+        );
+
+        let master_key = ctx.accounts.master.key();
+        let seeds: &[&[&[u8]]] = &[&[
+            LOOKUP_TABLE_SEED.as_bytes(),
+            master_key.as_ref(),
+            &[ctx.bumps.lookup_table_authority],
+        ]];
+
+        invoke_signed(
+            &instruction,
+            &[
+                ctx.accounts.lookup_table.to_account_info(),
+                ctx.accounts.lookup_table_authority.to_account_info(),
+                ctx.accounts.admin.to_account_info(),
+            ],
+            seeds,
+        )?;
+
+        ctx.accounts.master.lookup_table = Pubkey::default();
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to withdraw SOL to their wallet. Only the hot portion of the
+    /// balance, below vault_config.hot_balance_threshold, can be withdrawn, and nothing once claim mode is triggered.
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &ctx.accounts.vault_config;
+        let admin = &mut ctx.accounts.admin;
+
+        require!(!master.claim_mode, Errors::ClaimModeActive);
+        draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+        require!(master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        admin.add_lamports(amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut master.net_flow, amount)?;
+        master.lifetime.record_withdrawal(amount);
+
+        let clock = get_clock()?;
+
+        emit!(AdminWithdrawEvent {
+            user: admin.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to withdraw any amount of SOL to the registered cold wallet,
+    /// including the balance above the hot balance threshold which is reserved for it
+    pub fn withdraw_to_cold(ctx: Context<WithdrawToCold>, amount: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &ctx.accounts.vault_config;
+        let cold_wallet = &mut ctx.accounts.cold_wallet;
+
+        require!(!master.claim_mode, Errors::ClaimModeActive);
+        require!(master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        cold_wallet.add_lamports(amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        master.cold_reserve = master.cold_reserve.saturating_sub(amount);
+        record_outflow(&mut master.net_flow, amount)?;
+        master.lifetime.record_withdrawal(amount);
+
+        let clock = get_clock()?;
+
+        emit!(AdminWithdrawEvent {
+            user: cold_wallet.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by anyone, e.g. a crank or the operator, to move the SOL reserved for the cold
+    /// wallet, the balance above the hot balance threshold, to the registered cold wallet, so excess float does not sit
+    /// behind the hot operator key
+    pub fn sweep_to_cold(ctx: Context<SweepToCold>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &ctx.accounts.vault_config;
+        let cold_wallet = &mut ctx.accounts.cold_wallet;
+
+        require!(!master.claim_mode, Errors::ClaimModeActive);
+        require!(
+            vault_config.hot_balance_threshold > 0,
+            Errors::HotBalanceSplitDisabled
+        );
+        let amount = reserve_cold_balance(master, vault_config.hot_balance_threshold);
+        require!(amount > 0, Errors::NothingToSweep);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        cold_wallet.add_lamports(amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        master.cold_reserve = 0;
+        record_outflow(&mut master.net_flow, amount)?;
+        master.lifetime.record_withdrawal(amount);
+
+        emit!(SweepToColdEvent {
+            master: master.key(),
+            cold_wallet: cold_wallet.key(),
+            amount,
+            balance: master.balance,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to withdraw any amount of a supported token to their wallet
+    pub fn withdraw_token(ctx: Context<WithdrawToken>, amount: u64) -> Result<()> {
+        let master = &ctx.accounts.master;
+        let vault_token = &mut ctx.accounts.vault_token;
+        let admin = &mut ctx.accounts.admin_ata;
+        let from = &mut ctx.accounts.master_ata;
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+
+        let vault_id = master.vault_id.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &vault_id, &[ctx.bumps.master]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            cpi_program,
+            Transfer {
+                from: from.to_account_info(),
+                to: admin.to_account_info(),
+                authority: master.to_account_info(),
+            },
+            seeds,
+        );
+
+        anchor_spl::token::transfer(cpi_ctx, amount)?;
+
+        vault_token.balance = vault_token
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut vault_token.net_flow, amount)?;
+        vault_token.lifetime.record_withdrawal(amount);
+
+        let clock = get_clock()?;
+
+        emit!(AdminWithdrawEvent {
+            user: admin.key(),
+            holder: from.key(),
+            amount,
+            decimals: vault_token.decimals,
+            ui_amount: ui_amount(amount, vault_token.decimals),
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.admin to move the full balance of a token account of master PDA to his
+    /// wallet, for tokens sent to the vault by mistake. Supported mints of the vault cannot be rescued.
+    pub fn rescue_token(ctx: Context<RescueToken>, mint: Pubkey) -> Result<()> {
+        require!(
+            !pda_exists(&ctx.accounts.vault_token),
+            Errors::MintSupported
+        );
+
+        let master = &ctx.accounts.master;
+        let from = &ctx.accounts.token_account;
+        let amount = from.amount;
+
+        let vault_id = master.vault_id.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &vault_id, &[ctx.bumps.master]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: from.to_account_info(),
+                to: ctx.accounts.admin_ata.to_account_info(),
+                authority: master.to_account_info(),
+            },
+            seeds,
+        );
+
+        anchor_spl::token::transfer(cpi_ctx, amount)?;
+
+        emit!(TokenRescuedEvent {
+            mint,
+            token_account: from.key(),
+            amount,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by an admin of both vaults to move SOL from one master PDA to another. Like
+    /// `withdraw` it only draws from the hot portion of the source balance and is stopped by claim mode of either
+    /// vault, and the vault cap of the destination applies.
+    pub fn transfer_between_vaults(ctx: Context<TransferBetweenVaults>, amount: u64) -> Result<()> {
+        let from_master = &mut ctx.accounts.from_master;
+        let to_master = &mut ctx.accounts.to_master;
+
+        require!(
+            !from_master.claim_mode && !to_master.claim_mode,
+            Errors::ClaimModeActive
+        );
+        draw_hot_balance(
+            from_master,
+            ctx.accounts.from_vault_config.hot_balance_threshold,
+            amount,
+        )?;
+        require!(from_master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &from_master.to_account_info(),
+            ctx.accounts.from_vault_config.min_sol_reserve,
+            from_master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        from_master.sub_lamports(amount)?;
+        to_master.add_lamports(amount)?;
+
+        from_master.balance = from_master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        to_master.balance = to_master
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        require_within_vault_cap(ctx.accounts.to_vault_config.vault_cap, to_master.balance)?;
+        record_outflow(&mut from_master.net_flow, amount)?;
+        from_master.lifetime.record_withdrawal(amount);
+        to_master.lifetime.record_deposit(amount);
+
+        emit!(VaultTransferEvent {
+            from_vault: from_master.vault_id,
+            to_vault: to_master.vault_id,
+            mint: None,
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by an admin of both vaults to move a supported token from one master ATA to another.
+    /// It is stopped by claim mode of either vault, and the vault cap of the mint in the destination applies.
+    pub fn transfer_token_between_vaults(
+        ctx: Context<TransferTokenBetweenVaults>,
+        amount: u64,
+    ) -> Result<()> {
+        let from_master = &ctx.accounts.from_master;
+        let from_vault_token = &mut ctx.accounts.from_vault_token;
+        let to_vault_token = &mut ctx.accounts.to_vault_token;
+
+        require!(
+            !from_master.claim_mode && !ctx.accounts.to_master.claim_mode,
+            Errors::ClaimModeActive
+        );
+
+        let vault_id = from_master.vault_id.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &vault_id, &[ctx.bumps.from_master]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.from_master_ata.to_account_info(),
+                to: ctx.accounts.to_master_ata.to_account_info(),
+                authority: from_master.to_account_info(),
+            },
+            seeds,
+        );
+
+        anchor_spl::token::transfer(cpi_ctx, amount)?;
+
+        from_vault_token.balance = from_vault_token
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        to_vault_token.balance = to_vault_token
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        require_within_vault_cap(to_vault_token.vault_cap, to_vault_token.balance)?;
+        record_outflow(&mut from_vault_token.net_flow, amount)?;
+        from_vault_token.lifetime.record_withdrawal(amount);
+        to_vault_token.lifetime.record_deposit(amount);
+
+        emit!(VaultTransferEvent {
+            from_vault: from_master.vault_id,
+            to_vault: ctx.accounts.to_master.vault_id,
+            mint: Some(from_vault_token.mint),
+            amount,
+            decimals: from_vault_token.decimals,
+            ui_amount: ui_amount(amount, from_vault_token.decimals),
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator or master.admin to wrap `amount` of the vault's SOL into the
+    /// wSOL ATA of master PDA. The native mint has to be a supported mint of the vault. The SOL reserved for the cold
+    /// wallet cannot be wrapped.
+    pub fn wrap_sol(ctx: Context<WrapSol>, amount: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+        require!(master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        ctx.accounts.master_ata.add_lamports(amount)?;
+
+        anchor_spl::token::sync_native(CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            SyncNative {
+                account: ctx.accounts.master_ata.to_account_info(),
+            },
+        ))?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        vault_token.balance = vault_token
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+
+        emit!(SolWrapEvent {
+            master: master.key(),
+            amount,
+            wrapped: true,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator or master.admin to unwrap `amount` of the vault's wSOL back into
+    /// the SOL balance of master PDA, e.g. before a SOL payout. The wSOL goes through a temporary account whose rent
+    /// is paid by and returned to the caller.
+    pub fn unwrap_sol(ctx: Context<UnwrapSol>, amount: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_token = &mut ctx.accounts.vault_token;
+        let unwrap_account = &ctx.accounts.unwrap_account;
+
+        let rent = unwrap_account.get_lamports();
+
+        let vault_id = master.vault_id.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &vault_id, &[ctx.bumps.master]]];
+
+        anchor_spl::token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.master_ata.to_account_info(),
+                    to: unwrap_account.to_account_info(),
+                    authority: master.to_account_info(),
+                },
+                seeds,
+            ),
+            amount,
+        )?;
+
+        anchor_spl::token::close_account(CpiContext::new_with_signer(
+            ctx.accounts.token_program.to_account_info(),
+            CloseAccount {
+                account: unwrap_account.to_account_info(),
+                destination: master.to_account_info(),
+                authority: master.to_account_info(),
+            },
+            seeds,
+        ))?;
+
+        master.sub_lamports(rent)?;
+        ctx.accounts.authority.add_lamports(rent)?;
+
+        vault_token.balance = vault_token
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        master.balance = master
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+
+        emit!(SolWrapEvent {
+            master: master.key(),
+            amount,
+            wrapped: false,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator or master.admin to credit lamports sent directly to master PDA
+    /// (airdrops, plain transfers) to the tracked balance. Everything above rent and the tracked balance is untracked.
+    pub fn sweep_untracked_sol(ctx: Context<SweepUntrackedSol>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+
+        let rent = get_rent()?.minimum_balance(master.to_account_info().data_len());
+        let amount = master
+            .get_lamports()
+            .saturating_sub(rent)
+            .saturating_sub(master.balance);
+        require!(amount > 0, Errors::NothingToSweep);
+
+        master.balance = master
+            .balance
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+
+        emit!(SweepEvent {
+            master: master.key(),
+            amount,
+            balance: master.balance,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by anyone to emit the lifetime statistics of the vault's SOL, or of the mint of
+    /// `vault_token` if passed, so reconciliation can compare them per asset.
+    pub fn emit_stats(ctx: Context<EmitStats>) -> Result<()> {
+        let master = &ctx.accounts.master;
+
+        let (mint, balance, lifetime) = match &ctx.accounts.vault_token {
+            Some(vault_token) => (
+                Some(vault_token.mint),
+                vault_token.balance,
+                vault_token.lifetime,
+            ),
+            None => (None, master.balance, master.lifetime),
+        };
+
+        emit!(StatsEvent {
+            master: master.key(),
+            mint,
+            balance,
+            lifetime,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to send withdraw SOL amount to user wallet.
+    /// With a backend `request_id` the payout creates the receipt PDA of that id, so a retried payout fails instead of
+    /// paying twice. An `express` payout retains the express fee of the vault from `amount` and pays the rest.
+    /// A `memo`, e.g. the destination tag an exchange requires, is posted with the SPL Memo program. `reference`, e.g.
+    /// the backend's payout id, is emitted in the withdraw event so reconciliation matches on it.
+    /// Payouts are limited by the per-payout cap, the daily withdraw limit and the circuit breaker, need master.admin
+    /// as co-signer outside of the payout hours, respect the payout cooldown of the receiver and only draw from the hot
+    /// portion of the balance, leaving master PDA rent-exempt or holding vault_config.min_sol_reserve.
+    /// Returns the config generation of the vault, so the operator can tell whether its cached settings are stale.
+    pub fn send_withdraw(
+        ctx: Context<SendWithdraw>,
+        amount: u64,
+        request_id: Option<u64>,
+        express: bool,
+        memo: Option<String>,
+        reference: Option<[u8; 32]>,
+    ) -> Result<u64> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let limit_exempt = ctx.accounts.limit_exempt.is_some();
+
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require_payout_hours(
+            vault_config.payout_hours,
+            get_clock()?.unix_timestamp,
+            ctx.accounts.admin_cosigner.is_some(),
+        )?;
+        if !limit_exempt {
+            require_within_payout_cap(vault_config.max_sol_per_payout, amount)?;
+        }
+        if trip_canary(
+            master,
+            vault_config,
+            &ctx.accounts.receiver_canary,
+            ctx.accounts.receiver.key(),
+            ctx.accounts.operator.key(),
+            amount,
+        )? {
+            discard_receipt(
+                ctx.accounts.receipt.as_ref(),
+                ctx.accounts.operator.to_account_info(),
+            )?;
+            return Ok(master.config_generation);
+        }
+        require!(
+            !vault_config.receiver_allowlist_enabled
+                || pda_exists(&ctx.accounts.receiver_allowlist),
+            Errors::ReceiverNotAllowlisted
+        );
+        require_not_blocked(&ctx.accounts.receiver_blocked, ctx.accounts.receiver.key())?;
+        require_not_frozen(&ctx.accounts.receiver_state)?;
+        record_receiver_payout(
+            ctx.accounts.receiver_cooldown.as_deref_mut(),
+            vault_config.payout_cooldown,
+            ctx.accounts.receiver.key(),
+            get_clock()?.unix_timestamp,
+        )?;
+
+        if trip_circuit_breaker(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.operator.key(),
+            amount,
+        )? {
+            discard_receipt(
+                ctx.accounts.receipt.as_ref(),
+                ctx.accounts.operator.to_account_info(),
+            )?;
+            return Ok(master.config_generation);
+        }
+        if !limit_exempt {
+            record_daily_withdrawal(master, vault_config, None, amount)?;
+        }
+        draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+
+        let fee = express_fee(
+            amount,
+            express,
+            vault_config.express_fee_bps,
+            ctx.accounts.program_config.max_fee_bps,
+        )?;
+        let net_amount = amount - fee;
+
+        let receiver = &mut ctx.accounts.receiver;
+
+        let clock = get_clock()?;
+        master.last_withdraw_time = clock.unix_timestamp;
+        master.heartbeat_time = clock.unix_timestamp;
+
+        require!(master.balance >= net_amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(net_amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(net_amount)?;
+        receiver.add_lamports(net_amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(net_amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut master.net_flow, net_amount)?;
+        master.lifetime.record_withdrawal(net_amount);
+
+        let product_id = debit_product(ctx.accounts.product_ledger.as_deref_mut(), net_amount)?;
+        write_receipt(
+            ctx.accounts.receipt.as_deref_mut(),
+            master.key(),
+            request_id,
+            receiver.key(),
+            None,
+            net_amount,
+            &clock,
+        )?;
+        let request = fulfill_request(
+            ctx.accounts.withdraw_request.as_ref(),
+            ctx.accounts.withdraw_queue.as_mut(),
+            amount,
+            receiver.to_account_info(),
+        )?;
+
+        post_memo(ctx.accounts.memo_program.as_ref(), &memo)?;
+
+        emit!(WithdrawEvent {
+            user: receiver.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+            product_id,
+            request,
+            fee,
+            net_amount,
+            memo,
+            reference,
+            batch: None,
+            config_generation: master.config_generation,
+        });
+
+        Ok(master.config_generation)
+    }
+
+    /// This function can be called by master.operator to commit to an upcoming payout batch before executing it.
+    /// `batch_hash` is the hash of the approved plan, `total_amount` caps what batch payouts referencing it pay out,
+    /// of the mint passed or of SOL. Batch payouts referencing the batch name its hash in their withdraw events.
+    pub fn announce_batch(
+        ctx: Context<AnnounceBatch>,
+        batch_hash: [u8; 32],
+        total_amount: u64,
+    ) -> Result<()> {
+        let clock = get_clock()?;
+        let mint = ctx.accounts.mint.as_ref().map(|mint| mint.key());
+
+        let announced_batch = &mut ctx.accounts.announced_batch;
+        announced_batch.master = ctx.accounts.master.key();
+        announced_batch.batch_hash = batch_hash;
+        announced_batch.mint = mint;
+        announced_batch.total_amount = total_amount;
+        announced_batch.announced_at = clock.unix_timestamp;
+
+        emit!(BatchAnnouncedEvent {
+            batch: announced_batch.key(),
+            batch_hash,
+            mint,
+            total_amount,
+            time: clock.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to send SOL to several users in one transaction, `amounts[i]`
+    /// to the i-th receiver. `remaining_accounts` holds for each receiver, in order, its wallet (writable) and its
+    /// receiver allowlist, blocked, user state and canary PDAs. Every payout is checked like by `send_withdraw` and
+    /// emits its own `WithdrawEvent`, any failing payout fails the whole batch. A tripped canary or circuit breaker
+    /// stops the batch, receivers before it are paid. Batches are rejected while the vault has a payout cooldown.
+    /// Returns the config generation of the vault like `send_withdraw`.
+    pub fn send_withdraw_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, SendWithdrawBatch<'info>>,
+        amounts: Vec<u64>,
+    ) -> Result<u64> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let operator = ctx.accounts.operator.key();
+
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require_payout_hours(
+            vault_config.payout_hours,
+            get_clock()?.unix_timestamp,
+            ctx.accounts.admin_cosigner.is_some(),
+        )?;
+        require_within_payout_cap(vault_config.max_sol_per_payout, batch_total(&amounts)?)?;
+        // Batches cannot pass the receiver cooldown PDAs, so they are rejected while the vault has a payout cooldown.
+        require!(
+            vault_config.payout_cooldown == 0,
+            Errors::ReceiverCooldownMissing
+        );
+        require!(
+            ctx.remaining_accounts.len() == amounts.len() * BATCH_ACCOUNTS_PER_PAYOUT,
+            Errors::BatchAccountsMismatch
+        );
+
+        let clock = get_clock()?;
+        master.last_withdraw_time = clock.unix_timestamp;
+        master.heartbeat_time = clock.unix_timestamp;
+
+        for (accounts, &amount) in ctx
+            .remaining_accounts
+            .chunks(BATCH_ACCOUNTS_PER_PAYOUT)
+            .zip(amounts.iter())
+        {
+            let [receiver, receiver_allowlist, receiver_blocked, receiver_state, receiver_canary] =
+                accounts
+            else {
+                return err!(Errors::BatchAccountsMismatch);
+            };
+            require!(
+                receiver.is_writable && receiver.owner == &System::id(),
+                Errors::BatchAccountsMismatch
+            );
+            let master_key = master.key();
+            require_receiver_pda(
+                receiver_allowlist,
+                RECEIVER_ALLOWLIST_SEED,
+                &master_key,
+                receiver.key,
+            )?;
+            require_receiver_pda(receiver_blocked, BLOCKED_SEED, &master_key, receiver.key)?;
+            require_receiver_pda(receiver_state, USER_SEED, &master_key, receiver.key)?;
+            require_receiver_pda(receiver_canary, CANARY_SEED, &master_key, receiver.key)?;
+
+            if trip_canary(
+                master,
+                vault_config,
+                receiver_canary,
+                receiver.key(),
+                operator,
+                amount,
+            )? {
+                return Ok(master.config_generation);
+            }
+            require!(
+                !vault_config.receiver_allowlist_enabled || pda_exists(receiver_allowlist),
+                Errors::ReceiverNotAllowlisted
+            );
+            require_not_blocked(receiver_blocked, receiver.key())?;
+            require_not_frozen(receiver_state)?;
+
+            if trip_circuit_breaker(master, vault_config, None, operator, amount)? {
+                return Ok(master.config_generation);
+            }
+            record_daily_withdrawal(master, vault_config, None, amount)?;
+            draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+            let batch = record_batch_payout(ctx.accounts.announced_batch.as_deref_mut(), amount)?;
+
+            require!(master.balance >= amount, Errors::NotEnoughBalance);
+            require_sol_reserve(
+                &master.to_account_info(),
+                vault_config.min_sol_reserve,
+                master
+                    .get_lamports()
+                    .checked_sub(amount)
+                    .map(Ok)
+                    .unwrap_or(Err(Errors::NotEnoughBalance))?,
+            )?;
+
+            master.sub_lamports(amount)?;
+            receiver.add_lamports(amount)?;
+
+            master.balance = master
+                .balance
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+            record_outflow(&mut master.net_flow, amount)?;
+            master.lifetime.record_withdrawal(amount);
+
+            emit!(WithdrawEvent {
+                user: receiver.key(),
+                holder: master.key(),
+                amount,
+                decimals: SOL_DECIMALS,
+                ui_amount: ui_amount(amount, SOL_DECIMALS),
+                time: clock.unix_timestamp,
+                product_id: None,
+                request: None,
+                fee: 0,
+                net_amount: amount,
+                memo: None,
+                reference: None,
+                batch,
+                config_generation: master.config_generation,
+            });
+        }
+
+        Ok(master.config_generation)
+    }
+
+    /// This function can be called by master.operator to send withdraw amount of a supported token to user wallet.
+    /// With a backend `request_id` the payout creates the receipt PDA of that id, so a retried payout fails instead of
+    /// paying twice. An `express` payout retains the express fee of the vault, a `memo` is posted and `reference` is
+    /// emitted like by `send_withdraw`.
+    /// Creates the receiver's ATA if needed, paid by the operator. If vault_config.reimburse_ata_rent is set the rent is
+    /// paid back to the operator from the SOL balance, less its outstanding penalty. The receiver may also pass any
+    /// other token account of the mint it owns, since exchanges and multisigs often use non-associated accounts.
+    /// Returns the config generation of the vault like `send_withdraw`.
+    pub fn send_withdraw_token(
+        ctx: Context<SendWithdrawToken>,
+        amount: u64,
+        request_id: Option<u64>,
+        express: bool,
+        memo: Option<String>,
+        reference: Option<[u8; 32]>,
+    ) -> Result<u64> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require_payout_hours(
+            vault_config.payout_hours,
+            get_clock()?.unix_timestamp,
+            ctx.accounts.admin_cosigner.is_some(),
+        )?;
+        if ctx.accounts.limit_exempt.is_none() {
+            require_within_payout_cap(vault_token.max_token_per_payout, amount)?;
+        }
+        if trip_canary(
+            master,
+            vault_config,
+            &ctx.accounts.receiver_canary,
+            ctx.accounts.receiver.key(),
+            ctx.accounts.operator.key(),
+            amount,
+        )? {
+            discard_receipt(
+                ctx.accounts.receipt.as_ref(),
+                ctx.accounts.operator.to_account_info(),
+            )?;
+            return Ok(master.config_generation);
+        }
+        require!(
+            !vault_config.receiver_allowlist_enabled
+                || pda_exists(&ctx.accounts.receiver_allowlist),
+            Errors::ReceiverNotAllowlisted
+        );
+        require_not_blocked(&ctx.accounts.receiver_blocked, ctx.accounts.receiver.key())?;
+        require_not_frozen(&ctx.accounts.receiver_state)?;
+        record_receiver_payout(
+            ctx.accounts.receiver_cooldown.as_deref_mut(),
+            vault_config.payout_cooldown,
+            ctx.accounts.receiver.key(),
+            get_clock()?.unix_timestamp,
+        )?;
+
+        if trip_circuit_breaker(
+            master,
+            vault_config,
+            Some(vault_token),
+            ctx.accounts.operator.key(),
+            amount,
+        )? {
+            discard_receipt(
+                ctx.accounts.receipt.as_ref(),
+                ctx.accounts.operator.to_account_info(),
+            )?;
+            return Ok(master.config_generation);
+        }
+        if ctx.accounts.limit_exempt.is_none() {
+            record_daily_withdrawal(master, vault_config, Some(vault_token), amount)?;
+        }
+
+        let fee = express_fee(
+            amount,
+            express,
+            vault_config.express_fee_bps,
+            ctx.accounts.program_config.max_fee_bps,
+        )?;
+        let net_amount = amount - fee;
+
+        let receiver = &mut ctx.accounts.receiver_ata;
+        let from = &mut ctx.accounts.master_ata;
+
+        let clock = get_clock()?;
+        master.last_withdraw_time = clock.unix_timestamp;
+        master.heartbeat_time = clock.unix_timestamp;
+
+        if receiver.data_is_empty() {
+            require_keys_eq!(
+                receiver.key(),
+                anchor_spl::associated_token::get_associated_token_address(
+                    &ctx.accounts.receiver.key(),
+                    &vault_token.mint
+                ),
+                Errors::ReceiverTokenAccountMismatch
+            );
+            anchor_spl::associated_token::create(CpiContext::new(
+                ctx.accounts.associated_token_program.to_account_info(),
+                anchor_spl::associated_token::Create {
+                    payer: ctx.accounts.operator.to_account_info(),
+                    associated_token: receiver.to_account_info(),
+                    authority: ctx.accounts.receiver.to_account_info(),
+                    mint: ctx.accounts.token_mint.to_account_info(),
+                    system_program: ctx.accounts.system_program.to_account_info(),
+                    token_program: ctx.accounts.token_program.to_account_info(),
+                },
+            ))?;
+            let rent = receiver.lamports();
+
+            let reimbursed = if vault_config.reimburse_ata_rent {
+                let reimbursed = net_penalty(&ctx.accounts.penalty_ledger, rent)?;
+
+                require!(master.balance >= reimbursed, Errors::NotEnoughBalance);
+                require_sol_reserve(
+                    &master.to_account_info(),
+                    vault_config.min_sol_reserve,
+                    master
+                        .get_lamports()
+                        .checked_sub(reimbursed)
+                        .map(Ok)
+                        .unwrap_or(Err(Errors::NotEnoughBalance))?,
+                )?;
+
+                master.sub_lamports(reimbursed)?;
+                ctx.accounts.operator.add_lamports(reimbursed)?;
+
+                master.balance = master
+                    .balance
+                    .checked_sub(reimbursed)
+                    .map(Ok)
+                    .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+                record_outflow(&mut master.net_flow, reimbursed)?;
+                reimbursed
+            } else {
+                0
+            };
+
+            emit!(ReceiverAtaCreatedEvent {
+                receiver: ctx.accounts.receiver.key(),
+                ata: receiver.key(),
+                mint: vault_token.mint,
+                rent,
+                reimbursed,
+                time: clock.unix_timestamp,
+            });
+        } else {
+            load_receiver_token_account(receiver, &ctx.accounts.receiver.key(), &vault_token.mint)?;
+        }
+
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+
+        let vault_id = master.vault_id.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &vault_id, &[ctx.bumps.master]]];
+
+        let cpi_ctx = CpiContext::new_with_signer(
+            cpi_program,
+            Transfer {
+                from: from.to_account_info(),
+                to: receiver.to_account_info(),
+                authority: master.to_account_info(),
+            },
+            seeds,
+        );
+
+        anchor_spl::token::transfer(cpi_ctx, net_amount)?;
+
+        vault_token.balance = vault_token
+            .balance
+            .checked_sub(net_amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut vault_token.net_flow, net_amount)?;
+        vault_token.lifetime.record_withdrawal(net_amount);
+
+        let product_id = debit_product(ctx.accounts.product_ledger.as_deref_mut(), net_amount)?;
+        write_receipt(
+            ctx.accounts.receipt.as_deref_mut(),
+            master.key(),
+            request_id,
+            ctx.accounts.receiver.key(),
+            Some(vault_token.mint),
+            net_amount,
+            &clock,
+        )?;
+        let request = fulfill_request(
+            ctx.accounts.withdraw_request.as_ref(),
+            ctx.accounts.withdraw_queue.as_mut(),
+            amount,
+            ctx.accounts.receiver.to_account_info(),
+        )?;
+
+        post_memo(ctx.accounts.memo_program.as_ref(), &memo)?;
+
+        emit!(WithdrawEvent {
+            user: receiver.key(),
+            holder: from.key(),
+            amount,
+            decimals: vault_token.decimals,
+            ui_amount: ui_amount(amount, vault_token.decimals),
+            time: clock.unix_timestamp,
+            product_id,
+            request,
+            fee,
+            net_amount,
+            memo,
+            reference,
+            batch: None,
+            config_generation: master.config_generation,
+        });
+
+        Ok(master.config_generation)
+    }
+
+    /// This function can be called by master.operator to send a supported token to several users in one transaction,
+    /// `amounts[i]` to the i-th receiver. `remaining_accounts` holds for each receiver, in order, its wallet, its token
+    /// account of the mint (writable) and its receiver allowlist, blocked, user state and canary PDAs. Every payout is checked
+    /// like by `send_withdraw_token` and emits its own `WithdrawEvent`. With `skip_on_error` a payout failing its
+    /// checks is skipped with a `BatchPayoutSkippedEvent`, otherwise it fails the whole batch. A tripped canary or
+    /// circuit breaker stops the batch, receivers before it are paid. Batches are rejected while the vault has a payout
+    /// cooldown.
+    /// Returns the config generation of the vault like `send_withdraw`.
+    pub fn send_withdraw_token_batch<'info>(
+        ctx: Context<'_, '_, 'info, 'info, SendWithdrawTokenBatch<'info>>,
+        amounts: Vec<u64>,
+        skip_on_error: bool,
+    ) -> Result<u64> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let vault_token = &mut ctx.accounts.vault_token;
+        let mut announced_batch = ctx.accounts.announced_batch.as_deref_mut();
+        let operator = ctx.accounts.operator.key();
+
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require_payout_hours(
+            vault_config.payout_hours,
+            get_clock()?.unix_timestamp,
+            ctx.accounts.admin_cosigner.is_some(),
+        )?;
+        require_within_payout_cap(vault_token.max_token_per_payout, batch_total(&amounts)?)?;
+        // Batches cannot pass the receiver cooldown PDAs, so they are rejected while the vault has a payout cooldown.
+        require!(
+            vault_config.payout_cooldown == 0,
+            Errors::ReceiverCooldownMissing
+        );
+        require!(
+            ctx.remaining_accounts.len() == amounts.len() * TOKEN_BATCH_ACCOUNTS_PER_PAYOUT,
+            Errors::BatchAccountsMismatch
+        );
+
+        let clock = get_clock()?;
+        master.last_withdraw_time = clock.unix_timestamp;
+        master.heartbeat_time = clock.unix_timestamp;
+
+        let vault_id = master.vault_id.to_le_bytes();
+        let seeds: &[&[&[u8]]] = &[&[MASTER_SEED.as_bytes(), &vault_id, &[ctx.bumps.master]]];
+
+        for (accounts, &amount) in ctx
+            .remaining_accounts
+            .chunks(TOKEN_BATCH_ACCOUNTS_PER_PAYOUT)
+            .zip(amounts.iter())
+        {
+            let [receiver, receiver_ata, receiver_allowlist, receiver_blocked, receiver_state, receiver_canary] =
+                accounts
+            else {
+                return err!(Errors::BatchAccountsMismatch);
+            };
+
+            // Ok(true) if a tripped canary or circuit breaker stops the batch.
+            let mut batch = None;
+            let checked = (|| -> Result<bool> {
+                let master_key = master.key();
+                require_receiver_pda(
+                    receiver_allowlist,
+                    RECEIVER_ALLOWLIST_SEED,
+                    &master_key,
+                    receiver.key,
+                )?;
+                require_receiver_pda(receiver_blocked, BLOCKED_SEED, &master_key, receiver.key)?;
+                require_receiver_pda(receiver_state, USER_SEED, &master_key, receiver.key)?;
+                require_receiver_pda(receiver_canary, CANARY_SEED, &master_key, receiver.key)?;
+                require!(receiver_ata.is_writable, Errors::BatchAccountsMismatch);
+                let token_account =
+                    load_receiver_token_account(receiver_ata, receiver.key, &vault_token.mint)?;
+                require!(!token_account.is_frozen(), Errors::BatchAccountsMismatch);
+
+                if trip_canary(
+                    master,
+                    vault_config,
+                    receiver_canary,
+                    receiver.key(),
+                    operator,
+                    amount,
+                )? {
+                    return Ok(true);
+                }
+                require!(
+                    !vault_config.receiver_allowlist_enabled || pda_exists(receiver_allowlist),
+                    Errors::ReceiverNotAllowlisted
+                );
+                require_not_blocked(receiver_blocked, receiver.key())?;
+                require_not_frozen(receiver_state)?;
+                require!(vault_token.balance >= amount, Errors::NotEnoughBalance);
+
+                // The batch is booked on a copy first, so a payout over the announced amount is skipped before the
+                // circuit breaker counts it.
+                let mut next_batch = announced_batch.as_deref().cloned();
+                batch = record_batch_payout(next_batch.as_mut(), amount)?;
+                // Likewise the daily window is restored if the circuit breaker stops the batch.
+                let daily_outflow = vault_token.daily_outflow;
+                record_daily_withdrawal(master, vault_config, Some(vault_token), amount)?;
+
+                if trip_circuit_breaker(master, vault_config, Some(vault_token), operator, amount)?
+                {
+                    vault_token.daily_outflow = daily_outflow;
+                    return Ok(true);
+                }
+                if let (Some(announced_batch), Some(next_batch)) =
+                    (announced_batch.as_deref_mut(), next_batch)
+                {
+                    *announced_batch = next_batch;
+                }
+                Ok(false)
+            })();
+            match checked {
+                Ok(true) => return Ok(master.config_generation),
+                Ok(false) => {}
+                Err(error) if skip_on_error => {
+                    emit!(BatchPayoutSkippedEvent {
+                        receiver: receiver.key(),
+                        mint: Some(vault_token.mint),
+                        amount,
+                        error_code: error_code(&error),
+                        time: clock.unix_timestamp,
+                    });
+                    continue;
+                }
+                Err(error) => return Err(error),
+            }
+
+            let cpi_ctx = CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.master_ata.to_account_info(),
+                    to: receiver_ata.clone(),
+                    authority: master.to_account_info(),
+                },
+                seeds,
+            );
+
+            anchor_spl::token::transfer(cpi_ctx, amount)?;
+
+            vault_token.balance = vault_token
+                .balance
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+            record_outflow(&mut vault_token.net_flow, amount)?;
+            vault_token.lifetime.record_withdrawal(amount);
+
+            emit!(WithdrawEvent {
+                user: receiver_ata.key(),
+                holder: ctx.accounts.master_ata.key(),
+                amount,
+                decimals: vault_token.decimals,
+                ui_amount: ui_amount(amount, vault_token.decimals),
+                time: clock.unix_timestamp,
+                product_id: None,
+                request: None,
+                fee: 0,
+                net_amount: amount,
+                memo: None,
+                reference: None,
+                batch,
+                config_generation: master.config_generation,
+            });
+        }
+
+        Ok(master.config_generation)
+    }
+
+    /// This function can be called by a user to redeem a withdraw voucher signed by master.operator, so the payout
+    /// does not need an operator transaction. The transaction has to carry the Ed25519 program instruction verifying
+    /// the operator's signature of the serialized voucher right before this one, or the Secp256k1 program instruction
+    /// verifying a signature of master.voucher_eth_signer. The voucher nonce is a backend request id, its receipt is
+    /// created like by `send_withdraw` and paid by the user.
+    /// Returns the config generation of the vault like `send_withdraw`.
+    pub fn redeem_voucher(ctx: Context<RedeemVoucher>, voucher: Voucher) -> Result<u64> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let amount = voucher.amount;
+
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require_payout_hours(
+            vault_config.payout_hours,
+            get_clock()?.unix_timestamp,
+            ctx.accounts.admin_cosigner.is_some(),
+        )?;
+        require_within_payout_cap(vault_config.max_sol_per_payout, amount)?;
+        require!(
+            get_clock()?.unix_timestamp < voucher.expiry,
+            Errors::VoucherExpired
+        );
+        verify_voucher_signature(&ctx.accounts.instructions, master, &voucher.try_to_vec()?)?;
+
+        let operator = master.operator;
+        if trip_canary(
+            master,
+            vault_config,
+            &ctx.accounts.receiver_canary,
+            ctx.accounts.receiver.key(),
+            operator,
+            amount,
+        )? {
+            discard_receipt(
+                Some(&ctx.accounts.receipt),
+                ctx.accounts.receiver.to_account_info(),
+            )?;
+            return Ok(master.config_generation);
+        }
+        require!(
+            !vault_config.receiver_allowlist_enabled
+                || pda_exists(&ctx.accounts.receiver_allowlist),
+            Errors::ReceiverNotAllowlisted
+        );
+        require_not_blocked(&ctx.accounts.receiver_blocked, ctx.accounts.receiver.key())?;
+        require_not_frozen(&ctx.accounts.receiver_state)?;
+        record_receiver_payout(
+            ctx.accounts.receiver_cooldown.as_deref_mut(),
+            vault_config.payout_cooldown,
+            ctx.accounts.receiver.key(),
+            get_clock()?.unix_timestamp,
+        )?;
+
+        if trip_circuit_breaker(master, vault_config, None, operator, amount)? {
+            discard_receipt(
+                Some(&ctx.accounts.receipt),
+                ctx.accounts.receiver.to_account_info(),
+            )?;
+            return Ok(master.config_generation);
+        }
+        record_daily_withdrawal(master, vault_config, None, amount)?;
+        draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+
+        let receiver = &mut ctx.accounts.receiver;
+
+        let clock = get_clock()?;
+        master.last_withdraw_time = clock.unix_timestamp;
+
+        require!(master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        receiver.add_lamports(amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut master.net_flow, amount)?;
+        master.lifetime.record_withdrawal(amount);
+
+        write_receipt(
+            Some(&mut ctx.accounts.receipt),
+            master.key(),
+            Some(voucher.nonce),
+            receiver.key(),
+            None,
+            amount,
+            &clock,
+        )?;
+
+        emit!(WithdrawEvent {
+            user: receiver.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+            product_id: None,
+            request: None,
+            fee: 0,
+            net_amount: amount,
+            memo: None,
+            reference: None,
+            batch: None,
+            config_generation: master.config_generation,
+        });
+
+        Ok(master.config_generation)
+    }
+
+    /// This function can be called by master.operator to post the merkle root of the (user, amount, epoch) payouts
+    /// of `epoch`, which users then claim themselves with `claim`. At most `total` can be claimed against the root.
+    /// Leaves are sha256(0x00, user, amount, epoch) and nodes sha256(0x01, sorted children), numbers little endian.
+    /// `CLAIM_MODE_EPOCH` is reserved for `trigger_claim_mode`.
+    pub fn post_payout_root(
+        ctx: Context<PostPayoutRoot>,
+        epoch: u64,
+        root: [u8; 32],
+        total: u64,
+    ) -> Result<()> {
+        let vault_config = &ctx.accounts.vault_config;
+        let master = &ctx.accounts.master;
+        require!(epoch != CLAIM_MODE_EPOCH, Errors::EpochReserved);
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require!(master.balance >= total, Errors::NotEnoughBalance);
+
+        let payout_root = &mut ctx.accounts.payout_root;
+        payout_root.master = master.key();
+        payout_root.epoch = epoch;
+        payout_root.root = root;
+        payout_root.total = total;
+
+        emit!(PayoutRootPostedEvent {
+            payout_root: payout_root.key(),
+            epoch,
+            root,
+            total,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by a user to claim its `amount` of SOL from the merkle payouts of an epoch, with
+    /// the `proof` of its entry against the posted root. Each user claims once per epoch. In claim mode the payout
+    /// limits and the cold wallet reserve no longer apply, as the reserve cannot be swept anymore.
+    /// A claim of the claim mode root pays what the user has already withdrawn with `self_withdraw` against the same
+    /// balance root less, so the balance is not paid twice.
+    pub fn claim(ctx: Context<Claim>, amount: u64, proof: Vec<[u8; 32]>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &ctx.accounts.vault_config;
+        let payout_root = &mut ctx.accounts.payout_root;
+        let user = &mut ctx.accounts.user;
+
+        require!(
+            !vault_config.withdrawals_paused || master.claim_mode,
+            Errors::WithdrawalsPaused
+        );
+        require!(
+            merkle_root(payout_leaf(&user.key(), amount, payout_root.epoch), &proof)
+                == payout_root.root,
+            Errors::InvalidMerkleProof
+        );
+        let self_withdrawn = if payout_root.epoch == CLAIM_MODE_EPOCH {
+            self_withdrawn_against(&ctx.accounts.self_withdrawal, &payout_root.root)?
+        } else {
+            0
+        };
+        let amount = amount
+            .checked_sub(self_withdrawn)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        if !master.claim_mode {
+            require_within_payout_cap(vault_config.max_sol_per_payout, amount)?;
+            record_daily_withdrawal(master, vault_config, None, amount)?;
+            draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+        }
+        require!(
+            !vault_config.receiver_allowlist_enabled
+                || pda_exists(&ctx.accounts.receiver_allowlist),
+            Errors::ReceiverNotAllowlisted
+        );
+        require_not_blocked(&ctx.accounts.receiver_blocked, user.key())?;
+        require_not_frozen(&ctx.accounts.receiver_state)?;
+
+        payout_root.claimed = payout_root
+            .claimed
+            .checked_add(amount)
+            .filter(|claimed| *claimed <= payout_root.total)
+            .map(Ok)
+            .unwrap_or(Err(Errors::PayoutRootExhausted))?;
+
+        let clock = get_clock()?;
+        master.last_withdraw_time = clock.unix_timestamp;
+
+        require!(master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        user.add_lamports(amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut master.net_flow, amount)?;
+        master.lifetime.record_withdrawal(amount);
+
+        let claim = &mut ctx.accounts.claim;
+        claim.payout_root = payout_root.key();
+        claim.user = user.key();
+        claim.amount = amount;
+
+        emit!(WithdrawEvent {
+            user: user.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+            product_id: None,
+            request: None,
+            fee: 0,
+            net_amount: amount,
+            memo: None,
+            reference: None,
+            batch: None,
+            config_generation: master.config_generation,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by a user to withdraw up to vault_config.self_withdraw_threshold of SOL without the
+    /// operator, against its `balance` in the balance root the operator has last published, proven by `proof`. A
+    /// user withdraws once per published root and at most once per vault_config.self_withdraw_cooldown.
+    pub fn self_withdraw(
+        ctx: Context<SelfWithdraw>,
+        amount: u64,
+        balance: u64,
+        proof: Vec<[u8; 32]>,
+    ) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &ctx.accounts.vault_config;
+        let user = &mut ctx.accounts.user;
+
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require!(
+            vault_config.self_withdraw_threshold > 0 && master.balance_root != [0; 32],
+            Errors::SelfWithdrawDisabled
+        );
+        require!(
+            amount <= vault_config.self_withdraw_threshold,
+            Errors::SelfWithdrawTooLarge
+        );
+        require!(
+            merkle_root(payout_leaf(&user.key(), balance, CLAIM_MODE_EPOCH), &proof)
+                == master.balance_root,
+            Errors::InvalidMerkleProof
+        );
+        require!(amount <= balance, Errors::NotEnoughBalance);
+        require!(
+            !vault_config.receiver_allowlist_enabled
+                || pda_exists(&ctx.accounts.receiver_allowlist),
+            Errors::ReceiverNotAllowlisted
+        );
+        require_not_blocked(&ctx.accounts.receiver_blocked, user.key())?;
+        require_not_frozen(&ctx.accounts.receiver_state)?;
+
+        let clock = get_clock()?;
+        let self_withdrawal = &mut ctx.accounts.self_withdrawal;
+        require!(
+            self_withdrawal.balance_root != master.balance_root
+                && clock.unix_timestamp - self_withdrawal.last_withdraw_time
+                    >= vault_config.self_withdraw_cooldown,
+            Errors::SelfWithdrawCooldown
+        );
+        self_withdrawal.user = user.key();
+        self_withdrawal.balance_root = master.balance_root;
+        self_withdrawal.last_withdraw_time = clock.unix_timestamp;
+        self_withdrawal.withdrawn = amount;
+
+        master.last_withdraw_time = clock.unix_timestamp;
+
+        draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+        require!(master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        user.add_lamports(amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut master.net_flow, amount)?;
+        master.lifetime.record_withdrawal(amount);
+
+        emit!(WithdrawEvent {
+            user: user.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+            product_id: None,
+            request: None,
+            fee: 0,
+            net_amount: amount,
+            memo: None,
+            reference: None,
+            batch: None,
+            config_generation: master.config_generation,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to authorize a SOL payout of `amount` to `receiver` now and
+    /// let anyone execute it with `execute_scheduled_payout` from `execute_after` on. `id` tells apart the scheduled
+    /// payouts of a vault. If the vault has a payout cooldown, the receiver cooldown PDA the execution is checked
+    /// against has to be passed, it is opened here if needed.
+    pub fn schedule_payout(
+        ctx: Context<SchedulePayout>,
+        id: u64,
+        receiver: Pubkey,
+        amount: u64,
+        execute_after: i64,
+    ) -> Result<()> {
+        require!(amount > 0, Errors::ZeroAmount);
+
+        let scheduled_payout = &mut ctx.accounts.scheduled_payout;
+        scheduled_payout.master = ctx.accounts.master.key();
+        scheduled_payout.id = id;
+        scheduled_payout.receiver = receiver;
+        scheduled_payout.amount = amount;
+        scheduled_payout.execute_after = execute_after;
+
+        emit!(PayoutScheduledEvent {
+            scheduled_payout: scheduled_payout.key(),
+            receiver,
+            amount,
+            execute_after,
+            time: get_clock()?.unix_timestamp,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to drop a scheduled payout before it is executed, its rent
+    /// goes back to the operator.
+    pub fn cancel_scheduled_payout(_ctx: Context<CancelScheduledPayout>) -> Result<()> {
+        Ok(())
+    }
+
+    /// This function can be called by anyone to execute a scheduled payout once its `execute_after` has passed. The
+    /// payout is checked like by `send_withdraw` at execution time. If a canary or the circuit breaker trips, the
+    /// scheduled payout stays open so it can be executed once payouts resume, otherwise its rent goes to the operator.
+    pub fn execute_scheduled_payout(ctx: Context<ExecuteScheduledPayout>) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+        let scheduled_payout = &ctx.accounts.scheduled_payout;
+        let amount = scheduled_payout.amount;
+        let operator = master.operator;
+
+        let clock = get_clock()?;
+        require!(
+            clock.unix_timestamp >= scheduled_payout.execute_after,
+            Errors::PayoutNotDue
+        );
+
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require_payout_hours(
+            vault_config.payout_hours,
+            clock.unix_timestamp,
+            ctx.accounts.admin_cosigner.is_some(),
+        )?;
+        require_within_payout_cap(vault_config.max_sol_per_payout, amount)?;
+        if trip_canary(
+            master,
+            vault_config,
+            &ctx.accounts.receiver_canary,
+            ctx.accounts.receiver.key(),
+            operator,
+            amount,
+        )? {
+            return Ok(());
+        }
+        require!(
+            !vault_config.receiver_allowlist_enabled
+                || pda_exists(&ctx.accounts.receiver_allowlist),
+            Errors::ReceiverNotAllowlisted
+        );
+        require_not_blocked(&ctx.accounts.receiver_blocked, ctx.accounts.receiver.key())?;
+        require_not_frozen(&ctx.accounts.receiver_state)?;
+        record_receiver_payout(
+            ctx.accounts.receiver_cooldown.as_deref_mut(),
+            vault_config.payout_cooldown,
+            ctx.accounts.receiver.key(),
+            clock.unix_timestamp,
+        )?;
+
+        if trip_circuit_breaker(master, vault_config, None, operator, amount)? {
+            return Ok(());
+        }
+        record_daily_withdrawal(master, vault_config, None, amount)?;
+        draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+
+        let receiver = &mut ctx.accounts.receiver;
+
+        master.last_withdraw_time = clock.unix_timestamp;
+
+        require!(master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        receiver.add_lamports(amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut master.net_flow, amount)?;
+        master.lifetime.record_withdrawal(amount);
+
+        scheduled_payout.close(ctx.accounts.operator.to_account_info())?;
+
+        emit!(WithdrawEvent {
+            user: receiver.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+            product_id: None,
+            request: None,
+            fee: 0,
+            net_amount: amount,
+            memo: None,
+            reference: None,
+            batch: None,
+            config_generation: master.config_generation,
+        });
+
+        Ok(())
+    }
+
+    /// This function can be called by master.operator to refund `amount` of SOL of the deposit with the backend id
+    /// `deposit_id` to its depositor. The refund creates the deposit refund PDA of that id, so each deposit is refunded
+    /// once, and emits a `RefundEvent` instead of a withdraw event, so refunds stay apart from payouts in accounting.
+    pub fn refund_deposit(ctx: Context<RefundDeposit>, deposit_id: u64, amount: u64) -> Result<()> {
+        let master = &mut ctx.accounts.master;
+        let vault_config = &mut ctx.accounts.vault_config;
+
+        require!(!vault_config.withdrawals_paused, Errors::WithdrawalsPaused);
+        require_payout_hours(
+            vault_config.payout_hours,
+            get_clock()?.unix_timestamp,
+            ctx.accounts.admin_cosigner.is_some(),
+        )?;
+        require_within_payout_cap(vault_config.max_sol_per_payout, amount)?;
+        if trip_canary(
+            master,
+            vault_config,
+            &ctx.accounts.depositor_canary,
+            ctx.accounts.depositor.key(),
+            ctx.accounts.operator.key(),
+            amount,
+        )? {
+            return ctx
+                .accounts
+                .deposit_refund
+                .close(ctx.accounts.operator.to_account_info());
+        }
+        require_not_blocked(
+            &ctx.accounts.depositor_blocked,
+            ctx.accounts.depositor.key(),
+        )?;
+        require_not_frozen(&ctx.accounts.depositor_state)?;
+        record_receiver_payout(
+            ctx.accounts.depositor_cooldown.as_deref_mut(),
+            vault_config.payout_cooldown,
+            ctx.accounts.depositor.key(),
+            get_clock()?.unix_timestamp,
+        )?;
+
+        if trip_circuit_breaker(
+            master,
+            vault_config,
+            None,
+            ctx.accounts.operator.key(),
+            amount,
+        )? {
+            return ctx
+                .accounts
+                .deposit_refund
+                .close(ctx.accounts.operator.to_account_info());
+        }
+        record_daily_withdrawal(master, vault_config, None, amount)?;
+        draw_hot_balance(master, vault_config.hot_balance_threshold, amount)?;
+
+        let depositor = &mut ctx.accounts.depositor;
+
+        let clock = get_clock()?;
+        master.last_withdraw_time = clock.unix_timestamp;
+        master.heartbeat_time = clock.unix_timestamp;
+
+        require!(master.balance >= amount, Errors::NotEnoughBalance);
+        require_sol_reserve(
+            &master.to_account_info(),
+            vault_config.min_sol_reserve,
+            master
+                .get_lamports()
+                .checked_sub(amount)
+                .map(Ok)
+                .unwrap_or(Err(Errors::NotEnoughBalance))?,
+        )?;
+
+        master.sub_lamports(amount)?;
+        depositor.add_lamports(amount)?;
+
+        master.balance = master
+            .balance
+            .checked_sub(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        record_outflow(&mut master.net_flow, amount)?;
+        master.lifetime.record_withdrawal(amount);
+
+        let deposit_refund = &mut ctx.accounts.deposit_refund;
+        deposit_refund.master = master.key();
+        deposit_refund.deposit_id = deposit_id;
+        deposit_refund.depositor = depositor.key();
+        deposit_refund.amount = amount;
+        deposit_refund.refunded_at = clock.unix_timestamp;
+
+        emit!(RefundEvent {
+            deposit_id,
+            user: depositor.key(),
+            holder: master.key(),
+            amount,
+            decimals: SOL_DECIMALS,
+            ui_amount: ui_amount(amount, SOL_DECIMALS),
+            time: clock.unix_timestamp,
+            config_generation: master.config_generation,
+        });
+
+        Ok(())
+    }
+}
+
+/// Errors of this smart contract.
+#[error_code]
+pub enum Errors {
+    /// Unathorized access of the smart contract.
+    #[msg("Unauthorized")]
+    Unauthorized,
+    /// Not enough balance
+    #[msg("Not Enough Balance")]
+    NotEnoughBalance,
+    /// Token account has already been created.
+    #[msg("Token account has already been created")]
+    TokenAccountAlreadyCreated,
     /// Math underflow or overflow occurred
     #[msg("Math underflow or overflow occurred")]
     MathUnderflowOrOverflow,
+    /// Account would drop below the rent-exempt minimum.
+    #[msg("Account would not remain rent exempt")]
+    NotRentExempt,
+    /// Payouts are paused.
+    #[msg("Withdrawals are paused")]
+    WithdrawalsPaused,
+    /// Receiver is not on the payout allowlist.
+    #[msg("Receiver is not allowlisted")]
+    ReceiverNotAllowlisted,
+    /// Address has been blocked.
+    #[msg("Address is blocked")]
+    AddressBlocked,
+    /// Depositor is not on the deposit whitelist.
+    #[msg("Depositor is not whitelisted")]
+    DepositorNotWhitelisted,
+    /// Currency code of a fiat quote is not three uppercase letters.
+    #[msg("Invalid currency code")]
+    InvalidCurrencyCode,
+    /// User has been frozen.
+    #[msg("User is frozen")]
+    UserFrozen,
+    /// Fee is above the protocol-wide maximum.
+    #[msg("Fee is too high")]
+    FeeTooHigh,
+    /// Program config already allows the maximum number of mints.
+    #[msg("Too many allowed mints")]
+    TooManyAllowedMints,
+    /// Mint is not allowed by the program config.
+    #[msg("Mint is not allowed")]
+    MintNotAllowed,
+    /// Deposits are paused.
+    #[msg("Deposits are paused")]
+    DepositsPaused,
+    /// Operator fault reason is longer than `MAX_FAULT_REASON_LEN`.
+    #[msg("Fault reason is too long")]
+    FaultReasonTooLong,
+    /// Rolling window length is not positive.
+    #[msg("Invalid window")]
+    InvalidWindow,
+    /// Deposits of the mint have been disabled.
+    #[msg("Mint is disabled")]
+    MintDisabled,
+    /// Mint cannot be removed while the vault still holds its tokens.
+    #[msg("Vault still holds tokens of the mint")]
+    MintNotEmpty,
+    /// Token mint is not the mint the vault token has been created for.
+    #[msg("Token mint does not match the vault token")]
+    MintMismatch,
+    /// Vault registry is full.
+    #[msg("Too many vaults")]
+    TooManyVaults,
+    /// Source and destination vault of a transfer are the same.
+    #[msg("Cannot transfer to the same vault")]
+    SameVault,
+    /// Deposit would take the daily net inflow of the asset over its limit.
+    #[msg("Daily net inflow limit exceeded")]
+    ExposureLimitExceeded,
+    /// Product ledger belongs to another vault or asset.
+    #[msg("Product ledger does not match the vault or asset")]
+    ProductLedgerMismatch,
+    /// Receiver lock belongs to another vault or receiver.
+    #[msg("Receiver lock does not match the vault or receiver")]
+    ReceiverLockMismatch,
+    /// Token mint is not the native mint.
+    #[msg("Token mint is not the wSOL mint")]
+    NotNativeMint,
+    /// Program-level kill switch is on.
+    #[msg("Program is paused")]
+    ProgramPaused,
+    /// Vault already has an address lookup table.
+    #[msg("Lookup table has already been created")]
+    LookupTableAlreadyCreated,
+    /// Mint is a supported mint of the vault, its tokens are not stray.
+    #[msg("Cannot rescue a supported mint")]
+    MintSupported,
+    /// Master PDA holds no lamports above rent and its tracked balance.
+    #[msg("No untracked SOL to sweep")]
+    NothingToSweep,
+    /// Token account can be moved by someone other than its owner.
+    #[msg("Token account has a delegate or close authority")]
+    TokenAccountNotExclusive,
+    /// Amount has to be greater than zero.
+    #[msg("Amount must be greater than zero")]
+    ZeroAmount,
+    /// Withdraw request belongs to another vault, receiver, asset or amount.
+    #[msg("Withdraw request does not match the payout")]
+    WithdrawRequestMismatch,
+    /// Withdraw request has expired and can no longer be paid.
+    #[msg("Withdraw request has expired")]
+    WithdrawRequestExpired,
+    /// Withdraw request is still payable and cannot be expired yet.
+    #[msg("Withdraw request has not expired yet")]
+    WithdrawRequestNotExpired,
+    /// Withdraw requests have to be paid in queue order.
+    #[msg("Withdraw request is not at the head of the queue")]
+    NotQueueHead,
+    /// Withdraw queue has no request left.
+    #[msg("Withdraw queue is empty")]
+    WithdrawQueueEmpty,
+    /// Request at the head of the withdraw queue still has to be paid, cancelled or expired.
+    #[msg("Withdraw request is still pending")]
+    WithdrawRequestPending,
+    /// Receipt has been passed without a request id, or a request id without a receipt.
+    #[msg("Receipt must be passed exactly when a request id is")]
+    ReceiptMismatch,
+    /// Receipt is younger than `RECEIPT_RETENTION`.
+    #[msg("Receipt is still retained")]
+    ReceiptRetained,
+    /// Voucher is not signed by master.operator or master.voucher_eth_signer in the preceding signature verification
+    /// instruction.
+    #[msg("Voucher signature is invalid")]
+    InvalidVoucherSignature,
+    /// Voucher is redeemed at or after its expiry.
+    #[msg("Voucher has expired")]
+    VoucherExpired,
+    /// Payout entry is not part of the merkle root of the epoch.
+    #[msg("Merkle proof is invalid")]
+    InvalidMerkleProof,
+    /// Claim would exceed the total posted with the merkle root.
+    #[msg("Payout root total exceeded")]
+    PayoutRootExhausted,
+    /// Remaining accounts of a batch payout are not the receiver accounts of its amounts.
+    #[msg("Batch accounts do not match the amounts")]
+    BatchAccountsMismatch,
+    /// Scheduled payout is executed before its `execute_after`.
+    #[msg("Scheduled payout is not due yet")]
+    PayoutNotDue,
+    /// Scheduled payout does not belong to the vault or receiver.
+    #[msg("Scheduled payout does not match")]
+    ScheduledPayoutMismatch,
+    /// Token account paid to is not owned by the receiver or holds another mint.
+    #[msg("Token account does not belong to the receiver and mint")]
+    ReceiverTokenAccountMismatch,
+    /// Payout memo is longer than `MAX_MEMO_LEN`.
+    #[msg("Memo is too long")]
+    MemoTooLong,
+    /// Payout memo has been passed without the SPL Memo program.
+    #[msg("Memo program is missing")]
+    MemoProgramMissing,
+    /// Plain deposit while the vault holds deposits in escrow.
+    #[msg("Deposits have to be escrowed")]
+    DepositEscrowRequired,
+    /// Escrowed deposit while the vault does not hold deposits in escrow.
+    #[msg("Deposit escrow is disabled")]
+    DepositEscrowDisabled,
+    /// Escrowed deposit is released before its escrow window has passed.
+    #[msg("Deposit is still in its escrow window")]
+    DepositInEscrowWindow,
+    /// Vault has been switched to claim mode, which cannot be left.
+    #[msg("Vault is in claim mode")]
+    ClaimModeActive,
+    /// Dead man period or published balance root is not set.
+    #[msg("Dead man switch is disabled")]
+    DeadManSwitchDisabled,
+    /// Operator has shown a heartbeat within the dead man period.
+    #[msg("Operator is alive")]
+    OperatorAlive,
+    /// Self-service threshold or published balance root is not set.
+    #[msg("Self-service withdrawals are disabled")]
+    SelfWithdrawDisabled,
+    /// Self-service withdrawal above vault_config.self_withdraw_threshold.
+    #[msg("Amount is above the self-service threshold")]
+    SelfWithdrawTooLarge,
+    /// Self-service withdrawal within the cooldown or against an already used balance root.
+    #[msg("Self-service withdrawal is cooling down")]
+    SelfWithdrawCooldown,
+    /// Payout fulfills a withdraw request which is disputed.
+    #[msg("Withdraw request is disputed")]
+    WithdrawRequestDisputed,
+    /// Announced batch does not belong to the vault or pays another asset.
+    #[msg("Announced batch does not match")]
+    AnnouncedBatchMismatch,
+    /// Batch payouts would exceed the total of their announced batch.
+    #[msg("Announced batch total exceeded")]
+    AnnouncedBatchExceeded,
+    /// Operator transaction pays out more than the per payout cap of the asset.
+    #[msg("Payout cap exceeded")]
+    PayoutCapExceeded,
+    /// Operator payouts of the last 24 hours would exceed the daily withdraw limit of the asset.
+    #[msg("Daily withdraw limit exceeded")]
+    DailyWithdrawLimitExceeded,
+    /// Payout would draw on the SOL reserved for the cold wallet.
+    #[msg("Hot balance exceeded")]
+    HotBalanceExceeded,
+    /// Hot balance threshold set without a cold wallet.
+    #[msg("Invalid cold wallet")]
+    InvalidColdWallet,
+    /// Operator payout outside the payout hours of the vault without admin co-sign.
+    #[msg("Outside payout hours")]
+    OutsidePayoutHours,
+    /// Payout hours with bits beyond the 24 hours of a day.
+    #[msg("Invalid payout hours")]
+    InvalidPayoutHours,
+    /// Payout would take master PDA below vault_config.min_sol_reserve.
+    #[msg("SOL reserve required")]
+    SolReserveRequired,
+    /// Sweep to the cold wallet while the vault has no hot balance threshold.
+    #[msg("Hot balance split is disabled")]
+    HotBalanceSplitDisabled,
+    /// Receiver is not the registered cold wallet.
+    #[msg("Cold wallet does not match")]
+    ColdWalletMismatch,
+    /// Deposit is empty or below the minimum deposit of the asset.
+    #[msg("Deposit is too small")]
+    DepositTooSmall,
+    /// Deposit is above the maximum deposit of the asset.
+    #[msg("Deposit is too large")]
+    DepositTooLarge,
+    /// User deposits PDA does not belong to the vault, user or asset.
+    #[msg("User deposits account does not match")]
+    UserDepositsMismatch,
+    /// Deposit of an asset with a user deposit cap without the user deposits PDA.
+    #[msg("User deposits account is missing")]
+    UserDepositsMissing,
+    /// Deposit would take the lifetime deposits of the user above its cap.
+    #[msg("User deposit cap exceeded")]
+    UserDepositCapExceeded,
+    /// Deposit would take the balance of the asset above the vault cap.
+    #[msg("Vault cap reached")]
+    VaultCapReached,
+    /// Payout of a vault with a payout cooldown without the receiver cooldown PDA.
+    #[msg("Receiver cooldown account is missing")]
+    ReceiverCooldownMissing,
+    /// Payout to a receiver sooner than the payout cooldown after the previous one.
+    #[msg("Receiver is in its payout cooldown")]
+    ReceiverInCooldown,
+    /// Config change of a mint setting without the vault token PDA of the mint.
+    #[msg("Vault token account is missing")]
+    VaultTokenMissing,
+    /// Payout root posted for the epoch only claim mode may post.
+    #[msg("Epoch is reserved for claim mode")]
+    EpochReserved,
+    /// Minimum deposit set above the maximum deposit of the asset.
+    #[msg("Minimum deposit exceeds the maximum deposit")]
+    InvalidDepositLimits,
+    /// Token account passed to `migrate_legacy_token` is not the one of the legacy master.
+    #[msg("Token account is not the one of the legacy master")]
+    LegacyTokenAccountMismatch,
+    /// Deposit which would exceed the deposit velocity limits of the vault.
+    #[msg("Deposit velocity limit exceeded")]
+    DepositVelocityExceeded,
+    /// `trip_deposit_velocity` called while the attempted deposit stays within the deposit velocity limits.
+    #[msg("Deposit is within the deposit velocity limits")]
+    DepositVelocityWithinLimits,
+}
+
+/// Fills the vault token PDA of a newly supported mint, held in `token_account` of master PDA.
+fn register_mint(
+    program_config: &ProgramConfig,
+    master: &mut Master,
+    vault_token: &mut VaultToken,
+    token_mint: &Account<Mint>,
+    token_account: Pubkey,
+) -> Result<()> {
+    let mint = token_mint.key();
+    require!(
+        program_config.allowed_mints.contains(&mint),
+        Errors::MintNotAllowed
+    );
+
+    vault_token.mint = mint;
+    vault_token.ata = token_account;
+    vault_token.enabled = true;
+    vault_token.decimals = token_mint.decimals;
+    master.bump_config_generation();
+
+    emit!(SupportedMintEvent {
+        mint,
+        enabled: true,
+        removed: false,
+        time: get_clock()?.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Fails unless `currency` looks like an ISO 4217 code.
+fn require_valid_currency(currency: &[u8; 3]) -> Result<()> {
+    require!(
+        currency.iter().all(u8::is_ascii_uppercase),
+        Errors::InvalidCurrencyCode
+    );
+    Ok(())
+}
+
+/// Fails unless the currency code of `quote` looks like an ISO 4217 code.
+fn require_valid_quote(quote: &Option<FiatQuote>) -> Result<()> {
+    if let Some(quote) = quote {
+        require_valid_currency(&quote.currency)?;
+    }
+    Ok(())
+}
+
+/// Fee of an `express` payout of `amount`, `fee_bps` of the vault capped by `max_fee_bps` of the program config, in
+/// case the protocol-wide maximum has been lowered since. 0 for regular payouts.
+fn express_fee(amount: u64, express: bool, fee_bps: u16, max_fee_bps: u16) -> Result<u64> {
+    if !express {
+        return Ok(0);
+    }
+    let fee = u128::from(amount) * u128::from(fee_bps.min(max_fee_bps)) / u128::from(MAX_BPS);
+    let fee = u64::try_from(fee)
+        .map(Ok)
+        .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+    Ok(fee)
+}
+
+/// Formats `amount` with `decimals` the way wallets show it, e.g. 1_500_000 with 6 decimals is "1.5".
+fn ui_amount(amount: u64, decimals: u8) -> String {
+    spl_token::amount_to_ui_amount_string_trimmed(amount, decimals)
+}
+
+/// If `canary` is a planted canary PDA, stops payouts and emits `CanaryTriggeredEvent`.
+/// Returns whether the payout has to be skipped; it must not fail, otherwise the pause would be rolled back.
+fn trip_canary(
+    master: &mut Master,
+    vault_config: &mut VaultConfig,
+    canary: &AccountInfo,
+    receiver: Pubkey,
+    operator: Pubkey,
+    amount: u64,
+) -> Result<bool> {
+    if !pda_exists(canary) {
+        return Ok(false);
+    }
+
+    change_config(
+        master,
+        vault_config,
+        None,
+        operator,
+        ConfigSetting::WithdrawalsPaused(true),
+    )?;
+
+    let clock = get_clock()?;
+
+    emit!(CanaryTriggeredEvent {
+        receiver,
+        operator,
+        amount,
+        time: clock.unix_timestamp,
+    });
+
+    Ok(true)
+}
+
+/// Adds `amount` to the rolling window of SOL payouts, or of `vault_token` payouts if given. If that would exceed the
+/// configured limit, payouts are paused instead, `CircuitBreakerTrippedEvent` is emitted and `true` is returned so the
+/// caller skips the payout.
+fn trip_circuit_breaker(
+    master: &mut Master,
+    vault_config: &mut VaultConfig,
+    vault_token: Option<&mut VaultToken>,
+    operator: Pubkey,
+    amount: u64,
+) -> Result<bool> {
+    let window_secs = vault_config.outflow_window_secs;
+    let (window, limit, mint) = match vault_token {
+        Some(vault_token) => (
+            &mut vault_token.outflow,
+            vault_token.outflow_limit,
+            Some(vault_token.mint),
+        ),
+        None => (&mut master.outflow, vault_config.outflow_limit, None),
+    };
+    if limit == 0 {
+        return Ok(false);
+    }
+
+    let clock = get_clock()?;
+
+    let mut next = *window;
+    let total = next.record(clock.unix_timestamp, window_secs, amount)?;
+    if total <= limit {
+        *window = next;
+        return Ok(false);
+    }
+
+    change_config(
+        master,
+        vault_config,
+        None,
+        operator,
+        ConfigSetting::WithdrawalsPaused(true),
+    )?;
+
+    emit!(CircuitBreakerTrippedEvent {
+        operator,
+        amount,
+        window_amount: total - amount,
+        limit,
+        mint,
+        time: clock.unix_timestamp,
+    });
+
+    Ok(true)
+}
+
+/// Returns the rolling deposit velocity windows of vault_config.deposit_velocity_window_secs with a deposit of
+/// `amount` added: the count of all deposits of the vault and the volume of SOL, or of `vault_token` if given, and
+/// whether they stay within the configured thresholds.
+fn next_deposit_velocity(
+    master: &Master,
+    vault_config: &VaultConfig,
+    vault_token: Option<&VaultToken>,
+    amount: u64,
+) -> Result<(RateWindow, RateWindow, bool)> {
+    let window_secs = vault_config.deposit_velocity_window_secs;
+    let max_count = vault_config.deposit_velocity_max_count;
+    let (mut volume, max_volume) = match vault_token {
+        Some(vault_token) => (
+            vault_token.deposit_volume,
+            vault_token.deposit_velocity_max_volume,
+        ),
+        None => (
+            master.deposit_volume,
+            vault_config.deposit_velocity_max_volume,
+        ),
+    };
+    let mut count = master.deposit_count;
+    if max_count == 0 && max_volume == 0 {
+        return Ok((count, volume, true));
+    }
+
+    let clock = get_clock()?;
+
+    let window_volume = volume.record(clock.unix_timestamp, window_secs, amount)?;
+    let window_count = count.record(clock.unix_timestamp, window_secs, 1)?;
+    let within = (max_count == 0 || window_count <= max_count)
+        && (max_volume == 0 || window_volume <= max_volume);
+
+    Ok((count, volume, within))
+}
+
+/// Adds a deposit of `amount` to the deposit velocity windows of the vault, see `next_deposit_velocity`. A deposit
+/// which would exceed the configured thresholds fails with `DepositVelocityExceeded` and is not recorded.
+fn record_deposit_velocity(
+    master: &mut Master,
+    vault_config: &VaultConfig,
+    vault_token: Option<&mut VaultToken>,
+    amount: u64,
+) -> Result<()> {
+    let (count, volume, within) =
+        next_deposit_velocity(master, vault_config, vault_token.as_deref(), amount)?;
+    require!(within, Errors::DepositVelocityExceeded);
+
+    master.deposit_count = count;
+    match vault_token {
+        Some(vault_token) => vault_token.deposit_volume = volume,
+        None => master.deposit_volume = volume,
+    }
+
+    Ok(())
+}
+
+/// Adds an operator payout of `amount` to the rolling 24 hour window of SOL payouts, or of `vault_token` payouts if
+/// given. Fails with `DailyWithdrawLimitExceeded` if the window would exceed the daily withdraw limit of the asset, 0
+/// means unlimited. Emits a `DailyWithdrawLimitReachedEvent` when the payout uses up the limit.
+fn record_daily_withdrawal(
+    master: &mut Master,
+    vault_config: &VaultConfig,
+    vault_token: Option<&mut VaultToken>,
+    amount: u64,
+) -> Result<()> {
+    let (window, limit, mint) = match vault_token {
+        Some(vault_token) => (
+            &mut vault_token.daily_outflow,
+            vault_token.daily_withdraw_limit,
+            Some(vault_token.mint),
+        ),
+        None => (
+            &mut master.daily_outflow,
+            vault_config.daily_withdraw_limit,
+            None,
+        ),
+    };
+    if limit == 0 {
+        return Ok(());
+    }
+
+    let clock = get_clock()?;
+
+    let mut next = *window;
+    let total = next.record(clock.unix_timestamp, SECONDS_PER_DAY, amount)?;
+    require!(total <= limit, Errors::DailyWithdrawLimitExceeded);
+    *window = next;
+
+    if total == limit {
+        emit!(DailyWithdrawLimitReachedEvent {
+            limit,
+            mint,
+            window_start: next.start,
+            time: clock.unix_timestamp,
+        });
+    }
+
+    Ok(())
+}
+
+/// Adds the SOL above `hot_balance_threshold` to master.cold_reserve, keeping what has been reserved before as long as
+/// the balance covers it, and returns the reserve.
+fn reserve_cold_balance(master: &mut Master, hot_balance_threshold: u64) -> u64 {
+    master.cold_reserve = master
+        .cold_reserve
+        .min(master.balance)
+        .max(master.balance.saturating_sub(hot_balance_threshold));
+    master.cold_reserve
+}
+
+/// Reserves the SOL above `hot_balance_threshold` for the cold wallet, keeping what has been reserved before, and
+/// fails with `HotBalanceExceeded` if a payout of `amount` would draw on the reserve. A threshold of 0 disables it.
+fn draw_hot_balance(master: &mut Master, hot_balance_threshold: u64, amount: u64) -> Result<()> {
+    if hot_balance_threshold == 0 {
+        return Ok(());
+    }
+
+    reserve_cold_balance(master, hot_balance_threshold);
+    require!(
+        master.balance - master.cold_reserve >= amount,
+        Errors::HotBalanceExceeded
+    );
+    Ok(())
+}
+
+/// Fails with `DepositTooSmall` if a deposit of `amount` is empty or below `min_deposit`, and with `DepositTooLarge`
+/// if it is above `max_deposit`, 0 means unlimited.
+fn require_deposit_size(min_deposit: u64, max_deposit: u64, amount: u64) -> Result<()> {
+    require!(amount > 0 && amount >= min_deposit, Errors::DepositTooSmall);
+    require!(
+        max_deposit == 0 || amount <= max_deposit,
+        Errors::DepositTooLarge
+    );
+    Ok(())
+}
+
+/// Records an operator payout to `receiver` at `now` in its `receiver_cooldown` PDA. If the vault has a
+/// `payout_cooldown` the PDA has to be passed, and the previous payout to the receiver has to be at least that long ago.
+fn record_receiver_payout(
+    receiver_cooldown: Option<&mut ReceiverCooldown>,
+    payout_cooldown: i64,
+    receiver: Pubkey,
+    now: i64,
+) -> Result<()> {
+    let Some(receiver_cooldown) = receiver_cooldown else {
+        require!(payout_cooldown == 0, Errors::ReceiverCooldownMissing);
+        return Ok(());
+    };
+
+    require!(
+        receiver_cooldown.last_payout_time == 0
+            || now.saturating_sub(receiver_cooldown.last_payout_time) >= payout_cooldown,
+        Errors::ReceiverInCooldown
+    );
+    receiver_cooldown.receiver = receiver;
+    receiver_cooldown.last_payout_time = now;
+    Ok(())
+}
+
+/// Fails with `OutsidePayoutHours` if operator payouts are restricted to the UTC hours of `payout_hours` (bit `h` set
+/// for hour `h`, 0 means any hour) and `now` is outside of them, unless the admin has co-signed the payout.
+fn require_payout_hours(payout_hours: u32, now: i64, admin_cosigned: bool) -> Result<()> {
+    if payout_hours == 0 || admin_cosigned {
+        return Ok(());
+    }
+
+    let hour = now.rem_euclid(SECONDS_PER_DAY) / SECONDS_PER_HOUR;
+    require!(payout_hours & (1 << hour) != 0, Errors::OutsidePayoutHours);
+    Ok(())
+}
+
+/// Fails with `VaultCapReached` if a deposit or donation took the `balance` of an asset above its `vault_cap`, 0
+/// means uncapped.
+fn require_within_vault_cap(vault_cap: u64, balance: u64) -> Result<()> {
+    require!(
+        vault_cap == 0 || balance <= vault_cap,
+        Errors::VaultCapReached
+    );
+    Ok(())
+}
+
+/// Adds a deposit of `amount` to the lifetime deposits of the user in `user_deposits`. If the asset caps the lifetime
+/// deposits of users at `default_cap` the PDA has to be passed, and the deposits may not exceed the cap of the user,
+/// or `default_cap` if the admin has not set one for the user. 0 means uncapped.
+fn record_user_deposit(
+    user_deposits: Option<&mut UserDeposits>,
+    default_cap: u64,
+    amount: u64,
+) -> Result<()> {
+    let Some(user_deposits) = user_deposits else {
+        require!(default_cap == 0, Errors::UserDepositsMissing);
+        return Ok(());
+    };
+
+    let cap = if user_deposits.cap > 0 {
+        user_deposits.cap
+    } else {
+        default_cap
+    };
+    user_deposits.deposited = user_deposits
+        .deposited
+        .checked_add(amount)
+        .filter(|deposited| cap == 0 || *deposited <= cap)
+        .map(Ok)
+        .unwrap_or(Err(Errors::UserDepositCapExceeded))?;
+    Ok(())
+}
+
+/// Takes a returned deposit of `amount` back out of the lifetime deposits of the user in `user_deposits`. The PDA has
+/// to be passed like for `record_user_deposit` if the asset caps the lifetime deposits at `default_cap`.
+fn unrecord_user_deposit(
+    user_deposits: Option<&mut UserDeposits>,
+    default_cap: u64,
+    amount: u64,
+) -> Result<()> {
+    let Some(user_deposits) = user_deposits else {
+        require!(default_cap == 0, Errors::UserDepositsMissing);
+        return Ok(());
+    };
+
+    user_deposits.deposited = user_deposits.deposited.saturating_sub(amount);
+    Ok(())
+}
+
+/// Adds a deposit of `amount` to the daily `net_flow`. Fails with `ExposureLimitExceeded` if the net inflow of the day
+/// would exceed `limit`, 0 means unlimited.
+fn record_inflow(net_flow: &mut NetFlow, limit: u64, amount: u64) -> Result<()> {
+    let amount = i64::try_from(amount)
+        .map(Ok)
+        .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+    let net_inflow = net_flow.record(get_clock()?.unix_timestamp, amount)?;
+
+    require!(
+        limit == 0 || i128::from(net_inflow) <= i128::from(limit),
+        Errors::ExposureLimitExceeded
+    );
+    Ok(())
+}
+
+/// Subtracts a payout of `amount` from the daily `net_flow`. Saturates instead of failing, so accounting never blocks
+/// a payout.
+fn record_outflow(net_flow: &mut NetFlow, amount: u64) -> Result<()> {
+    let amount = i64::try_from(amount).unwrap_or(i64::MAX);
+    net_flow.record(get_clock()?.unix_timestamp, -amount)?;
+    Ok(())
+}
+
+/// Fails with `PayoutCapExceeded` if a single transaction pays out more than `cap`, a cap of 0 disables it.
+fn require_within_payout_cap(cap: u64, amount: u64) -> Result<()> {
+    require!(cap == 0 || amount <= cap, Errors::PayoutCapExceeded);
+    Ok(())
+}
+
+/// Sum of the `amounts` of a batch payout.
+fn batch_total(amounts: &[u64]) -> Result<u64> {
+    amounts
+        .iter()
+        .try_fold(0u64, |total, amount| total.checked_add(*amount))
+        .map(Ok)
+        .unwrap_or(err!(Errors::MathUnderflowOrOverflow))
+}
+
+/// Books a batch payout of `amount` to the `announced_batch` it references, if any, and returns its hash.
+/// Fails with `AnnouncedBatchExceeded` if the batch would pay out more than announced.
+fn record_batch_payout(
+    announced_batch: Option<&mut AnnouncedBatch>,
+    amount: u64,
+) -> Result<Option<[u8; 32]>> {
+    let Some(announced_batch) = announced_batch else {
+        return Ok(None);
+    };
+
+    announced_batch.paid = announced_batch
+        .paid
+        .checked_add(amount)
+        .filter(|paid| *paid <= announced_batch.total_amount)
+        .map(Ok)
+        .unwrap_or(Err(Errors::AnnouncedBatchExceeded))?;
+
+    Ok(Some(announced_batch.batch_hash))
+}
+
+/// Books a deposit of `amount` to the product sub-ledger, if any, and returns its product id.
+fn credit_product(product_ledger: Option<&mut ProductLedger>, amount: u64) -> Result<Option<u64>> {
+    let Some(product_ledger) = product_ledger else {
+        return Ok(None);
+    };
+
+    product_ledger.balance = product_ledger
+        .balance
+        .checked_add(amount)
+        .map(Ok)
+        .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+    product_ledger.total_deposited = product_ledger
+        .total_deposited
+        .checked_add(amount)
+        .map(Ok)
+        .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+
+    Ok(Some(product_ledger.product_id))
+}
+
+/// Books a payout of `amount` to the product sub-ledger, if any, and returns its product id.
+/// Fails with `NotEnoughBalance` if the product has not got that much.
+fn debit_product(product_ledger: Option<&mut ProductLedger>, amount: u64) -> Result<Option<u64>> {
+    let Some(product_ledger) = product_ledger else {
+        return Ok(None);
+    };
+
+    product_ledger.balance = product_ledger
+        .balance
+        .checked_sub(amount)
+        .map(Ok)
+        .unwrap_or(Err(Errors::NotEnoughBalance))?;
+    product_ledger.total_paid_out = product_ledger
+        .total_paid_out
+        .checked_add(amount)
+        .map(Ok)
+        .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+
+    Ok(Some(product_ledger.product_id))
+}
+
+/// Fails with `AddressBlocked` if the `blocked` PDA of `address` has been created.
+/// `BlockedAddressHitEvent` is emitted first, so the attempt stays visible in the logs of the failed transaction.
+fn require_not_blocked(blocked: &AccountInfo, address: Pubkey) -> Result<()> {
+    if pda_exists(blocked) {
+        let clock = get_clock()?;
+
+        emit!(BlockedAddressHitEvent {
+            address,
+            time: clock.unix_timestamp,
+        });
+
+        return Err(Errors::AddressBlocked.into());
+    }
+    Ok(())
+}
+
+/// Fills the newly created `master` and `vault_config` of vault `vault_id` and records the vault in `vault_registry`.
+fn create_vault(
+    master: &mut Account<Master>,
+    vault_config: &mut VaultConfig,
+    vault_registry: &mut VaultRegistry,
+    vault_id: u64,
+    admin: Pubkey,
+    operator: Pubkey,
+    metadata: VaultMetadata,
+) -> Result<()> {
+    master.vault_id = vault_id;
+    master.operator = operator;
+    master.admin = admin;
+    master.metadata = metadata;
+    master.version = MASTER_VERSION;
+
+    vault_config.master = master.key();
+
+    require!(
+        vault_registry.vaults.len() < MAX_VAULTS,
+        Errors::TooManyVaults
+    );
+
+    let clock = get_clock()?;
+
+    vault_registry.vaults.push(VaultEntry {
+        vault_id,
+        master: master.key(),
+        created_at: clock.unix_timestamp,
+    });
+
+    emit!(VaultCreatedEvent {
+        vault_id,
+        master: master.key(),
+        admin,
+        metadata,
+        time: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Deserializes the master deployed before vaults behind `legacy_master`, failing if it is not owned by the program.
+fn load_legacy_master(legacy_master: &AccountInfo) -> Result<LegacyMaster> {
+    require_keys_eq!(
+        *legacy_master.owner,
+        crate::ID,
+        ErrorCode::AccountOwnedByWrongProgram
+    );
+    LegacyMaster::try_from_data(&legacy_master.try_borrow_data()?)
+}
+
+/// Closes the master deployed before vaults, moving its lamports to `destination`.
+fn close_legacy_master(legacy_master: &AccountInfo, destination: &AccountInfo) -> Result<()> {
+    destination.add_lamports(legacy_master.lamports())?;
+    legacy_master.sub_lamports(legacy_master.lamports())?;
+    legacy_master.realloc(0, false)?;
+    legacy_master.assign(&System::id());
+    Ok(())
+}
+
+/// Deserializes the `UserState` behind `user_state` if that PDA has been created.
+fn load_user_state(user_state: &AccountInfo) -> Result<Option<UserState>> {
+    if !pda_exists(user_state) {
+        return Ok(None);
+    }
+    let data = user_state.try_borrow_data()?;
+    Ok(Some(UserState::try_deserialize(&mut &data[..])?))
+}
+
+/// Fails with `UserFrozen` if the `UserState` behind `user_state` has been frozen.
+fn require_not_frozen(user_state: &AccountInfo) -> Result<()> {
+    if let Some(state) = load_user_state(user_state)? {
+        require!(!state.frozen, Errors::UserFrozen);
+    }
+    Ok(())
+}
+
+/// Fails with `OperatorAlive` unless more than `dead_man_period` has passed at `now` since `heartbeat_time`.
+fn require_operator_silent(heartbeat_time: i64, dead_man_period: i64, now: i64) -> Result<()> {
+    let silent_for = now
+        .checked_sub(heartbeat_time)
+        .map(Ok)
+        .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+    require!(silent_for > dead_man_period, Errors::OperatorAlive);
+    Ok(())
+}
+
+/// Returns the amount the user behind `self_withdrawal` has withdrawn with `self_withdraw` against `balance_root`, 0 if
+/// it has never self-withdrawn or only against another root.
+fn self_withdrawn_against(self_withdrawal: &AccountInfo, balance_root: &[u8; 32]) -> Result<u64> {
+    if !pda_exists(self_withdrawal) {
+        return Ok(0);
+    }
+    let data = self_withdrawal.try_borrow_data()?;
+    let self_withdrawal = SelfWithdrawal::try_deserialize(&mut &data[..])?;
+    Ok(if &self_withdrawal.balance_root == balance_root {
+        self_withdrawal.withdrawn
+    } else {
+        0
+    })
+}
+
+/// Closes the withdraw `request` a payout of `amount` fulfills, if any, returning its rent to `user`, and moves the
+/// head of `queue` past it. The request has to be at the head of the queue unless it is prioritized. Returns the
+/// address of the request.
+fn fulfill_request<'info>(
+    request: Option<&Account<'info, WithdrawRequest>>,
+    queue: Option<&mut Account<'info, WithdrawQueue>>,
+    amount: u64,
+    user: AccountInfo<'info>,
+) -> Result<Option<Pubkey>> {
+    let Some(request) = request else {
+        return Ok(None);
+    };
+    let Some(queue) = queue else {
+        return err!(Errors::WithdrawRequestMismatch);
+    };
+    require!(request.amount == amount, Errors::WithdrawRequestMismatch);
+    require!(!request.disputed, Errors::WithdrawRequestDisputed);
+    require!(
+        request.sequence == queue.head || request.priority > 0,
+        Errors::NotQueueHead
+    );
+    require!(
+        get_clock()?.unix_timestamp < request.expires_at,
+        Errors::WithdrawRequestExpired
+    );
+
+    // A prioritized request paid ahead of the head is skipped with `skip_closed_request` once the head reaches it.
+    if request.sequence == queue.head {
+        queue.head += 1;
+    }
+    request.close(user)?;
+    Ok(Some(request.key()))
+}
+
+/// Fills the `receipt` of a payout of `amount` of SOL, or of `mint` if passed, to `receiver` keyed by `request_id`.
+/// The receipt is created by the account constraints, so a second payout with the same id fails. It has to be passed
+/// exactly when a request id is.
+fn write_receipt(
+    receipt: Option<&mut Receipt>,
+    master: Pubkey,
+    request_id: Option<u64>,
+    receiver: Pubkey,
+    mint: Option<Pubkey>,
+    amount: u64,
+    clock: &Clock,
+) -> Result<()> {
+    match (receipt, request_id) {
+        (Some(receipt), Some(request_id)) => {
+            receipt.master = master;
+            receipt.request_id = request_id;
+            receipt.receiver = receiver;
+            receipt.mint = mint;
+            receipt.amount = amount;
+            receipt.slot = clock.slot;
+            receipt.paid_at = clock.unix_timestamp;
+            Ok(())
+        }
+        (None, None) => Ok(()),
+        _ => err!(Errors::ReceiptMismatch),
+    }
+}
+
+/// Closes the `receipt` created for a payout which has been skipped, so the payout can be retried with the same
+/// request id once payouts resume.
+fn discard_receipt<'info>(
+    receipt: Option<&Account<'info, Receipt>>,
+    operator: AccountInfo<'info>,
+) -> Result<()> {
+    match receipt {
+        Some(receipt) => receipt.close(operator),
+        None => Ok(()),
+    }
+}
+
+/// Posts the `memo` of a payout, if any, with the SPL Memo program.
+fn post_memo<'info>(
+    memo_program: Option<&Program<'info, Memo>>,
+    memo: &Option<String>,
+) -> Result<()> {
+    let Some(memo) = memo else {
+        return Ok(());
+    };
+    require!(memo.len() <= MAX_MEMO_LEN, Errors::MemoTooLong);
+    let Some(memo_program) = memo_program else {
+        return err!(Errors::MemoProgramMissing);
+    };
+
+    build_memo(
+        CpiContext::new(memo_program.to_account_info(), BuildMemo {}),
+        memo.as_bytes(),
+    )
+}
+
+/// Nets the outstanding penalty in `penalty_ledger`, if it has been created, against a `reimbursement` of the operator
+/// and returns the amount left to pay out.
+fn net_penalty(penalty_ledger: &AccountInfo, reimbursement: u64) -> Result<u64> {
+    if !pda_exists(penalty_ledger) {
+        return Ok(reimbursement);
+    }
+
+    let mut data = penalty_ledger.try_borrow_mut_data()?;
+    let mut ledger = PenaltyLedger::try_deserialize(&mut &data[..])?;
+    let reimbursement = ledger.net_against(reimbursement);
+    ledger.try_serialize(&mut &mut data[..])?;
+    Ok(reimbursement)
+}
+
+/// Checks that the instruction right before the current one of the transaction verifies a signature over `message`,
+/// either an Ed25519 program instruction by master.operator or a Secp256k1 program instruction by
+/// master.voucher_eth_signer.
+fn verify_voucher_signature(
+    instructions: &AccountInfo,
+    master: &Master,
+    message: &[u8],
+) -> Result<()> {
+    let current = load_current_index_checked(instructions)?;
+    let Some(previous) = current.checked_sub(1) else {
+        return err!(Errors::InvalidVoucherSignature);
+    };
+    let ix = load_instruction_at_checked(previous as usize, instructions)?;
+
+    let signed = if ix.program_id == ed25519_program::ID {
+        ed25519_signs(&ix.data, &master.operator, message)
+    } else if ix.program_id == secp256k1_program::ID {
+        master.voucher_eth_signer != [0; 20]
+            && u8::try_from(previous).is_ok_and(|index| {
+                secp256k1_signs(&ix.data, index, &master.voucher_eth_signer, message)
+            })
+    } else {
+        false
+    };
+    require!(signed, Errors::InvalidVoucherSignature);
+
+    Ok(())
+}
+
+/// Whether the Ed25519 program instruction `data` verifies a single signature of `signer` over `message`, with all
+/// of its data inside that instruction.
+fn ed25519_signs(data: &[u8], signer: &Pubkey, message: &[u8]) -> bool {
+    // num_signatures, padding and one set of offsets, which are u16 little endian values of
+    // signature_offset, signature_instruction_index, public_key_offset, public_key_instruction_index,
+    // message_data_offset, message_data_size, message_instruction_index
+    if data.len() < 16 || data[0] != 1 {
+        return false;
+    }
+    let offset = |i: usize| u16::from_le_bytes([data[2 + 2 * i], data[3 + 2 * i]]);
+    let field = |start: u16, len: usize| data.get(start as usize..start as usize + len);
+
+    // u16::MAX refers to the Ed25519 instruction itself.
+    [offset(1), offset(3), offset(6)]
+        .iter()
+        .all(|&index| index == u16::MAX)
+        && field(offset(2), 32) == Some(signer.as_ref())
+        && offset(5) as usize == message.len()
+        && field(offset(4), message.len()) == Some(message)
+}
+
+/// Whether the Secp256k1 program instruction `data`, at `index` in the transaction, verifies a single signature of
+/// `eth_address` over `message`, with all of its data inside that instruction.
+fn secp256k1_signs(data: &[u8], index: u8, eth_address: &[u8; 20], message: &[u8]) -> bool {
+    // num_signatures and one set of offsets: signature_offset (u16), signature_instruction_index (u8),
+    // eth_address_offset (u16), eth_address_instruction_index (u8), message_data_offset (u16),
+    // message_data_size (u16), message_instruction_index (u8), u16 values little endian
+    if data.len() < 12 || data[0] != 1 {
+        return false;
+    }
+    let offset = |at: usize| u16::from_le_bytes([data[at], data[at + 1]]);
+    let field = |start: u16, len: usize| data.get(start as usize..start as usize + len);
+
+    // Instruction indices are absolute, unlike the Ed25519 program there is no value for the instruction itself.
+    [data[3], data[6], data[11]].iter().all(|&ix| ix == index)
+        && field(offset(4), 20) == Some(eth_address.as_ref())
+        && offset(9) as usize == message.len()
+        && field(offset(7), message.len()) == Some(message)
+}
+
+/// Leaf of the merkle payout tree for `amount` of SOL paid to `user` in `epoch`.
+fn payout_leaf(user: &Pubkey, amount: u64, epoch: u64) -> [u8; 32] {
+    hashv(&[
+        &[0],
+        user.as_ref(),
+        &amount.to_le_bytes(),
+        &epoch.to_le_bytes(),
+    ])
+    .to_bytes()
+}
+
+/// Root of the merkle tree containing `leaf` with the sibling hashes of `proof`, from the leaf up. Each node hashes
+/// its children in sorted order, so the proof does not need to tell left from right.
+fn merkle_root(leaf: [u8; 32], proof: &[[u8; 32]]) -> [u8; 32] {
+    proof.iter().fold(leaf, |node, sibling| {
+        let (left, right) = if node <= *sibling {
+            (node, *sibling)
+        } else {
+            (*sibling, node)
+        };
+        hashv(&[&[1], &left, &right]).to_bytes()
+    })
+}
+
+/// Applies `setting` to `vault_config`, or to `vault_token` for a setting of a mint, marks the settings of the vault
+/// as changed and emits `ConfigChangedEvent` with the old and the new value. Fails if the value is out of range or
+/// inconsistent with the settings it depends on.
+fn change_config(
+    master: &mut Master,
+    vault_config: &mut VaultConfig,
+    vault_token: Option<&mut VaultToken>,
+    authority: Pubkey,
+    setting: ConfigSetting,
+) -> Result<()> {
+    require_valid_setting(master, vault_config, vault_token.as_deref(), setting)?;
+
+    let clock = get_clock()?;
+
+    let (old, mint) = match vault_config.apply_config(setting) {
+        Some(old) => (old, None),
+        None => {
+            let vault_token = vault_token
+                .map(Ok)
+                .unwrap_or(Err(Errors::VaultTokenMissing))?;
+            let old = vault_token
+                .apply_config(setting)
+                .map(Ok)
+                .unwrap_or(Err(Errors::VaultTokenMissing))?;
+            (old, Some(vault_token.mint))
+        }
+    };
+    if let ConfigSetting::DeadManPeriod(_) = setting {
+        master.heartbeat_time = clock.unix_timestamp;
+    }
+    master.bump_config_generation();
+
+    emit!(ConfigChangedEvent {
+        master: vault_config.master,
+        authority,
+        mint,
+        old,
+        new: setting,
+        config_generation: master.config_generation,
+        time: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Fails unless `setting` is in range and consistent with the settings it depends on in `vault_config`, or in
+/// `vault_token` for a setting of a mint, so the dedicated setters and `update_config` validate alike.
+fn require_valid_setting(
+    master: &Master,
+    vault_config: &VaultConfig,
+    vault_token: Option<&VaultToken>,
+    setting: ConfigSetting,
+) -> Result<()> {
+    match setting {
+        ConfigSetting::DepositsPaused(paused) | ConfigSetting::WithdrawalsPaused(paused) => {
+            require!(paused || !master.claim_mode, Errors::ClaimModeActive)
+        }
+        ConfigSetting::DepositEscrowWindow(secs)
+        | ConfigSetting::PayoutCooldown(secs)
+        | ConfigSetting::DeadManPeriod(secs)
+        | ConfigSetting::SelfWithdrawCooldown(secs) => require!(secs >= 0, Errors::InvalidWindow),
+        ConfigSetting::DepositVelocityWindowSecs(secs) => require!(
+            secs > 0
+                || (secs == 0
+                    && vault_config.deposit_velocity_max_count == 0
+                    && vault_config.deposit_velocity_max_volume == 0),
+            Errors::InvalidWindow
+        ),
+        ConfigSetting::DepositVelocityMaxCount(limit)
+        | ConfigSetting::DepositVelocityMaxVolume(limit) => require!(
+            limit == 0 || vault_config.deposit_velocity_window_secs > 0,
+            Errors::InvalidWindow
+        ),
+        ConfigSetting::MinDeposit(min_deposit) => {
+            require_valid_deposit_limits(min_deposit, vault_config.max_deposit)?
+        }
+        ConfigSetting::MaxDeposit(max_deposit) => {
+            require_valid_deposit_limits(vault_config.min_deposit, max_deposit)?
+        }
+        ConfigSetting::TokenMinDeposit(min_deposit) => {
+            if let Some(vault_token) = vault_token {
+                require_valid_deposit_limits(min_deposit, vault_token.max_deposit)?
+            }
+        }
+        ConfigSetting::TokenMaxDeposit(max_deposit) => {
+            if let Some(vault_token) = vault_token {
+                require_valid_deposit_limits(vault_token.min_deposit, max_deposit)?
+            }
+        }
+        ConfigSetting::OutflowWindowSecs(secs) => require!(secs > 0, Errors::InvalidWindow),
+        ConfigSetting::PayoutHours(payout_hours) => require!(
+            payout_hours & !ALL_PAYOUT_HOURS == 0,
+            Errors::InvalidPayoutHours
+        ),
+        ConfigSetting::ColdWallet(cold_wallet) => require!(
+            cold_wallet != Pubkey::default() || vault_config.hot_balance_threshold == 0,
+            Errors::InvalidColdWallet
+        ),
+        ConfigSetting::HotBalanceThreshold(threshold) => require!(
+            threshold == 0 || vault_config.cold_wallet != Pubkey::default(),
+            Errors::InvalidColdWallet
+        ),
+        _ => {}
+    }
+    Ok(())
+}
+
+/// Fails with `InvalidDepositLimits` if `min_deposit` exceeds `max_deposit`, a maximum of 0 being uncapped.
+fn require_valid_deposit_limits(min_deposit: u64, max_deposit: u64) -> Result<()> {
+    require!(
+        max_deposit == 0 || min_deposit <= max_deposit,
+        Errors::InvalidDepositLimits
+    );
+    Ok(())
+}
+
+/// Emits `PauseEvent` with the current pause flags of `vault_config`.
+fn emit_pause_event(authority: Pubkey, master: &Master, vault_config: &VaultConfig) -> Result<()> {
+    let clock = get_clock()?;
+
+    emit!(PauseEvent {
+        authority,
+        deposits_paused: vault_config.deposits_paused,
+        withdrawals_paused: vault_config.withdrawals_paused,
+        config_generation: master.config_generation,
+        time: clock.unix_timestamp,
+    });
+
+    Ok(())
+}
+
+/// Returns the `Clock` sysvar, or the clock injected by [`test_sysvars`] when one has been set.
+fn get_clock() -> Result<Clock> {
+    #[cfg(any(test, feature = "test-sysvars"))]
+    if let Some(clock) = test_sysvars::clock() {
+        return Ok(clock);
+    }
+    Ok(Clock::get()?)
+}
+
+/// Returns the `Rent` sysvar, or the rent injected by [`test_sysvars`] when one has been set.
+fn get_rent() -> Result<Rent> {
+    #[cfg(any(test, feature = "test-sysvars"))]
+    if let Some(rent) = test_sysvars::rent() {
+        return Ok(rent);
+    }
+    Ok(Rent::get()?)
+}
+
+/// Overrides for the `Clock` and `Rent` sysvars, so time and rent dependent logic can be
+/// unit-tested deterministically. Overrides are kept per thread.
+#[cfg(any(test, feature = "test-sysvars"))]
+pub mod test_sysvars {
+    use super::*;
+    use std::cell::RefCell;
+
+    thread_local! {
+        static CLOCK: RefCell<Option<Clock>> = const { RefCell::new(None) };
+        static RENT: RefCell<Option<Rent>> = const { RefCell::new(None) };
+    }
+
+    /// Makes the program see `clock` as the current `Clock` sysvar.
+    pub fn set_clock(clock: Clock) {
+        CLOCK.with(|c| *c.borrow_mut() = Some(clock));
+    }
+
+    /// Makes the program see `unix_timestamp` as the current time.
+    pub fn set_unix_timestamp(unix_timestamp: i64) {
+        set_clock(Clock {
+            unix_timestamp,
+            ..Clock::default()
+        });
+    }
+
+    /// Makes the program see `rent` as the current `Rent` sysvar.
+    pub fn set_rent(rent: Rent) {
+        RENT.with(|r| *r.borrow_mut() = Some(rent));
+    }
+
+    /// Removes all overrides.
+    pub fn reset() {
+        CLOCK.with(|c| *c.borrow_mut() = None);
+        RENT.with(|r| *r.borrow_mut() = None);
+    }
+
+    pub(crate) fn clock() -> Option<Clock> {
+        CLOCK.with(|c| c.borrow().clone())
+    }
+
+    pub(crate) fn rent() -> Option<Rent> {
+        RENT.with(|r| r.borrow().clone())
+    }
+}
+
+/// Whether `key` is a point on the ed25519 curve, i.e. an address a private key can exist for.
+fn is_on_curve(key: &Pubkey) -> bool {
+    #[cfg(target_os = "solana")]
+    {
+        const CURVE25519_EDWARDS: u64 = 0;
+        let mut result = 0u8;
+        // SAFETY: the syscall reads the 32 bytes of `key` and writes nothing through `result`.
+        unsafe {
+            anchor_lang::solana_program::syscalls::sol_curve_validate_point(
+                CURVE25519_EDWARDS,
+                key.as_ref().as_ptr(),
+                &mut result,
+            ) == 0
+        }
+    }
+    #[cfg(not(target_os = "solana"))]
+    {
+        key.is_on_curve()
+    }
+}
+
+/// Classifies the depositor `user`: a tagged exchange hot wallet if `exchange_wallet` exists, otherwise a PDA if
+/// `user` is off the curve, otherwise a wallet.
+fn deposit_source(user: &Pubkey, exchange_wallet: &AccountInfo) -> DepositSource {
+    if pda_exists(exchange_wallet) {
+        DepositSource::Exchange
+    } else if is_on_curve(user) {
+        DepositSource::Wallet
+    } else {
+        DepositSource::Pda
+    }
+}
+
+/// Checks that `account` is the PDA of `receiver` with the seed prefix `seed` in the vault of `master`, for accounts
+/// passed as remaining accounts.
+fn require_receiver_pda(
+    account: &AccountInfo,
+    seed: &str,
+    master: &Pubkey,
+    receiver: &Pubkey,
+) -> Result<()> {
+    let (address, _) =
+        Pubkey::find_program_address(&[seed.as_bytes(), master.as_ref(), receiver.as_ref()], &ID);
+    require_keys_eq!(account.key(), address, Errors::BatchAccountsMismatch);
+    Ok(())
+}
+
+/// Numeric code of `error`, the custom error number for errors of this program.
+fn error_code(error: &Error) -> u32 {
+    match error {
+        Error::AnchorError(error) => error.error_code_number,
+        Error::ProgramError(error) => u64::from(error.program_error.clone()) as u32,
+    }
+}
+
+/// Loads the token account a payout goes to, which can be any token account of `mint` owned by `receiver`, not only
+/// its ATA.
+fn load_receiver_token_account(
+    account: &AccountInfo,
+    receiver: &Pubkey,
+    mint: &Pubkey,
+) -> Result<TokenAccount> {
+    if account.data_is_empty() {
+        return err!(ErrorCode::AccountNotInitialized);
+    }
+    require_keys_eq!(
+        *account.owner,
+        Token::id(),
+        Errors::ReceiverTokenAccountMismatch
+    );
+
+    let token_account = TokenAccount::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+    require!(
+        token_account.owner == *receiver && token_account.mint == *mint,
+        Errors::ReceiverTokenAccountMismatch
+    );
+    Ok(token_account)
+}
+
+/// Whether `account` is a live PDA owned by this program, i.e. it has been created and not closed.
+fn pda_exists(account: &AccountInfo) -> bool {
+    account.owner == &ID && !account.data_is_empty()
+}
+
+/// Fails unless `account` is rent-exempt for its actual data length while holding `lamports`.
+fn require_rent_exempt(account: &AccountInfo, lamports: u64) -> Result<()> {
+    let minimum = get_rent()?.minimum_balance(account.data_len());
+    require!(lamports >= minimum, Errors::NotRentExempt);
+    Ok(())
+}
+
+/// Fails unless the master PDA `account` keeps the vault's `min_sol_reserve` while holding `lamports`, e.g. as an
+/// operational buffer for ATA creation and fees. It never goes below rent exemption, which a reserve of 0 falls back to.
+fn require_sol_reserve(account: &AccountInfo, min_sol_reserve: u64, lamports: u64) -> Result<()> {
+    require_rent_exempt(account, lamports)?;
+    require!(lamports >= min_sol_reserve, Errors::SolReserveRequired);
+    Ok(())
+}
+
+/// Kind of account a deposit has come from.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DepositSource {
+    /// Wallet controlled by a private key.
+    Wallet,
+    /// PDA of a program, depositing through CPI.
+    Pda,
+    /// Hot wallet of an exchange, tagged by the admin.
+    Exchange,
+}
+
+/// Tunable setting of a vault with its value, changed with `update_config`. `Token*` settings and
+/// `MaxTokenPerPayout` belong to the vault token PDA of a mint, the others to the vault config PDA.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ConfigSetting {
+    /// vault_config.deposits_paused, only cleared outside of claim mode
+    DepositsPaused(bool),
+    /// vault_config.withdrawals_paused, only cleared outside of claim mode
+    WithdrawalsPaused(bool),
+    /// vault_config.express_fee_bps, at most the highest fee of the program config
+    ExpressFeeBps(u16),
+    /// vault_config.reimburse_ata_rent
+    ReimburseAtaRent(bool),
+    /// vault_config.receiver_allowlist_enabled
+    ReceiverAllowlistEnabled(bool),
+    /// vault_config.deposit_whitelist_enabled
+    DepositWhitelistEnabled(bool),
+    /// vault_config.max_sol_per_payout
+    MaxSolPerPayout(u64),
+    /// vault_config.daily_withdraw_limit
+    DailyWithdrawLimit(u64),
+    /// vault_config.max_daily_net_inflow
+    MaxDailyNetInflow(u64),
+    /// vault_config.min_deposit
+    MinDeposit(u64),
+    /// vault_config.max_deposit
+    MaxDeposit(u64),
+    /// vault_config.user_deposit_cap
+    UserDepositCap(u64),
+    /// vault_config.vault_cap
+    VaultCap(u64),
+    /// vault_config.deposit_escrow_window, not negative
+    DepositEscrowWindow(i64),
+    /// vault_config.payout_cooldown, not negative
+    PayoutCooldown(i64),
+    /// vault_config.min_sol_reserve
+    MinSolReserve(u64),
+    /// vault_config.payout_hours, at most `ALL_PAYOUT_HOURS`
+    PayoutHours(u32),
+    /// vault_config.outflow_window_secs, positive
+    OutflowWindowSecs(i64),
+    /// vault_config.outflow_limit
+    OutflowLimit(u64),
+    /// vault_config.cold_wallet, only the default pubkey while the hot balance threshold is 0
+    ColdWallet(Pubkey),
+    /// vault_config.hot_balance_threshold, only non-zero with a cold wallet
+    HotBalanceThreshold(u64),
+    /// vault_config.deposit_velocity_window_secs, not negative
+    DepositVelocityWindowSecs(i64),
+    /// vault_config.deposit_velocity_max_count
+    DepositVelocityMaxCount(u64),
+    /// vault_config.deposit_velocity_max_volume
+    DepositVelocityMaxVolume(u64),
+    /// vault_config.dead_man_period, not negative, setting it counts as a heartbeat
+    DeadManPeriod(i64),
+    /// vault_config.self_withdraw_threshold
+    SelfWithdrawThreshold(u64),
+    /// vault_config.self_withdraw_cooldown, not negative
+    SelfWithdrawCooldown(i64),
+    /// vault_token.outflow_limit
+    TokenOutflowLimit(u64),
+    /// vault_token.max_daily_net_inflow
+    TokenMaxDailyNetInflow(u64),
+    /// vault_token.max_token_per_payout
+    MaxTokenPerPayout(u64),
+    /// vault_token.min_deposit
+    TokenMinDeposit(u64),
+    /// vault_token.max_deposit
+    TokenMaxDeposit(u64),
+    /// vault_token.user_deposit_cap
+    TokenUserDepositCap(u64),
+    /// vault_token.vault_cap
+    TokenVaultCap(u64),
+    /// vault_token.deposit_velocity_max_volume
+    TokenDepositVelocityMaxVolume(u64),
+    /// vault_token.daily_withdraw_limit
+    TokenDailyWithdrawLimit(u64),
+}
+
+/// Fiat value quoted to the user for a deposit.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct FiatQuote {
+    /// Quoted value in minor units of `currency`, e.g. cents.
+    pub value: u64,
+    /// ISO 4217 currency code, e.g. `*b"USD"`.
+    pub currency: [u8; 3],
+}
+
+/// Payout signed off chain by master.operator, which the receiver redeems with `redeem_voucher`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Voucher {
+    /// Master PDA of the vault paying the voucher.
+    pub master: Pubkey,
+    /// Wallet the voucher pays to, which has to redeem it.
+    pub receiver: Pubkey,
+    /// Lamports paid.
+    pub amount: u64,
+    /// Backend request id of the payout, the voucher creates the receipt PDA of it.
+    pub nonce: u64,
+    /// Unix timestamp from which the voucher can no longer be redeemed.
+    pub expiry: i64,
+}
+
+/// Volume moved within a rolling time window.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct RateWindow {
+    /// When does the current window has started.
+    pub start: i64,
+    /// Volume moved since `start`.
+    pub amount: u64,
+}
+
+impl RateWindow {
+    /// Adds `amount` at `now`, starting a new window once `window_secs` have passed, and returns the window volume.
+    /// A window covers `[start, start + window_secs)`: `now == start + window_secs` already starts the next one.
+    /// The window is left untouched if the volume would overflow.
+    pub fn record(&mut self, now: i64, window_secs: i64, amount: u64) -> Result<u64> {
+        let (start, current) = if now.saturating_sub(self.start) >= window_secs {
+            (now, 0)
+        } else {
+            (self.start, self.amount)
+        };
+
+        self.amount = current
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        self.start = start;
+        Ok(self.amount)
+    }
+}
+
+/// Seconds in a day, the period of net inflow limits.
+pub const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Seconds in an hour, the granularity of payout hours.
+pub const SECONDS_PER_HOUR: i64 = 3_600;
+
+/// Payout hours with a bit for each hour of the day.
+pub const ALL_PAYOUT_HOURS: u32 = (1 << 24) - 1;
+
+/// Net inflow of an asset during one day.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct NetFlow {
+    /// Day (unix timestamp / `SECONDS_PER_DAY`) the net inflow belongs to.
+    pub day: i64,
+    /// Deposits minus outflows during `day`, negative if more has left than came in.
+    pub net_inflow: i64,
+}
+
+impl NetFlow {
+    /// Adds `delta` at `now`, starting over on a new day, and returns the net inflow of the day.
+    /// Days start at multiples of `SECONDS_PER_DAY` in UTC, the first second of a day already belongs to it.
+    /// Fails if a positive `delta` would overflow, negative ones saturate.
+    pub fn record(&mut self, now: i64, delta: i64) -> Result<i64> {
+        let day = now.div_euclid(SECONDS_PER_DAY);
+        let current = if day == self.day { self.net_inflow } else { 0 };
+
+        let net_inflow = if delta >= 0 {
+            current
+                .checked_add(delta)
+                .map(Ok)
+                .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?
+        } else {
+            current.saturating_add(delta)
+        };
+
+        self.day = day;
+        self.net_inflow = net_inflow;
+        Ok(net_inflow)
+    }
+}
+
+/// Lifetime totals of an asset of the vault, for reconciliation per asset.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct LifetimeStats {
+    /// Sum of all deposits.
+    pub total_deposited: u64,
+    /// Sum of all payouts and admin withdraws.
+    pub total_withdrawn: u64,
+    /// Number of deposits.
+    pub deposit_count: u64,
+    /// Number of payouts and admin withdraws.
+    pub withdraw_count: u64,
+}
+
+impl LifetimeStats {
+    /// Adds a deposit of `amount`. Saturates instead of failing, so statistics never block a deposit.
+    pub fn record_deposit(&mut self, amount: u64) {
+        self.total_deposited = self.total_deposited.saturating_add(amount);
+        self.deposit_count = self.deposit_count.saturating_add(1);
+    }
+
+    /// Adds a payout or admin withdraw of `amount`. Saturates instead of failing, so statistics never block a payout.
+    pub fn record_withdrawal(&mut self, amount: u64) {
+        self.total_withdrawn = self.total_withdrawn.saturating_add(amount);
+        self.withdraw_count = self.withdraw_count.saturating_add(1);
+    }
+}
+
+/// Event of some deposit.
+#[event]
+pub struct DepositEvent {
+    /// User which has deposited something.
+    pub user: Pubkey,
+    /// The account the deposit has been placed to.
+    pub holder: Pubkey,
+    /// Amount of SOL or token.
+    pub amount: u64,
+    /// Decimals of the mint, `SOL_DECIMALS` for SOL.
+    pub decimals: u8,
+    /// `amount` in whole SOL or tokens, as wallets show it.
+    pub ui_amount: String,
+    /// When does the deposit event has happened.
+    pub time: i64,
+    /// Fiat value quoted to the user, if the frontend has passed one.
+    pub quote: Option<FiatQuote>,
+    /// Product the deposit has been booked to, if any.
+    pub product_id: Option<u64>,
+    /// Kind of account the deposit has come from.
+    pub source: DepositSource,
+    /// Config generation of the vault the deposit has been accepted under.
+    pub config_generation: u64,
+}
+
+/// Event of a donation, funds added to the vault without crediting any user.
+#[event]
+pub struct DonationEvent {
+    /// Wallet which has donated.
+    pub donor: Pubkey,
+    /// The account the donation has been placed to.
+    pub holder: Pubkey,
+    /// Amount of SOL or token.
+    pub amount: u64,
+    /// Decimals of the mint, `SOL_DECIMALS` for SOL.
+    pub decimals: u8,
+    /// `amount` in whole SOL or tokens, as wallets show it.
+    pub ui_amount: String,
+    /// When does the donation has happened.
+    pub time: i64,
+}
+
+/// Event of a user requesting a payout on-chain.
+#[event]
+pub struct WithdrawRequestedEvent {
+    /// Withdraw request PDA.
+    pub request: Pubkey,
+    /// User which has requested the payout.
+    pub user: Pubkey,
+    /// Position of the request in the withdraw queue.
+    pub sequence: u64,
+    /// Mint of the requested token, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Requested amount of SOL or token.
+    pub amount: u64,
+    /// Unix timestamp after which the request can no longer be paid.
+    pub expires_at: i64,
+    /// When does the request has been made.
+    pub time: i64,
+}
+
+/// Event of the merkle root of an epoch's payouts being posted.
+#[event]
+pub struct PayoutRootPostedEvent {
+    /// Payout root PDA of the epoch.
+    pub payout_root: Pubkey,
+    /// Epoch of the payouts.
+    pub epoch: u64,
+    /// Merkle root of the (user, amount, epoch) entries.
+    pub root: [u8; 32],
+    /// Highest amount of SOL which can be claimed against the root.
+    pub total: u64,
+    /// When does the root has been posted.
+    pub time: i64,
+}
+
+/// Event of a payout being scheduled for later execution.
+#[event]
+pub struct PayoutScheduledEvent {
+    /// Scheduled payout PDA.
+    pub scheduled_payout: Pubkey,
+    /// Wallet the payout goes to.
+    pub receiver: Pubkey,
+    /// Scheduled amount of SOL.
+    pub amount: u64,
+    /// Unix timestamp from which the payout can be executed.
+    pub execute_after: i64,
+    /// When does the payout has been scheduled.
+    pub time: i64,
+}
+
+/// Event of a token payout creating the ATA of its receiver.
+#[event]
+pub struct ReceiverAtaCreatedEvent {
+    /// Wallet the ATA belongs to.
+    pub receiver: Pubkey,
+    /// Created ATA.
+    pub ata: Pubkey,
+    /// Mint of the ATA.
+    pub mint: Pubkey,
+    /// Rent of the ATA in lamports, paid by the operator.
+    pub rent: u64,
+    /// Lamports reimbursed to the operator from the SOL balance.
+    pub reimbursed: u64,
+    /// When does the ATA has been created.
+    pub time: i64,
+}
+
+/// Event of a batch payout being skipped because it failed its checks.
+#[event]
+pub struct BatchPayoutSkippedEvent {
+    /// Wallet which has not been paid.
+    pub receiver: Pubkey,
+    /// Mint of the token, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Skipped amount of SOL or token.
+    pub amount: u64,
+    /// Code of the error the payout has failed with.
+    pub error_code: u32,
+    /// When does the payout has been skipped.
+    pub time: i64,
+}
+
+/// Event of a pending withdraw request being closed unfulfilled, cancelled by its user or expired.
+#[event]
+pub struct WithdrawCancelledEvent {
+    /// Withdraw request PDA, closed by the cancellation.
+    pub request: Pubkey,
+    /// User which has requested the payout.
+    pub user: Pubkey,
+    /// Mint of the requested token, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Requested amount of SOL or token.
+    pub amount: u64,
+    /// Whether the request has expired, `false` if the user has cancelled it.
+    pub expired: bool,
+    /// When does the cancellation has happened.
+    pub time: i64,
+}
+
+/// Event of a dispute on a withdraw request being opened or closed.
+#[event]
+pub struct WithdrawDisputeEvent {
+    /// Withdraw request PDA.
+    pub request: Pubkey,
+    /// User which has requested the payout.
+    pub user: Pubkey,
+    /// Operator or admin which has opened or closed the dispute.
+    pub authority: Pubkey,
+    /// Whether the dispute has been opened, `false` if it has been closed.
+    pub disputed: bool,
+    /// When does the change has happened.
+    pub time: i64,
+}
+
+/// Event of a payout batch being announced.
+#[event]
+pub struct BatchAnnouncedEvent {
+    /// Announced batch PDA.
+    pub batch: Pubkey,
+    /// Hash of the approved payout plan.
+    pub batch_hash: [u8; 32],
+    /// Mint the batch pays, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Highest amount the batch pays out.
+    pub total_amount: u64,
+    /// When does the announcement has happened.
+    pub time: i64,
+}
+
+/// Event of the priority tier of a withdraw request being set.
+#[event]
+pub struct WithdrawPriorityEvent {
+    /// Withdraw request PDA.
+    pub request: Pubkey,
+    /// User which has requested the payout.
+    pub user: Pubkey,
+    /// Position of the request in the withdraw queue.
+    pub sequence: u64,
+    /// Priority tier of the request, 0 for normal requests.
+    pub priority: u8,
+    /// When does the change has happened.
+    pub time: i64,
+}
+
+/// Event of operator payouts using up the daily withdraw limit of a vault.
+#[event]
+pub struct DailyWithdrawLimitReachedEvent {
+    /// The daily withdraw limit.
+    pub limit: u64,
+    /// Mint of the limited payouts, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// When does the current 24 hour window has started.
+    pub window_start: i64,
+    /// When does the limit has been reached.
+    pub time: i64,
+}
+
+/// Event of deposits being paused because their velocity has exceeded a threshold.
+#[event]
+pub struct DepositVelocityAlertEvent {
+    /// User whose rejected deposit has tripped the threshold.
+    pub user: Pubkey,
+    /// Amount of SOL or token which has been attempted.
+    pub amount: u64,
+    /// Mint of the deposit, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Deposits within the current window, including the attempted one.
+    pub window_count: u64,
+    /// Volume of the asset within the current window, including the attempted deposit.
+    pub window_volume: u64,
+    /// Configured count threshold of the window.
+    pub max_count: u64,
+    /// Configured volume threshold of the asset.
+    pub max_volume: u64,
+    /// Config generation of the vault after the pause.
+    pub config_generation: u64,
+    /// When does the pause has happened.
+    pub time: i64,
+}
+
+/// Event of the admin setting the lifetime deposit cap of a user.
+#[event]
+pub struct UserDepositCapEvent {
+    /// User the cap applies to.
+    pub user: Pubkey,
+    /// Mint of the capped asset, the default pubkey for SOL.
+    pub asset: Pubkey,
+    /// Cap of the lifetime deposits, 0 if the cap of the asset applies.
+    pub cap: u64,
+    /// Lifetime deposits of the user so far.
+    pub deposited: u64,
+    /// When does the change has happened.
+    pub time: i64,
+}
+
+/// Event of a withdraw.
+#[event]
+pub struct WithdrawEvent {
+    /// User which has withdrawn something.
+    pub user: Pubkey,
+    /// The account the withdraw has been taken tokens from.
+    pub holder: Pubkey,
+    /// Amount of SOL or token.
+    pub amount: u64,
+    /// Decimals of the mint, `SOL_DECIMALS` for SOL.
+    pub decimals: u8,
+    /// `amount` in whole SOL or tokens, as wallets show it.
+    pub ui_amount: String,
+    /// When does the withdraw event has happened.
+    pub time: i64,
+    /// Product the payout has been booked to, if any.
+    pub product_id: Option<u64>,
+    /// Withdraw request PDA the payout has fulfilled, if any.
+    pub request: Option<Pubkey>,
+    /// Express fee retained by the vault from `amount`, 0 for regular payouts.
+    pub fee: u64,
+    /// Amount the user has received, `amount` minus `fee`.
+    pub net_amount: u64,
+    /// Memo posted with the payout, if any.
+    pub memo: Option<String>,
+    /// Reference of the payout in the backend, e.g. its payout id, if any.
+    pub reference: Option<[u8; 32]>,
+    /// Hash of the announced batch the payout belongs to, if any.
+    pub batch: Option<[u8; 32]>,
+    /// Config generation of the vault the payout has been made under.
+    pub config_generation: u64,
+}
+
+/// Event of a deposit being refunded to its depositor.
+#[event]
+pub struct RefundEvent {
+    /// Backend id of the refunded deposit.
+    pub deposit_id: u64,
+    /// User which has deposited and got the refund.
+    pub user: Pubkey,
+    /// The account the refund has been taken from.
+    pub holder: Pubkey,
+    /// Amount of SOL.
+    pub amount: u64,
+    /// Decimals of SOL, `SOL_DECIMALS`.
+    pub decimals: u8,
+    /// `amount` in whole SOL, as wallets show it.
+    pub ui_amount: String,
+    /// When does the refund has happened.
+    pub time: i64,
+    /// Config generation of the vault the refund has been made under.
+    pub config_generation: u64,
+}
+
+/// Event of a deposit being placed in escrow.
+#[event]
+pub struct DepositEscrowedEvent {
+    /// User which has deposited.
+    pub user: Pubkey,
+    /// The account the deposit is merged into once released.
+    pub holder: Pubkey,
+    /// Escrow PDA holding the deposit.
+    pub escrow: Pubkey,
+    /// Amount of SOL.
+    pub amount: u64,
+    /// Decimals of SOL, `SOL_DECIMALS`.
+    pub decimals: u8,
+    /// `amount` in whole SOL, as wallets show it.
+    pub ui_amount: String,
+    /// Unix timestamp the deposit can be released from.
+    pub release_after: i64,
+    /// When does the deposit has happened.
+    pub time: i64,
+    /// Kind of account the deposit has come from.
+    pub source: DepositSource,
+    /// Config generation of the vault the deposit has been made under.
+    pub config_generation: u64,
+}
+
+/// Event of an escrowed deposit being sent back to its user.
+#[event]
+pub struct DepositRejectedEvent {
+    /// User which has deposited and got the deposit back.
+    pub user: Pubkey,
+    /// The account the deposit would have been merged into.
+    pub holder: Pubkey,
+    /// Escrow PDA which has held the deposit.
+    pub escrow: Pubkey,
+    /// Amount of SOL.
+    pub amount: u64,
+    /// When does the rejection has happened.
+    pub time: i64,
+    /// Config generation of the vault the rejection has been made under.
+    pub config_generation: u64,
+}
+
+/// Event of the dead man switch switching a vault to claim mode.
+#[event]
+pub struct ClaimModeEvent {
+    /// Account which has triggered the switch.
+    pub triggered_by: Pubkey,
+    /// Master PDA of the vault.
+    pub holder: Pubkey,
+    /// Published balance root users claim against.
+    pub balance_root: [u8; 32],
+    /// Highest amount of SOL which can be claimed.
+    pub total: u64,
+    /// Unix timestamp of the last payout or heartbeat of the operator.
+    pub last_heartbeat: i64,
+    /// When does the switch has happened.
+    pub time: i64,
+}
+
+/// Event of admin withdrawal.
+#[event]
+pub struct AdminWithdrawEvent {
+    /// User which has withdrawn something.
+    pub user: Pubkey,
+    /// The account the withdraw has been taken tokens from.
+    pub holder: Pubkey,
+    /// Amount of SOL or token.
+    pub amount: u64,
+    /// Decimals of the mint, `SOL_DECIMALS` for SOL.
+    pub decimals: u8,
+    /// `amount` in whole SOL or tokens, as wallets show it.
+    pub ui_amount: String,
+    /// When does the withdraw event has happened.
+    pub time: i64,
+}
+
+/// Event of the operator being revoked in an emergency.
+#[event]
+pub struct OperatorsRevokedEvent {
+    /// Admin or guardian which has revoked the operator.
+    pub authority: Pubkey,
+    /// Operator which has been revoked.
+    pub operator: Pubkey,
+    /// When does the revocation has happened.
+    pub time: i64,
+}
+
+/// Event of a tunable of a vault being changed, by the admin or guardian, or by a limit tripping.
+#[event]
+pub struct ConfigChangedEvent {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Admin or guardian which has changed the setting, or the signer whose payout or deposit has tripped a limit.
+    pub authority: Pubkey,
+    /// Mint of the vault token PDA changed, `None` for a setting of the vault config PDA.
+    pub mint: Option<Pubkey>,
+    /// Setting with its value before the change.
+    pub old: ConfigSetting,
+    /// Setting with its value after the change.
+    pub new: ConfigSetting,
+    /// Config generation of the vault after the change.
+    pub config_generation: u64,
+    /// When does the change has happened.
+    pub time: i64,
+}
+
+/// Event of deposits or payouts being paused or unpaused.
+#[event]
+pub struct PauseEvent {
+    /// Admin or guardian which has updated the pause flags.
+    pub authority: Pubkey,
+    /// Whether deposits are paused now.
+    pub deposits_paused: bool,
+    /// Whether operator payouts are paused now.
+    pub withdrawals_paused: bool,
+    /// Config generation of the vault after the update.
+    pub config_generation: u64,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of a receiver being added to or removed from the payout allowlist.
+#[event]
+pub struct ReceiverAllowlistEvent {
+    /// Receiver which has been updated.
+    pub receiver: Pubkey,
+    /// Whether the receiver is allowed to get payouts now.
+    pub allowed: bool,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of a receiver being exempted from or subjected to payout limits again.
+#[event]
+pub struct LimitExemptionEvent {
+    /// Receiver which has been updated.
+    pub receiver: Pubkey,
+    /// Whether payouts to the receiver bypass the payout limits now.
+    pub exempt: bool,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of an address being blocked or unblocked.
+#[event]
+pub struct AddressBlockedEvent {
+    /// Address which has been updated.
+    pub address: Pubkey,
+    /// Whether the address is blocked now.
+    pub blocked: bool,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of a blocked address trying to deposit or receive a payout.
+#[event]
+pub struct BlockedAddressHitEvent {
+    /// Blocked address.
+    pub address: Pubkey,
+    /// When does the attempt has happened.
+    pub time: i64,
+}
+
+/// Event of a user being added to or removed from the deposit whitelist.
+#[event]
+pub struct DepositWhitelistEvent {
+    /// User which has been updated.
+    pub user: Pubkey,
+    /// Whether the user is allowed to deposit now.
+    pub whitelisted: bool,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of a user being frozen or unfrozen.
+#[event]
+pub struct UserFrozenEvent {
+    /// User which has been updated.
+    pub user: Pubkey,
+    /// Whether the user is frozen now.
+    pub frozen: bool,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of SOL or token moved between two vaults.
+#[event]
+pub struct VaultTransferEvent {
+    /// Id of the vault the funds have left.
+    pub from_vault: u64,
+    /// Id of the vault the funds have arrived at.
+    pub to_vault: u64,
+    /// Mint of the transfer, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Amount of SOL or token which has been moved.
+    pub amount: u64,
+    /// Decimals of the mint, `SOL_DECIMALS` for SOL.
+    pub decimals: u8,
+    /// `amount` in whole SOL or tokens, as wallets show it.
+    pub ui_amount: String,
+    /// When does the transfer has happened.
+    pub time: i64,
+}
+
+/// Event of SOL of a vault being wrapped into or unwrapped from wSOL.
+#[event]
+pub struct SolWrapEvent {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Amount of SOL which has been wrapped or unwrapped.
+    pub amount: u64,
+    /// Whether the SOL has been wrapped, `false` if it has been unwrapped.
+    pub wrapped: bool,
+    /// When does the wrap has happened.
+    pub time: i64,
+}
+
+/// Event of the lifetime statistics of an asset of a vault.
+#[event]
+pub struct StatsEvent {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Mint of the asset, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Tracked balance of the asset.
+    pub balance: u64,
+    /// Lifetime totals of the asset.
+    pub lifetime: LifetimeStats,
+    /// When have the statistics been taken.
+    pub time: i64,
+}
+
+/// Event of untracked SOL of master PDA being credited to the tracked balance.
+#[event]
+pub struct SweepEvent {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Amount of SOL which has been credited.
+    pub amount: u64,
+    /// Tracked SOL balance of the vault after the sweep.
+    pub balance: u64,
+    /// When does the sweep has happened.
+    pub time: i64,
+}
+
+/// Event of the SOL above the hot balance threshold being moved to the cold wallet.
+#[event]
+pub struct SweepToColdEvent {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Cold wallet which has received the SOL.
+    pub cold_wallet: Pubkey,
+    /// Amount of SOL which has been moved.
+    pub amount: u64,
+    /// Tracked SOL balance of the vault after the sweep.
+    pub balance: u64,
+    /// When does the sweep has happened.
+    pub time: i64,
+}
+
+/// Event of the accounting currency of a vault being declared.
+#[event]
+pub struct AccountingCurrencyEvent {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// ISO 4217 code of the accounting currency.
+    pub currency: [u8; 3],
+    /// Decimals amounts in the accounting currency are normalized to.
+    pub decimals: u8,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of stray tokens being rescued from a token account of master PDA.
+#[event]
+pub struct TokenRescuedEvent {
+    /// Mint of the rescued tokens.
+    pub mint: Pubkey,
+    /// Token account of master PDA the tokens have been taken from.
+    pub token_account: Pubkey,
+    /// Amount of tokens sent to master.admin.
+    pub amount: u64,
+    /// When does the rescue has happened.
+    pub time: i64,
+}
+
+/// Event of a new vault being created.
+#[event]
+pub struct VaultCreatedEvent {
+    /// Id of the vault.
+    pub vault_id: u64,
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Admin of the vault.
+    pub admin: Pubkey,
+    /// Label of the vault.
+    pub metadata: VaultMetadata,
+    /// When does the vault has been created.
+    pub time: i64,
+}
+
+/// Event of a master account being migrated to the current layout.
+#[event]
+pub struct MasterMigratedEvent {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Layout version before the migration.
+    pub from_version: u8,
+    /// Layout version after the migration.
+    pub to_version: u8,
+    /// When does the migration has happened.
+    pub time: i64,
+}
+
+/// Event of a balance of the master deployed before vaults being moved to a vault.
+#[event]
+pub struct LegacyMigratedEvent {
+    /// Master PDA deployed before vaults, at seeds `[MASTER_SEED]`.
+    pub legacy_master: Pubkey,
+    /// Master PDA of the vault the balance has moved to.
+    pub master: Pubkey,
+    /// Mint of the moved tokens, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Moved amount.
+    pub amount: u64,
+    /// When does the migration has happened.
+    pub time: i64,
+}
+
+/// Event of the label of a vault being updated.
+#[event]
+pub struct VaultMetadataEvent {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// New label of the vault.
+    pub metadata: VaultMetadata,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of the program config being updated.
+#[event]
+pub struct ProgramConfigEvent {
+    /// Highest fee any vault may charge, in basis points.
+    pub max_fee_bps: u16,
+    /// Whether deposits and payouts of every vault are stopped.
+    pub paused: bool,
+    /// Mints vaults may hold.
+    pub allowed_mints: Vec<Pubkey>,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of a payout to a canary receiver, which has stopped all payouts.
+#[event]
+pub struct CanaryTriggeredEvent {
+    /// Canary receiver of the payout.
+    pub receiver: Pubkey,
+    /// Operator which has sent the payout.
+    pub operator: Pubkey,
+    /// Amount of SOL or token which has been attempted.
+    pub amount: u64,
+    /// When does the attempt has happened.
+    pub time: i64,
+}
+
+/// Event of a mint being added to, disabled in or removed from the vault.
+#[event]
+pub struct SupportedMintEvent {
+    /// Mint which has been updated.
+    pub mint: Pubkey,
+    /// Whether deposits of the mint are accepted now.
+    pub enabled: bool,
+    /// Whether the mint has been removed from the vault.
+    pub removed: bool,
+    /// When does the update has happened.
+    pub time: i64,
+}
+
+/// Event of the circuit breaker pausing payouts because of excessive outflow.
+#[event]
+pub struct CircuitBreakerTrippedEvent {
+    /// Operator whose payout has tripped the breaker.
+    pub operator: Pubkey,
+    /// Amount of SOL or token which has been attempted.
+    pub amount: u64,
+    /// Amount already paid out within the current window.
+    pub window_amount: u64,
+    /// Configured limit of the window.
+    pub limit: u64,
+    /// Mint of the payout, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// When does the breaker has tripped.
+    pub time: i64,
+}
+
+/// Event of an operator fault being recorded in the penalty ledger.
+#[event]
+pub struct OperatorFaultEvent {
+    /// Operator which has made the fault.
+    pub operator: Pubkey,
+    /// Penalty amount of the fault.
+    pub amount: u64,
+    /// Penalty still to be netted against reimbursements.
+    pub outstanding: u64,
+    /// Why the fault has been recorded.
+    pub reason: String,
+    /// When does the fault has been recorded.
+    pub time: i64,
+}
+
+const PROGRAM_CONFIG_SIZE: usize = 8 + 32 + 2 + 1 + 4 + 32 * MAX_ALLOWED_MINTS;
+/// `ProgramConfig` account, which holds protocol-wide guardrails vault admins cannot exceed.
+#[account]
+pub struct ProgramConfig {
+    /// Super admin which is allowed to manage the program config.
+    pub super_admin: Pubkey,
+    /// Highest fee any vault may charge, in basis points.
+    pub max_fee_bps: u16,
+    /// Kill switch stopping deposits and payouts of every vault.
+    pub paused: bool,
+    /// Mints vaults may hold, at most `MAX_ALLOWED_MINTS`.
+    pub allowed_mints: Vec<Pubkey>,
+}
+
+/// Label of a vault for indexers and dashboards. Both fields are UTF-8, padded with zero bytes.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Default, PartialEq, Eq, Debug)]
+pub struct VaultMetadata {
+    /// Display name of the vault.
+    pub name: [u8; 32],
+    /// Code of the product the vault serves.
+    pub product_code: [u8; 8],
+}
+
+/// Vault as recorded in the vault registry.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub struct VaultEntry {
+    /// Id of the vault.
+    pub vault_id: u64,
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// When does the vault has been created.
+    pub created_at: i64,
+}
+
+const VAULT_REGISTRY_SIZE: usize = 8 + 4 + (8 + 32 + 8) * MAX_VAULTS;
+/// `VaultRegistry` account, which enumerates all vaults of the deployment.
+#[account]
+pub struct VaultRegistry {
+    /// Created vaults in creation order, at most `MAX_VAULTS`.
+    pub vaults: Vec<VaultEntry>,
+}
+
+/// Layout of the single master account deployed before vaults and versioning, at seeds `[MASTER_SEED]`. It is read
+/// by `migrate_legacy_master` and `migrate_legacy_token`, which move its balances into a vault.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default, Debug, PartialEq, Eq)]
+pub struct LegacyMaster {
+    /// Solana stored in the smart contract.
+    pub balance: u64,
+    /// Tokens stored into the PDA of the smart contract.
+    pub token_balance: u64,
+    /// Associated token account for the master.
+    pub token_account: Option<Pubkey>,
+    /// Last time some withdraw has happen.
+    pub last_withdraw_time: i64,
+    /// Operator which is allowed to transfer token.
+    pub operator: Pubkey,
+    /// Admin which is allowed to manage the smart contract.
+    pub admin: Pubkey,
+}
+
+impl LegacyMaster {
+    /// Deserializes the account `data` of a legacy master, discriminator included. It shares the discriminator of
+    /// `Master`, the data is only read past it.
+    pub fn try_from_data(data: &[u8]) -> Result<Self> {
+        require!(
+            data.len() >= 8 && data[..8] == <Master as anchor_lang::Discriminator>::DISCRIMINATOR,
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        Ok(Self::deserialize(&mut &data[8..])?)
+    }
+}
+
+const MASTER_SIZE: usize = size_of::<Master>() + 8;
+/// `Master` account, which is the main account of the smart contract. The tunables of the vault are held by its
+/// `VaultConfig` PDA.
+#[account]
+#[derive(Default)]
+pub struct Master {
+    /// Id of the vault, part of the master PDA seeds.
+    pub vault_id: u64,
+    /// Solana stored in the smart contract.
+    pub balance: u64,
+    /// Last time some withdraw has happen.
+    pub last_withdraw_time: i64,
+    /// Operator which is allowed to transfer token.
+    pub operator: Pubkey,
+    /// Admin which is allowed to manage the smart contract.
+    pub admin: Pubkey,
+    /// Guardian which is allowed to take emergency actions next to the admin.
+    pub guardian: Pubkey,
+    /// SOL paid out by the operator within the current window.
+    pub outflow: RateWindow,
+    /// SOL deposits minus outflows of the current day.
+    pub net_flow: NetFlow,
+    /// Address lookup table of the vault's frequently used accounts, the default pubkey if none.
+    pub lookup_table: Pubkey,
+    /// Bumped on every change of a setting deposits or payouts depend on, so clients can tell their cache is stale.
+    pub config_generation: u64,
+    /// ISO 4217 code of the currency the vault reports its figures in, all zero if not declared.
+    pub accounting_currency: [u8; 3],
+    /// Decimals amounts in `accounting_currency` are normalized to.
+    pub accounting_decimals: u8,
+    /// Lifetime deposit and withdraw counters of the SOL balance.
+    pub lifetime: LifetimeStats,
+    /// Label of the vault, set at init and updated by the admin.
+    pub metadata: VaultMetadata,
+    /// Layout version of the account, `MASTER_VERSION` of the program that created or last migrated it.
+    pub version: u8,
+    /// Ethereum address of the secp256k1 key which may sign vouchers besides the operator, all zero if none.
+    pub voucher_eth_signer: [u8; 20],
+    /// Unix timestamp of the last heartbeat or payout signed by the operator. Payouts users or cranks run do not move
+    /// it, so they cannot keep the dead man switch from firing.
+    pub heartbeat_time: i64,
+    /// Merkle root of the SOL balances of the users, last published by the operator with a heartbeat.
+    pub balance_root: [u8; 32],
+    /// Whether the dead man switch has switched the vault to claim mode for good.
+    pub claim_mode: bool,
+    /// SOL sent by operator payouts within the current 24 hour window.
+    pub daily_outflow: RateWindow,
+    /// Deposits of any asset within the current deposit velocity window.
+    pub deposit_count: RateWindow,
+    /// SOL deposited within the current deposit velocity window.
+    pub deposit_volume: RateWindow,
+    /// SOL of the balance reserved for the cold wallet.
+    pub cold_reserve: u64,
+}
+
+impl Master {
+    /// Marks the settings of the vault as changed.
+    pub fn bump_config_generation(&mut self) {
+        self.config_generation = self.config_generation.wrapping_add(1);
+    }
+}
+
+const VAULT_CONFIG_SIZE: usize = size_of::<VaultConfig>() + 8;
+/// `VaultConfig` account, which holds the tunables of a vault. They are changed with `update_config` or a dedicated
+/// setter, both emitting `ConfigChangedEvent`.
+#[account]
+#[derive(Default)]
+pub struct VaultConfig {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Whether operator payouts are currently stopped.
+    pub withdrawals_paused: bool,
+    /// Whether operator payouts may only go to allowlisted receivers.
+    pub receiver_allowlist_enabled: bool,
+    /// Whether deposits are only accepted from whitelisted users.
+    pub deposit_whitelist_enabled: bool,
+    /// Whether deposits are currently stopped.
+    pub deposits_paused: bool,
+    /// Length of the circuit breaker window in seconds.
+    pub outflow_window_secs: i64,
+    /// Highest SOL amount operator payouts may move within a window, 0 if unlimited.
+    pub outflow_limit: u64,
+    /// Highest amount SOL deposits may exceed outflows by per day, 0 if unlimited.
+    pub max_daily_net_inflow: u64,
+    /// Fee of express payouts in basis points, retained by the vault.
+    pub express_fee_bps: u16,
+    /// Whether token payouts reimburse the operator for the rent of receiver ATAs they create.
+    pub reimburse_ata_rent: bool,
+    /// Seconds SOL deposits are held in escrow before they can be released, 0 if deposits are not escrowed.
+    pub deposit_escrow_window: i64,
+    /// Seconds the operator may be silent before anyone can switch the vault to claim mode, 0 if it never switches.
+    pub dead_man_period: i64,
+    /// Highest amount of SOL users withdraw themselves with `self_withdraw`, 0 if self-service is off.
+    pub self_withdraw_threshold: u64,
+    /// Seconds a user waits between two self-service withdrawals.
+    pub self_withdraw_cooldown: i64,
+    /// Highest amount of SOL a single operator transaction pays out, 0 if uncapped.
+    pub max_sol_per_payout: u64,
+    /// Highest amount of SOL operator payouts send within 24 hours, 0 if unlimited.
+    pub daily_withdraw_limit: u64,
+    /// Smallest SOL deposit accepted, deposits of 0 are always rejected.
+    pub min_deposit: u64,
+    /// Largest SOL deposit accepted in one transaction, 0 if unlimited.
+    pub max_deposit: u64,
+    /// Lifetime SOL deposits of a user without a cap of its own, 0 if uncapped.
+    pub user_deposit_cap: u64,
+    /// Highest SOL balance deposits may take the vault to, 0 if uncapped.
+    pub vault_cap: u64,
+    /// Seconds which have to pass between two operator payouts to the same receiver, 0 if disabled.
+    pub payout_cooldown: i64,
+    /// Length of the deposit velocity windows in seconds.
+    pub deposit_velocity_window_secs: i64,
+    /// Most deposits of any asset within a deposit velocity window, 0 if unlimited.
+    pub deposit_velocity_max_count: u64,
+    /// Most SOL deposited within a deposit velocity window, 0 if unlimited.
+    pub deposit_velocity_max_volume: u64,
+    /// Wallet the SOL above the hot balance threshold may be withdrawn to.
+    pub cold_wallet: Pubkey,
+    /// Highest SOL balance operator payouts draw from, 0 if the balance is not split.
+    pub hot_balance_threshold: u64,
+    /// Lamports payouts have to leave in master PDA, 0 if rent exemption is enough.
+    pub min_sol_reserve: u64,
+    /// UTC hours operator payouts are allowed in without admin co-sign, bit `h` for hour `h`, 0 if any hour.
+    pub payout_hours: u32,
+}
+
+impl VaultConfig {
+    /// Applies `setting` and returns the previous value of the same setting, `None` for a setting of a mint.
+    pub fn apply_config(&mut self, setting: ConfigSetting) -> Option<ConfigSetting> {
+        let old = match setting {
+            ConfigSetting::WithdrawalsPaused(value) => ConfigSetting::WithdrawalsPaused(
+                std::mem::replace(&mut self.withdrawals_paused, value),
+            ),
+            ConfigSetting::ReceiverAllowlistEnabled(value) => {
+                ConfigSetting::ReceiverAllowlistEnabled(std::mem::replace(
+                    &mut self.receiver_allowlist_enabled,
+                    value,
+                ))
+            }
+            ConfigSetting::DepositWhitelistEnabled(value) => {
+                ConfigSetting::DepositWhitelistEnabled(std::mem::replace(
+                    &mut self.deposit_whitelist_enabled,
+                    value,
+                ))
+            }
+            ConfigSetting::DepositsPaused(value) => {
+                ConfigSetting::DepositsPaused(std::mem::replace(&mut self.deposits_paused, value))
+            }
+            ConfigSetting::OutflowWindowSecs(value) => ConfigSetting::OutflowWindowSecs(
+                std::mem::replace(&mut self.outflow_window_secs, value),
+            ),
+            ConfigSetting::OutflowLimit(value) => {
+                ConfigSetting::OutflowLimit(std::mem::replace(&mut self.outflow_limit, value))
+            }
+            ConfigSetting::MaxDailyNetInflow(value) => ConfigSetting::MaxDailyNetInflow(
+                std::mem::replace(&mut self.max_daily_net_inflow, value),
+            ),
+            ConfigSetting::ExpressFeeBps(value) => {
+                ConfigSetting::ExpressFeeBps(std::mem::replace(&mut self.express_fee_bps, value))
+            }
+            ConfigSetting::ReimburseAtaRent(value) => ConfigSetting::ReimburseAtaRent(
+                std::mem::replace(&mut self.reimburse_ata_rent, value),
+            ),
+            ConfigSetting::DepositEscrowWindow(value) => ConfigSetting::DepositEscrowWindow(
+                std::mem::replace(&mut self.deposit_escrow_window, value),
+            ),
+            ConfigSetting::DeadManPeriod(value) => {
+                ConfigSetting::DeadManPeriod(std::mem::replace(&mut self.dead_man_period, value))
+            }
+            ConfigSetting::SelfWithdrawThreshold(value) => ConfigSetting::SelfWithdrawThreshold(
+                std::mem::replace(&mut self.self_withdraw_threshold, value),
+            ),
+            ConfigSetting::SelfWithdrawCooldown(value) => ConfigSetting::SelfWithdrawCooldown(
+                std::mem::replace(&mut self.self_withdraw_cooldown, value),
+            ),
+            ConfigSetting::MaxSolPerPayout(value) => ConfigSetting::MaxSolPerPayout(
+                std::mem::replace(&mut self.max_sol_per_payout, value),
+            ),
+            ConfigSetting::DailyWithdrawLimit(value) => ConfigSetting::DailyWithdrawLimit(
+                std::mem::replace(&mut self.daily_withdraw_limit, value),
+            ),
+            ConfigSetting::MinDeposit(value) => {
+                ConfigSetting::MinDeposit(std::mem::replace(&mut self.min_deposit, value))
+            }
+            ConfigSetting::MaxDeposit(value) => {
+                ConfigSetting::MaxDeposit(std::mem::replace(&mut self.max_deposit, value))
+            }
+            ConfigSetting::UserDepositCap(value) => {
+                ConfigSetting::UserDepositCap(std::mem::replace(&mut self.user_deposit_cap, value))
+            }
+            ConfigSetting::VaultCap(value) => {
+                ConfigSetting::VaultCap(std::mem::replace(&mut self.vault_cap, value))
+            }
+            ConfigSetting::PayoutCooldown(value) => {
+                ConfigSetting::PayoutCooldown(std::mem::replace(&mut self.payout_cooldown, value))
+            }
+            ConfigSetting::DepositVelocityWindowSecs(value) => {
+                ConfigSetting::DepositVelocityWindowSecs(std::mem::replace(
+                    &mut self.deposit_velocity_window_secs,
+                    value,
+                ))
+            }
+            ConfigSetting::DepositVelocityMaxCount(value) => {
+                ConfigSetting::DepositVelocityMaxCount(std::mem::replace(
+                    &mut self.deposit_velocity_max_count,
+                    value,
+                ))
+            }
+            ConfigSetting::DepositVelocityMaxVolume(value) => {
+                ConfigSetting::DepositVelocityMaxVolume(std::mem::replace(
+                    &mut self.deposit_velocity_max_volume,
+                    value,
+                ))
+            }
+            ConfigSetting::ColdWallet(value) => {
+                ConfigSetting::ColdWallet(std::mem::replace(&mut self.cold_wallet, value))
+            }
+            ConfigSetting::HotBalanceThreshold(value) => ConfigSetting::HotBalanceThreshold(
+                std::mem::replace(&mut self.hot_balance_threshold, value),
+            ),
+            ConfigSetting::MinSolReserve(value) => {
+                ConfigSetting::MinSolReserve(std::mem::replace(&mut self.min_sol_reserve, value))
+            }
+            ConfigSetting::PayoutHours(value) => {
+                ConfigSetting::PayoutHours(std::mem::replace(&mut self.payout_hours, value))
+            }
+            _ => return None,
+        };
+
+        Some(old)
+    }
+}
+
+/// `VaultToken` account, which registers a mint supported by the vault.
+#[account]
+#[derive(Default)]
+pub struct VaultToken {
+    /// Mint of the token.
+    pub mint: Pubkey,
+    /// Associated token account of master for the mint.
+    pub ata: Pubkey,
+    /// Tokens stored into the ATA of the smart contract.
+    pub balance: u64,
+    /// Whether the mint is supported.
+    pub enabled: bool,
+    /// Decimals of the mint.
+    pub decimals: u8,
+    /// Highest amount operator payouts may move within a circuit breaker window, 0 if unlimited.
+    pub outflow_limit: u64,
+    /// Tokens paid out by the operator within the current window.
+    pub outflow: RateWindow,
+    /// Highest amount deposits may exceed outflows by per day, 0 if unlimited.
+    pub max_daily_net_inflow: u64,
+    /// Deposits minus outflows of the current day.
+    pub net_flow: NetFlow,
+    /// Lifetime deposit and withdraw counters of the mint.
+    pub lifetime: LifetimeStats,
+    /// Highest amount of the token a single operator transaction pays out, 0 if uncapped.
+    pub max_token_per_payout: u64,
+    /// Smallest deposit of the token accepted, deposits of 0 are always rejected.
+    pub min_deposit: u64,
+    /// Largest deposit of the token accepted in one transaction, 0 if unlimited.
+    pub max_deposit: u64,
+    /// Lifetime deposits of the token of a user without a cap of its own, 0 if uncapped.
+    pub user_deposit_cap: u64,
+    /// Highest token balance deposits may take the vault to, 0 if uncapped.
+    pub vault_cap: u64,
+    /// Most tokens deposited within a deposit velocity window, 0 if unlimited.
+    pub deposit_velocity_max_volume: u64,
+    /// Tokens deposited within the current deposit velocity window.
+    pub deposit_volume: RateWindow,
+    /// Most tokens operator payouts may send within a rolling 24 hours, 0 if unlimited.
+    pub daily_withdraw_limit: u64,
+    /// Tokens paid out by the operator within the current 24 hour window.
+    pub daily_outflow: RateWindow,
+}
+
+impl VaultToken {
+    /// Applies `setting` and returns the previous value of the same setting, `None` for a setting of the vault.
+    pub fn apply_config(&mut self, setting: ConfigSetting) -> Option<ConfigSetting> {
+        let old = match setting {
+            ConfigSetting::TokenOutflowLimit(value) => {
+                ConfigSetting::TokenOutflowLimit(std::mem::replace(&mut self.outflow_limit, value))
+            }
+            ConfigSetting::TokenMaxDailyNetInflow(value) => ConfigSetting::TokenMaxDailyNetInflow(
+                std::mem::replace(&mut self.max_daily_net_inflow, value),
+            ),
+            ConfigSetting::MaxTokenPerPayout(value) => ConfigSetting::MaxTokenPerPayout(
+                std::mem::replace(&mut self.max_token_per_payout, value),
+            ),
+            ConfigSetting::TokenMinDeposit(value) => {
+                ConfigSetting::TokenMinDeposit(std::mem::replace(&mut self.min_deposit, value))
+            }
+            ConfigSetting::TokenMaxDeposit(value) => {
+                ConfigSetting::TokenMaxDeposit(std::mem::replace(&mut self.max_deposit, value))
+            }
+            ConfigSetting::TokenUserDepositCap(value) => ConfigSetting::TokenUserDepositCap(
+                std::mem::replace(&mut self.user_deposit_cap, value),
+            ),
+            ConfigSetting::TokenVaultCap(value) => {
+                ConfigSetting::TokenVaultCap(std::mem::replace(&mut self.vault_cap, value))
+            }
+            ConfigSetting::TokenDepositVelocityMaxVolume(value) => {
+                ConfigSetting::TokenDepositVelocityMaxVolume(std::mem::replace(
+                    &mut self.deposit_velocity_max_volume,
+                    value,
+                ))
+            }
+            ConfigSetting::TokenDailyWithdrawLimit(value) => {
+                ConfigSetting::TokenDailyWithdrawLimit(std::mem::replace(
+                    &mut self.daily_withdraw_limit,
+                    value,
+                ))
+            }
+            _ => return None,
+        };
+
+        Some(old)
+    }
+}
+
+/// `AllowedReceiver` account, which marks a receiver as allowed to get operator payouts.
+#[account]
+pub struct AllowedReceiver {
+    /// Receiver which is allowed.
+    pub receiver: Pubkey,
+}
+
+/// `LimitExemptReceiver` account, which marks a trusted receiver whose payouts bypass the payout limits.
+#[account]
+pub struct LimitExemptReceiver {
+    /// Receiver which is exempt.
+    pub receiver: Pubkey,
+}
+
+/// Accounts for `InitMaster` instruction.
+#[derive(Accounts)]
+#[instruction(vault_id: u64)]
+pub struct InitMaster<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = MASTER_SIZE,//8 + 8 + 32 + 32 + 8,
+        seeds = [MASTER_SEED.as_bytes(), vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = VAULT_CONFIG_SIZE,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = VAULT_REGISTRY_SIZE,
+        seeds = [VAULT_REGISTRY_SEED.as_bytes()],
+        bump,
+    )]
+    pub vault_registry: Account<'info, VaultRegistry>,
+
+    #[account(mut, address = program_config.super_admin @ Errors::Unauthorized)]
+    pub payer: Signer<'info>,
+
+    pub admin: SystemAccount<'info>,
+
+    pub operator: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `MigrateMaster` instruction.
+#[derive(Accounts)]
+#[instruction(vault_id: u64)]
+pub struct MigrateMaster<'info> {
+    /// CHECK: may still have an older, shorter layout, so it is deserialized by the instruction once it has been grown
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `MigrateLegacyMaster` instruction.
+#[derive(Accounts)]
+#[instruction(vault_id: u64)]
+pub struct MigrateLegacyMaster<'info> {
+    /// CHECK: has the layout deployed before vaults, so it is deserialized by the instruction as `LegacyMaster`
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes()],
+        bump,
+    )]
+    pub legacy_master: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = MASTER_SIZE,
+        seeds = [MASTER_SEED.as_bytes(), vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = VAULT_CONFIG_SIZE,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = VAULT_REGISTRY_SIZE,
+        seeds = [VAULT_REGISTRY_SEED.as_bytes()],
+        bump,
+    )]
+    pub vault_registry: Account<'info, VaultRegistry>,
+
+    #[account(mut, address = program_config.super_admin @ Errors::Unauthorized)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `MigrateLegacyToken` instruction.
+#[derive(Accounts)]
+pub struct MigrateLegacyToken<'info> {
+    /// CHECK: has the layout deployed before vaults, so it is deserialized by the instruction as `LegacyMaster`
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes()],
+        bump,
+    )]
+    pub legacy_master: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = legacy_master,
+    )]
+    pub legacy_ata: Account<'info, TokenAccount>,
+
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        constraint = vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(mut, address = vault_token.ata)]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for `InitProgramConfig` instruction.
+#[derive(Accounts)]
+pub struct InitProgramConfig<'info> {
+    #[account(
+        init,
+        payer = authority,
+        space = PROGRAM_CONFIG_SIZE,
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()))]
+    pub program: Program<'info, crate::program::ProBalance>,
+
+    #[account(constraint = program_data.upgrade_authority_address == Some(authority.key()) @ Errors::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `SetSuperAdmin` instruction.
+#[derive(Accounts)]
+pub struct SetSuperAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(address = program_config.super_admin)]
+    pub super_admin: Signer<'info>,
+
+    pub new_super_admin: SystemAccount<'info>,
+}
+
+/// Accounts for instructions updating the program config.
+#[derive(Accounts)]
+pub struct UpdateProgramConfig<'info> {
+    #[account(
+        mut,
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(address = program_config.super_admin)]
+    pub super_admin: Signer<'info>,
+}
+
+/// Accounts for `AddSupportedMint` instruction.
+#[derive(Accounts)]
+pub struct AddSupportedMint<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<VaultToken>(),
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        init,
+        payer = admin,
+        associated_token::mint = token_mint,
+        associated_token::authority = master,
+        associated_token::token_program = token_program,
+    )]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `AddSupportedMintAccount` instruction.
+#[derive(Accounts)]
+pub struct AddSupportedMintAccount<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<VaultToken>(),
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        token::mint = token_mint,
+        token::authority = master,
+        constraint = token_account.delegate.is_none()
+            && token_account.close_authority.is_none() @ Errors::TokenAccountNotExclusive,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `SetOperator` instruction.
+#[derive(Accounts)]
+pub struct SetOperator<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub new_operator: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `SetAdmin` instruction.
+#[derive(Accounts)]
+pub struct SetAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub new_admin: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `SetGuardian` instruction.
+#[derive(Accounts)]
+pub struct SetGuardian<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub new_guardian: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for emergency instructions callable by master.admin or master.guardian.
+#[derive(Accounts)]
+pub struct EmergencyAction<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        constraint = authority.key() == master.admin
+            || authority.key() == master.guardian @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+}
+
+/// `WhitelistedDepositor` account, which marks a user as allowed to deposit while the deposit whitelist is enabled.
+#[account]
+pub struct WhitelistedDepositor {
+    /// User which is whitelisted.
+    pub user: Pubkey,
+}
+
+/// `UserState` account, which keeps per-user flags.
+#[account]
+pub struct UserState {
+    /// User this state belongs to.
+    pub user: Pubkey,
+    /// Whether deposits from and payouts to the user are blocked.
+    pub frozen: bool,
+}
+
+/// `Canary` account, which marks a receiver as a canary no legitimate payout ever goes to.
+#[account]
+pub struct Canary {
+    /// Canary receiver.
+    pub receiver: Pubkey,
+}
+
+/// `Blocked` account, which marks an address as blocked from deposits and payouts.
+#[account]
+pub struct Blocked {
+    /// Address which is blocked.
+    pub address: Pubkey,
+}
+
+/// `ExchangeWallet` account, which tags an address as a known exchange hot wallet.
+#[account]
+pub struct ExchangeWallet {
+    /// Address of the hot wallet.
+    pub address: Pubkey,
+}
+
+/// `ReceiverLock` account, which marks a receiver whose payouts are being fulfilled by an operator instance.
+#[account]
+pub struct ReceiverLock {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Receiver whose payouts are locked.
+    pub receiver: Pubkey,
+    /// Unix timestamp the lock was taken at.
+    pub locked_at: i64,
+}
+
+/// `ReceiverCooldown` account, which records the last operator payout to a receiver.
+#[account]
+pub struct ReceiverCooldown {
+    /// Receiver of the payouts.
+    pub receiver: Pubkey,
+    /// Unix timestamp of the last operator payout to the receiver.
+    pub last_payout_time: i64,
+}
+
+/// `WithdrawRequest` account, which records a payout a user has requested until the operator fulfills it.
+#[account]
+pub struct WithdrawRequest {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// User which has requested the payout, and its receiver.
+    pub user: Pubkey,
+    /// Mint of the requested token, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Requested amount of SOL or token.
+    pub amount: u64,
+    /// Unix timestamp the request was made at.
+    pub created_at: i64,
+    /// Unix timestamp after which the request can no longer be paid.
+    pub expires_at: i64,
+    /// Position of the request in the withdraw queue.
+    pub sequence: u64,
+    /// Whether the request is disputed and cannot be fulfilled until the dispute is closed.
+    pub disputed: bool,
+    /// Priority tier set by the operator, 0 for normal requests. Prioritized requests can be paid ahead of the head.
+    pub priority: u8,
+}
+
+/// `Receipt` account, which records that the payout of a backend request id has been made.
+#[account]
+pub struct Receipt {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Id of the request in the backend.
+    pub request_id: u64,
+    /// Wallet which has been paid.
+    pub receiver: Pubkey,
+    /// Mint of the paid token, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Paid amount of SOL or token.
+    pub amount: u64,
+    /// Slot the payout has been made in.
+    pub slot: u64,
+    /// Unix timestamp the payout has been made at.
+    pub paid_at: i64,
+}
+
+/// `PayoutRoot` account, which holds the merkle root of the payouts of an epoch users claim with `claim`.
+#[account]
+pub struct PayoutRoot {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Epoch of the payouts.
+    pub epoch: u64,
+    /// Merkle root of the (user, amount, epoch) entries.
+    pub root: [u8; 32],
+    /// Highest amount of SOL which can be claimed against the root.
+    pub total: u64,
+    /// Amount of SOL claimed so far.
+    pub claimed: u64,
+}
+
+/// `MerkleClaim` account, which records that a user has claimed its payout of an epoch.
+#[account]
+pub struct MerkleClaim {
+    /// Payout root PDA the claim has been made against.
+    pub payout_root: Pubkey,
+    /// User which has claimed.
+    pub user: Pubkey,
+    /// Claimed amount of SOL.
+    pub amount: u64,
+}
+
+/// `SelfWithdrawal` account, which tracks the last self-service withdrawal of a user.
+#[account]
+pub struct SelfWithdrawal {
+    /// User this account belongs to.
+    pub user: Pubkey,
+    /// Balance root the last withdrawal has been proven against.
+    pub balance_root: [u8; 32],
+    /// Unix timestamp of the last withdrawal.
+    pub last_withdraw_time: i64,
+    /// Amount withdrawn against `balance_root`, which a claim mode claim of the same root pays less.
+    pub withdrawn: u64,
+}
+
+/// `UserDeposits` account, which tracks the lifetime deposits of a user in one asset against its cap.
+#[account]
+pub struct UserDeposits {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// User which has deposited.
+    pub user: Pubkey,
+    /// Mint of the asset, the default pubkey for SOL.
+    pub asset: Pubkey,
+    /// Lifetime deposits of the user.
+    pub deposited: u64,
+    /// Cap of the lifetime deposits set by the admin, 0 if the cap of the asset applies.
+    pub cap: u64,
+}
+
+/// `AnnouncedBatch` account, which commits to a payout batch before it is executed.
+#[account]
+pub struct AnnouncedBatch {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Hash of the approved payout plan.
+    pub batch_hash: [u8; 32],
+    /// Mint the batch pays, `None` for SOL.
+    pub mint: Option<Pubkey>,
+    /// Highest amount the batch pays out.
+    pub total_amount: u64,
+    /// Amount paid out by batch payouts referencing the batch so far.
+    pub paid: u64,
+    /// Unix timestamp the batch has been announced at.
+    pub announced_at: i64,
+}
+
+/// `ScheduledPayout` account, which holds a payout authorized by the operator until it is executed.
+#[account]
+pub struct ScheduledPayout {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Id of the scheduled payout within the vault.
+    pub id: u64,
+    /// Wallet the payout goes to.
+    pub receiver: Pubkey,
+    /// Scheduled amount of SOL.
+    pub amount: u64,
+    /// Unix timestamp from which anyone can execute the payout.
+    pub execute_after: i64,
+}
+
+/// `DepositRefund` account, which records that a deposit has been refunded.
+#[account]
+pub struct DepositRefund {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Backend id of the refunded deposit.
+    pub deposit_id: u64,
+    /// Wallet which has been refunded.
+    pub depositor: Pubkey,
+    /// Refunded amount of SOL.
+    pub amount: u64,
+    /// Unix timestamp the refund has been made at.
+    pub refunded_at: i64,
+}
+
+/// `DepositEscrow` account, which holds an escrowed deposit until it is released or rejected.
+#[account]
+pub struct DepositEscrow {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// User which has deposited.
+    pub user: Pubkey,
+    /// Id of the escrow, chosen by the user.
+    pub escrow_id: u64,
+    /// Escrowed amount of SOL, held on top of the rent.
+    pub amount: u64,
+    /// Kind of account the deposit has come from.
+    pub source: DepositSource,
+    /// Unix timestamp the deposit can be released from.
+    pub release_after: i64,
+}
+
+/// `WithdrawQueue` account, which orders the withdraw requests of a vault. Requests are numbered from `tail` on and
+/// paid from `head` on, the request PDA of a sequence number is derived from it.
+#[account]
+#[derive(Default)]
+pub struct WithdrawQueue {
+    /// Sequence number of the next request to pay.
+    pub head: u64,
+    /// Sequence number the next request gets.
+    pub tail: u64,
+}
+
+/// `ProductLedger` account, which keeps the sub-ledger of one product for SOL or one token inside a vault.
+#[account]
+pub struct ProductLedger {
+    /// Master PDA of the vault.
+    pub master: Pubkey,
+    /// Id of the product.
+    pub product_id: u64,
+    /// Mint of the token, the default pubkey for SOL.
+    pub asset: Pubkey,
+    /// Amount held for the product.
+    pub balance: u64,
+    /// Sum of all deposits booked to the product.
+    pub total_deposited: u64,
+    /// Sum of all payouts booked to the product.
+    pub total_paid_out: u64,
+}
+
+/// `PenaltyLedger` account, which keeps the faults recorded against an operator.
+#[account]
+pub struct PenaltyLedger {
+    /// Operator this ledger belongs to.
+    pub operator: Pubkey,
+    /// Penalty not yet netted against reimbursements.
+    pub outstanding: u64,
+    /// Sum of all penalties ever recorded.
+    pub total_recorded: u64,
+    /// Number of faults ever recorded.
+    pub fault_count: u32,
+    /// When does the last fault has been recorded.
+    pub last_fault_time: i64,
+}
+
+impl PenaltyLedger {
+    /// Adds a fault of `amount` at `time`. The ledger is left untouched if any counter would overflow.
+    pub fn record_fault(&mut self, amount: u64, time: i64) -> Result<()> {
+        let outstanding = self
+            .outstanding
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        let total_recorded = self
+            .total_recorded
+            .checked_add(amount)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+        let fault_count = self
+            .fault_count
+            .checked_add(1)
+            .map(Ok)
+            .unwrap_or(Err(Errors::MathUnderflowOrOverflow))?;
+
+        self.outstanding = outstanding;
+        self.total_recorded = total_recorded;
+        self.fault_count = fault_count;
+        self.last_fault_time = time;
+        Ok(())
+    }
+
+    /// Nets the outstanding penalty against a reimbursement and returns the amount left to pay out.
+    pub fn net_against(&mut self, reimbursement: u64) -> u64 {
+        let netted = self.outstanding.min(reimbursement);
+        self.outstanding -= netted;
+        reimbursement - netted
+    }
+}
+
+/// Accounts for `SetReceiverAllowlistEnabled` instruction.
+#[derive(Accounts)]
+pub struct SetReceiverAllowlistEnabled<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetExposureLimit` instruction.
+#[derive(Accounts)]
+pub struct SetExposureLimit<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetVaultMetadata` instruction.
+#[derive(Accounts)]
+pub struct SetVaultMetadata<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetAccountingCurrency` instruction.
+#[derive(Accounts)]
+pub struct SetAccountingCurrency<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetVoucherEthSigner` instruction.
+#[derive(Accounts)]
+pub struct SetVoucherEthSigner<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetExpressFeeBps` instruction.
+#[derive(Accounts)]
+pub struct SetExpressFeeBps<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `UpdateConfig` instruction.
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    /// Vault token PDA of the mint, only needed for a setting of a mint
+    #[account(
+        mut,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), vault_token.mint.as_ref()],
+        bump,
+    )]
+    pub vault_token: Option<Account<'info, VaultToken>>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetReimburseAtaRent` instruction.
+#[derive(Accounts)]
+pub struct SetReimburseAtaRent<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetDepositEscrowWindow` instruction.
+#[derive(Accounts)]
+pub struct SetDepositEscrowWindow<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetDeadManPeriod` instruction.
+#[derive(Accounts)]
+pub struct SetDeadManPeriod<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetSelfWithdrawLimits` instruction.
+#[derive(Accounts)]
+pub struct SetSelfWithdrawLimits<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `SetUserDepositCap` instruction.
+#[derive(Accounts)]
+#[instruction(user: Pubkey, asset: Pubkey)]
+pub struct SetUserDepositCap<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + size_of::<UserDeposits>(),
+        seeds = [USER_DEPOSITS_SEED.as_bytes(), master.key().as_ref(), user.as_ref(), asset.as_ref()],
+        bump,
+    )]
+    pub user_deposits: Account<'info, UserDeposits>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `OpenUserDeposits` instruction.
+#[derive(Accounts)]
+#[instruction(asset: Pubkey)]
+pub struct OpenUserDeposits<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + size_of::<UserDeposits>(),
+        seeds = [USER_DEPOSITS_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref(), asset.as_ref()],
+        bump,
+    )]
+    pub user_deposits: Account<'info, UserDeposits>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `Heartbeat` instruction.
+#[derive(Accounts)]
+pub struct Heartbeat<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(address = master.operator)]
+    pub operator: Signer<'info>,
+}
+
+/// Accounts for `TriggerClaimMode` instruction.
+#[derive(Accounts)]
+pub struct TriggerClaimMode<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + size_of::<PayoutRoot>(),
+        seeds = [
+            PAYOUT_ROOT_SEED.as_bytes(),
+            master.key().as_ref(),
+            CLAIM_MODE_EPOCH.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub payout_root: Account<'info, PayoutRoot>,
+
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `SetCircuitBreaker` instruction.
+#[derive(Accounts)]
+pub struct SetCircuitBreaker<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `UpdateVaultToken` instruction.
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct UpdateVaultToken<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), mint.as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `RemoveMint` instruction.
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct RemoveMint<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), mint.as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(mut, address = vault_token.ata)]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for `AddAllowedReceiver` instruction.
+#[derive(Accounts)]
+pub struct AddAllowedReceiver<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<AllowedReceiver>(),
+        seeds = [RECEIVER_ALLOWLIST_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub allowed_receiver: Account<'info, AllowedReceiver>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub receiver: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `RemoveAllowedReceiver` instruction.
+#[derive(Accounts)]
+pub struct RemoveAllowedReceiver<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [RECEIVER_ALLOWLIST_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub allowed_receiver: Account<'info, AllowedReceiver>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub receiver: SystemAccount<'info>,
+}
+
+/// Accounts for `AddLimitExemptReceiver` instruction.
+#[derive(Accounts)]
+pub struct AddLimitExemptReceiver<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<LimitExemptReceiver>(),
+        seeds = [LIMIT_EXEMPT_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub limit_exempt: Account<'info, LimitExemptReceiver>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub receiver: SystemAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `RemoveLimitExemptReceiver` instruction.
+#[derive(Accounts)]
+pub struct RemoveLimitExemptReceiver<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [LIMIT_EXEMPT_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub limit_exempt: Account<'info, LimitExemptReceiver>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub receiver: SystemAccount<'info>,
+}
+
+/// Accounts for `SetDepositWhitelistEnabled` instruction.
+#[derive(Accounts)]
+pub struct SetDepositWhitelistEnabled<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `AddWhitelistedDepositor` instruction.
+#[derive(Accounts)]
+pub struct AddWhitelistedDepositor<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = authority,
+        space = 8 + size_of::<WhitelistedDepositor>(),
+        seeds = [DEPOSIT_WHITELIST_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub whitelisted_depositor: Account<'info, WhitelistedDepositor>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == master.admin
+            || authority.key() == master.operator @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+
+    /// CHECK: any wallet or PDA can be whitelisted
+    pub user: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `RemoveWhitelistedDepositor` instruction.
+#[derive(Accounts)]
+pub struct RemoveWhitelistedDepositor<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = authority,
+        seeds = [DEPOSIT_WHITELIST_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub whitelisted_depositor: Account<'info, WhitelistedDepositor>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == master.admin
+            || authority.key() == master.operator @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+
+    /// CHECK: any wallet or PDA can be whitelisted
+    pub user: UncheckedAccount<'info>,
+}
+
+/// Accounts for `FreezeUser` instruction.
+#[derive(Accounts)]
+pub struct FreezeUser<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 8 + size_of::<UserState>(),
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_state: Account<'info, UserState>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == master.operator
+            || authority.key() == master.admin @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+
+    /// CHECK: any wallet or PDA can be frozen
+    pub user: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `UnfreezeUser` instruction.
+#[derive(Accounts)]
+pub struct UnfreezeUser<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_state: Account<'info, UserState>,
+
+    #[account(
+        constraint = authority.key() == master.operator
+            || authority.key() == master.admin @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+
+    /// CHECK: only used to derive the user state PDA
+    pub user: UncheckedAccount<'info>,
+}
+
+/// Accounts for `RecordOperatorFault` instruction.
+#[derive(Accounts)]
+pub struct RecordOperatorFault<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init_if_needed,
+        payer = admin,
+        space = 8 + size_of::<PenaltyLedger>(),
+        seeds = [PENALTY_SEED.as_bytes(), master.key().as_ref(), operator.key().as_ref()],
+        bump,
+    )]
+    pub penalty_ledger: Account<'info, PenaltyLedger>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    /// CHECK: any current or former operator can be penalized
+    pub operator: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `InitProductLedger` instruction.
+#[derive(Accounts)]
+#[instruction(product_id: u64, asset: Pubkey)]
+pub struct InitProductLedger<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<ProductLedger>(),
+        seeds = [PRODUCT_SEED.as_bytes(), master.key().as_ref(), product_id.to_le_bytes().as_ref(), asset.as_ref()],
+        bump,
+    )]
+    pub product_ledger: Account<'info, ProductLedger>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `AddCanary` instruction.
+#[derive(Accounts)]
+#[instruction(receiver: Pubkey)]
+pub struct AddCanary<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<Canary>(),
+        seeds = [CANARY_SEED.as_bytes(), master.key().as_ref(), receiver.as_ref()],
+        bump,
+    )]
+    pub canary: Account<'info, Canary>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `RemoveCanary` instruction.
+#[derive(Accounts)]
+#[instruction(receiver: Pubkey)]
+pub struct RemoveCanary<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [CANARY_SEED.as_bytes(), master.key().as_ref(), receiver.as_ref()],
+        bump,
+    )]
+    pub canary: Account<'info, Canary>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `LockReceiver` instruction.
+#[derive(Accounts)]
+#[instruction(receiver: Pubkey)]
+pub struct LockReceiver<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + size_of::<ReceiverLock>(),
+        seeds = [RECEIVER_LOCK_SEED.as_bytes(), master.key().as_ref(), receiver.as_ref()],
+        bump,
+    )]
+    pub receiver_lock: Account<'info, ReceiverLock>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `RequestWithdraw` instruction.
+#[derive(Accounts)]
+pub struct RequestWithdraw<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + size_of::<WithdrawQueue>(),
+        seeds = [WITHDRAW_QUEUE_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub withdraw_queue: Account<'info, WithdrawQueue>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + size_of::<WithdrawRequest>(),
+        seeds = [
+            WITHDRAW_REQUEST_SEED.as_bytes(),
+            master.key().as_ref(),
+            withdraw_queue.tail.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `CancelWithdrawRequest` instruction.
+#[derive(Accounts)]
+pub struct CancelWithdrawRequest<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = user,
+        has_one = master @ Errors::WithdrawRequestMismatch,
+        has_one = user @ Errors::WithdrawRequestMismatch,
+    )]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+}
+
+/// Accounts for `ExpireRequest` instruction.
+#[derive(Accounts)]
+pub struct ExpireRequest<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = user,
+        has_one = master @ Errors::WithdrawRequestMismatch,
+        has_one = user @ Errors::WithdrawRequestMismatch,
+    )]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+
+    #[account(mut)]
+    pub user: SystemAccount<'info>,
+}
+
+/// Accounts for `SkipClosedRequest` instruction.
+#[derive(Accounts)]
+pub struct SkipClosedRequest<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [WITHDRAW_QUEUE_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub withdraw_queue: Account<'info, WithdrawQueue>,
+
+    /// CHECK: only checked for existence, the request at the head of the queue has been closed unless it exists
+    #[account(
+        seeds = [
+            WITHDRAW_REQUEST_SEED.as_bytes(),
+            master.key().as_ref(),
+            withdraw_queue.head.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub head_request: UncheckedAccount<'info>,
+}
+
+/// Accounts for `SetRequestDisputed` instruction.
+#[derive(Accounts)]
+pub struct SetRequestDisputed<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(mut, has_one = master @ Errors::WithdrawRequestMismatch)]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+
+    #[account(
+        constraint = authority.key() == master.operator
+            || authority.key() == master.admin @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+}
+
+/// Accounts for `SetRequestPriority` instruction.
+#[derive(Accounts)]
+pub struct SetRequestPriority<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(mut, has_one = master @ Errors::WithdrawRequestMismatch)]
+    pub withdraw_request: Account<'info, WithdrawRequest>,
+
+    #[account(address = master.operator)]
+    pub operator: Signer<'info>,
+}
+
+/// Accounts for `CloseReceipt` instruction.
+#[derive(Accounts)]
+pub struct CloseReceipt<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = operator,
+        has_one = master @ Errors::ReceiptMismatch,
+    )]
+    pub receipt: Account<'info, Receipt>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+}
+
+/// Accounts for `UnlockReceiver` instruction.
+#[derive(Accounts)]
+#[instruction(receiver: Pubkey)]
+pub struct UnlockReceiver<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = operator,
+        seeds = [RECEIVER_LOCK_SEED.as_bytes(), master.key().as_ref(), receiver.as_ref()],
+        bump,
+    )]
+    pub receiver_lock: Account<'info, ReceiverLock>,
+
+    #[account(
+        constraint = authority.key() == master.operator
+            || authority.key() == master.admin @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+
+    /// CHECK: receives the rent of the lock, which the operator paid
+    #[account(mut, address = master.operator)]
+    pub operator: UncheckedAccount<'info>,
+}
+
+/// Accounts for `BlockAddress` instruction.
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct BlockAddress<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<Blocked>(),
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), address.as_ref()],
+        bump,
+    )]
+    pub blocked: Account<'info, Blocked>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `UnblockAddress` instruction.
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct UnblockAddress<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), address.as_ref()],
+        bump,
+    )]
+    pub blocked: Account<'info, Blocked>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `AddExchangeWallet` instruction.
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct AddExchangeWallet<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = admin,
+        space = 8 + size_of::<ExchangeWallet>(),
+        seeds = [EXCHANGE_WALLET_SEED.as_bytes(), master.key().as_ref(), address.as_ref()],
+        bump,
+    )]
+    pub exchange_wallet: Account<'info, ExchangeWallet>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `RemoveExchangeWallet` instruction.
+#[derive(Accounts)]
+#[instruction(address: Pubkey)]
+pub struct RemoveExchangeWallet<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = admin,
+        seeds = [EXCHANGE_WALLET_SEED.as_bytes(), master.key().as_ref(), address.as_ref()],
+        bump,
+    )]
+    pub exchange_wallet: Account<'info, ExchangeWallet>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for `CreateLookupTable` instruction.
+#[derive(Accounts)]
+pub struct CreateLookupTable<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    /// CHECK: only signs for the lookup table
+    #[account(
+        seeds = [LOOKUP_TABLE_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub lookup_table_authority: UncheckedAccount<'info>,
+
+    /// CHECK: derived and created by the address lookup table program
+    #[account(mut)]
+    pub lookup_table: UncheckedAccount<'info>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    /// CHECK: address lookup table program
+    #[account(address = address_lookup_table::program::ID)]
+    pub address_lookup_table_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for instructions updating the address lookup table of a vault.
+#[derive(Accounts)]
+pub struct UpdateLookupTable<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    /// CHECK: only signs for the lookup table
+    #[account(
+        seeds = [LOOKUP_TABLE_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub lookup_table_authority: UncheckedAccount<'info>,
+
+    /// CHECK: the lookup table recorded in master, checked by the address lookup table program
+    #[account(mut, address = master.lookup_table)]
+    pub lookup_table: UncheckedAccount<'info>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    /// CHECK: address lookup table program
+    #[account(address = address_lookup_table::program::ID)]
+    pub address_lookup_table_program: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `Deposit` instruction.
+#[derive(Accounts)]
+#[instruction(amount: u64)]
+pub struct Deposit<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: only checked for existence, the user is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is whitelisted if this PDA has been created
+    #[account(
+        seeds = [DEPOSIT_WHITELIST_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_whitelist: UncheckedAccount<'info>,
+
+    /// CHECK: the user is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is a tagged exchange hot wallet if this PDA has been created
+    #[account(
+        seeds = [EXCHANGE_WALLET_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_exchange: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::ProductLedgerMismatch,
+        constraint = product_ledger.asset == Pubkey::default() @ Errors::ProductLedgerMismatch,
+    )]
+    pub product_ledger: Option<Account<'info, ProductLedger>>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::UserDepositsMismatch,
+        has_one = user @ Errors::UserDepositsMismatch,
+        constraint = user_deposits.asset == Pubkey::default() @ Errors::UserDepositsMismatch,
+    )]
+    pub user_deposits: Option<Account<'info, UserDeposits>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `DepositEscrowed` instruction.
+#[derive(Accounts)]
+#[instruction(escrow_id: u64)]
+pub struct DepositEscrowed<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: only checked for existence, the user is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is whitelisted if this PDA has been created
+    #[account(
+        seeds = [DEPOSIT_WHITELIST_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_whitelist: UncheckedAccount<'info>,
+
+    /// CHECK: the user is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is a tagged exchange hot wallet if this PDA has been created
+    #[account(
+        seeds = [EXCHANGE_WALLET_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_exchange: UncheckedAccount<'info>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + size_of::<DepositEscrow>(),
+        seeds = [
+            DEPOSIT_ESCROW_SEED.as_bytes(),
+            master.key().as_ref(),
+            user.key().as_ref(),
+            escrow_id.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub escrow: Account<'info, DepositEscrow>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::UserDepositsMismatch,
+        has_one = user @ Errors::UserDepositsMismatch,
+        constraint = user_deposits.asset == Pubkey::default() @ Errors::UserDepositsMismatch,
+    )]
+    pub user_deposits: Option<Account<'info, UserDeposits>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `ReleaseDeposit` instruction.
+#[derive(Accounts)]
+pub struct ReleaseDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(address = master.operator)]
+    pub operator: Signer<'info>,
+
+    #[account(mut, address = escrow.user)]
+    pub user: SystemAccount<'info>,
+
+    #[account(mut, has_one = master, close = user)]
+    pub escrow: Account<'info, DepositEscrow>,
+}
+
+/// Accounts for `TripDepositVelocity` instruction.
+#[derive(Accounts)]
+pub struct TripDepositVelocity<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    /// Vault token PDA of the mint, only needed for a token deposit
+    #[account(
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), vault_token.mint.as_ref()],
+        bump,
+    )]
+    pub vault_token: Option<Account<'info, VaultToken>>,
+
+    #[account(address = master.operator)]
+    pub operator: Signer<'info>,
+}
+
+/// Accounts for `RejectDeposit` instruction.
+#[derive(Accounts)]
+pub struct RejectDeposit<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.operator)]
+    pub operator: Signer<'info>,
+
+    #[account(mut, address = escrow.user)]
+    pub user: SystemAccount<'info>,
+
+    #[account(mut, has_one = master, close = user)]
+    pub escrow: Account<'info, DepositEscrow>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::UserDepositsMismatch,
+        has_one = user @ Errors::UserDepositsMismatch,
+        constraint = user_deposits.asset == Pubkey::default() @ Errors::UserDepositsMismatch,
+    )]
+    pub user_deposits: Option<Account<'info, UserDeposits>>,
+}
+
+/// Accounts for `DepositToken` instruction.
+#[derive(Accounts)]
+pub struct DepositToken<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        constraint = vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        mut,
+        address = vault_token.ata,
+        token::mint = token_mint,
+        token::authority = master,
+    )]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = user,
+        associated_token::token_program = token_program,
+    )]
+    pub from: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: only checked for existence, the user is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is whitelisted if this PDA has been created
+    #[account(
+        seeds = [DEPOSIT_WHITELIST_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_whitelist: UncheckedAccount<'info>,
+
+    /// CHECK: the user is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is a tagged exchange hot wallet if this PDA has been created
+    #[account(
+        seeds = [EXCHANGE_WALLET_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_exchange: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::ProductLedgerMismatch,
+        constraint = product_ledger.asset == token_mint.key() @ Errors::ProductLedgerMismatch,
+    )]
+    pub product_ledger: Option<Account<'info, ProductLedger>>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::UserDepositsMismatch,
+        has_one = user @ Errors::UserDepositsMismatch,
+        constraint = user_deposits.asset == vault_token.mint @ Errors::UserDepositsMismatch,
+    )]
+    pub user_deposits: Option<Account<'info, UserDeposits>>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `DepositWrapped` instruction.
+#[derive(Accounts)]
+pub struct DepositWrapped<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        constraint = vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        mut,
+        address = vault_token.ata,
+        token::mint = token_mint,
+        token::authority = master,
+    )]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: only checked for existence, the user is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is whitelisted if this PDA has been created
+    #[account(
+        seeds = [DEPOSIT_WHITELIST_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_whitelist: UncheckedAccount<'info>,
+
+    /// CHECK: the user is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is a tagged exchange hot wallet if this PDA has been created
+    #[account(
+        seeds = [EXCHANGE_WALLET_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub user_exchange: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::ProductLedgerMismatch,
+        constraint = product_ledger.asset == token_mint.key() @ Errors::ProductLedgerMismatch,
+    )]
+    pub product_ledger: Option<Account<'info, ProductLedger>>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::UserDepositsMismatch,
+        has_one = user @ Errors::UserDepositsMismatch,
+        constraint = user_deposits.asset == vault_token.mint @ Errors::UserDepositsMismatch,
+    )]
+    pub user_deposits: Option<Account<'info, UserDeposits>>,
+
+    #[account(address = spl_token::native_mint::ID @ Errors::NotNativeMint)]
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `Donate` instruction.
+#[derive(Accounts)]
+pub struct Donate<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(mut)]
+    pub donor: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `DonateToken` instruction.
+#[derive(Accounts)]
+pub struct DonateToken<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        constraint = vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        mut,
+        address = vault_token.ata,
+        token::mint = token_mint,
+        token::authority = master,
+    )]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = donor,
+    )]
+    pub from: Account<'info, TokenAccount>,
+
+    pub donor: Signer<'info>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for `SendWithdraw` instruction.
+#[derive(Accounts)]
+#[instruction(amount: u64, request_id: Option<u64>)]
+pub struct SendWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+
+    #[account(mut)]
+    pub receiver: SystemAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is allowlisted if this PDA has been created
+    #[account(
+        seeds = [RECEIVER_ALLOWLIST_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_allowlist: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: the receiver is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is a canary if this PDA has been created
+    #[account(
+        seeds = [CANARY_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_canary: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::ProductLedgerMismatch,
+        constraint = product_ledger.asset == Pubkey::default() @ Errors::ProductLedgerMismatch,
+    )]
+    pub product_ledger: Option<Account<'info, ProductLedger>>,
+
+    #[account(
+        mut,
+        close = operator,
+        has_one = master @ Errors::ReceiverLockMismatch,
+        has_one = receiver @ Errors::ReceiverLockMismatch,
+    )]
+    pub receiver_lock: Option<Account<'info, ReceiverLock>>,
+
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = 8 + size_of::<ReceiverCooldown>(),
+        seeds = [RECEIVER_COOLDOWN_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_cooldown: Option<Account<'info, ReceiverCooldown>>,
+
+    #[account(
+        seeds = [LIMIT_EXEMPT_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub limit_exempt: Option<Account<'info, LimitExemptReceiver>>,
+
+    #[account(address = master.admin)]
+    pub admin_cosigner: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::WithdrawRequestMismatch,
+        constraint = withdraw_request.user == receiver.key() @ Errors::WithdrawRequestMismatch,
+        constraint = withdraw_request.mint.is_none() @ Errors::WithdrawRequestMismatch,
+    )]
+    pub withdraw_request: Option<Account<'info, WithdrawRequest>>,
+
+    #[account(
+        mut,
+        seeds = [WITHDRAW_QUEUE_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub withdraw_queue: Option<Account<'info, WithdrawQueue>>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + size_of::<Receipt>(),
+        seeds = [
+            RECEIPT_SEED.as_bytes(),
+            master.key().as_ref(),
+            request_id.unwrap_or_default().to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub receipt: Option<Account<'info, Receipt>>,
+
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `AnnounceBatch` instruction.
+#[derive(Accounts)]
+#[instruction(batch_hash: [u8; 32])]
+pub struct AnnounceBatch<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + size_of::<AnnouncedBatch>(),
+        seeds = [ANNOUNCED_BATCH_SEED.as_bytes(), master.key().as_ref(), batch_hash.as_ref()],
+        bump,
+    )]
+    pub announced_batch: Account<'info, AnnouncedBatch>,
+
+    pub mint: Option<Account<'info, Mint>>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `SendWithdrawBatch` instruction.
+#[derive(Accounts)]
+pub struct SendWithdrawBatch<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(address = master.operator)]
+    pub operator: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::AnnouncedBatchMismatch,
+        constraint = announced_batch.mint.is_none() @ Errors::AnnouncedBatchMismatch,
+    )]
+    pub announced_batch: Option<Account<'info, AnnouncedBatch>>,
+
+    #[account(address = master.admin)]
+    pub admin_cosigner: Option<Signer<'info>>,
+}
+
+/// Accounts for `SendWithdrawTokenBatch` instruction.
+#[derive(Accounts)]
+pub struct SendWithdrawTokenBatch<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), vault_token.mint.as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        mut,
+        address = vault_token.ata,
+        token::authority = master,
+    )]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(address = master.operator)]
+    pub operator: Signer<'info>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::AnnouncedBatchMismatch,
+        constraint = announced_batch.mint == Some(vault_token.mint) @ Errors::AnnouncedBatchMismatch,
+    )]
+    pub announced_batch: Option<Account<'info, AnnouncedBatch>>,
+
+    #[account(address = master.admin)]
+    pub admin_cosigner: Option<Signer<'info>>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for `RedeemVoucher` instruction.
+#[derive(Accounts)]
+#[instruction(voucher: Voucher)]
+pub struct RedeemVoucher<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+        address = voucher.master,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut, address = voucher.receiver)]
+    pub receiver: Signer<'info>,
+
+    /// CHECK: only checked for existence, the receiver is allowlisted if this PDA has been created
+    #[account(
+        seeds = [RECEIVER_ALLOWLIST_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_allowlist: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: the receiver is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is a canary if this PDA has been created
+    #[account(
+        seeds = [CANARY_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_canary: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = receiver,
+        space = 8 + size_of::<ReceiverCooldown>(),
+        seeds = [RECEIVER_COOLDOWN_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_cooldown: Option<Account<'info, ReceiverCooldown>>,
+
+    #[account(address = master.admin)]
+    pub admin_cosigner: Option<Signer<'info>>,
+
+    #[account(
+        init,
+        payer = receiver,
+        space = 8 + size_of::<Receipt>(),
+        seeds = [
+            RECEIPT_SEED.as_bytes(),
+            master.key().as_ref(),
+            voucher.nonce.to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub receipt: Account<'info, Receipt>,
+
+    /// CHECK: instructions sysvar, read for the signature verification instruction
+    #[account(address = anchor_lang::solana_program::sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `PostPayoutRoot` instruction.
+#[derive(Accounts)]
+#[instruction(epoch: u64)]
+pub struct PostPayoutRoot<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + size_of::<PayoutRoot>(),
+        seeds = [PAYOUT_ROOT_SEED.as_bytes(), master.key().as_ref(), epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub payout_root: Account<'info, PayoutRoot>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `Claim` instruction.
+#[derive(Accounts)]
+pub struct Claim<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        seeds = [PAYOUT_ROOT_SEED.as_bytes(), master.key().as_ref(), payout_root.epoch.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub payout_root: Account<'info, PayoutRoot>,
+
+    #[account(
+        init,
+        payer = user,
+        space = 8 + size_of::<MerkleClaim>(),
+        seeds = [CLAIM_SEED.as_bytes(), payout_root.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub claim: Account<'info, MerkleClaim>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: only checked for existence, the user is allowlisted if this PDA has been created
+    #[account(
+        seeds = [RECEIVER_ALLOWLIST_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub receiver_allowlist: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub receiver_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: the user is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub receiver_state: UncheckedAccount<'info>,
+
+    /// CHECK: what the user has self-withdrawn against a claim mode root is deducted if this PDA has been created
+    #[account(
+        seeds = [SELF_WITHDRAWAL_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub self_withdrawal: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `SelfWithdraw` instruction.
+#[derive(Accounts)]
+pub struct SelfWithdraw<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        init_if_needed,
+        payer = user,
+        space = 8 + size_of::<SelfWithdrawal>(),
+        seeds = [SELF_WITHDRAWAL_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub self_withdrawal: Account<'info, SelfWithdrawal>,
+
+    #[account(mut)]
+    pub user: Signer<'info>,
+
+    /// CHECK: only checked for existence, the user is allowlisted if this PDA has been created
+    #[account(
+        seeds = [RECEIVER_ALLOWLIST_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub receiver_allowlist: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the user is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub receiver_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: the user is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), user.key().as_ref()],
+        bump,
+    )]
+    pub receiver_state: UncheckedAccount<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `SchedulePayout` instruction.
+#[derive(Accounts)]
+#[instruction(id: u64, receiver: Pubkey)]
+pub struct SchedulePayout<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + size_of::<ScheduledPayout>(),
+        seeds = [SCHEDULED_PAYOUT_SEED.as_bytes(), master.key().as_ref(), id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub scheduled_payout: Account<'info, ScheduledPayout>,
+
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = 8 + size_of::<ReceiverCooldown>(),
+        seeds = [RECEIVER_COOLDOWN_SEED.as_bytes(), master.key().as_ref(), receiver.as_ref()],
+        bump,
+    )]
+    pub receiver_cooldown: Option<Account<'info, ReceiverCooldown>>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `CancelScheduledPayout` instruction.
+#[derive(Accounts)]
+pub struct CancelScheduledPayout<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        close = operator,
+        has_one = master @ Errors::ScheduledPayoutMismatch,
+    )]
+    pub scheduled_payout: Account<'info, ScheduledPayout>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+}
+
+/// Accounts for `ExecuteScheduledPayout` instruction.
+#[derive(Accounts)]
+pub struct ExecuteScheduledPayout<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::ScheduledPayoutMismatch,
+        has_one = receiver @ Errors::ScheduledPayoutMismatch,
+    )]
+    pub scheduled_payout: Account<'info, ScheduledPayout>,
+
+    /// CHECK: receives the rent of the executed scheduled payout
+    #[account(mut, address = master.operator)]
+    pub operator: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub receiver: SystemAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is allowlisted if this PDA has been created
+    #[account(
+        seeds = [RECEIVER_ALLOWLIST_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_allowlist: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: the receiver is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is a canary if this PDA has been created
+    #[account(
+        seeds = [CANARY_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_canary: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        seeds = [RECEIVER_COOLDOWN_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_cooldown: Option<Account<'info, ReceiverCooldown>>,
+
+    #[account(address = master.admin)]
+    pub admin_cosigner: Option<Signer<'info>>,
+}
+
+/// Accounts for `RefundDeposit` instruction.
+#[derive(Accounts)]
+#[instruction(deposit_id: u64)]
+pub struct RefundDeposit<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+
+    #[account(mut)]
+    pub depositor: SystemAccount<'info>,
+
+    /// CHECK: only checked for existence, the depositor is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), depositor.key().as_ref()],
+        bump,
+    )]
+    pub depositor_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: the depositor is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), depositor.key().as_ref()],
+        bump,
+    )]
+    pub depositor_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the depositor is a canary if this PDA has been created
+    #[account(
+        seeds = [CANARY_SEED.as_bytes(), master.key().as_ref(), depositor.key().as_ref()],
+        bump,
+    )]
+    pub depositor_canary: UncheckedAccount<'info>,
+
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = 8 + size_of::<ReceiverCooldown>(),
+        seeds = [RECEIVER_COOLDOWN_SEED.as_bytes(), master.key().as_ref(), depositor.key().as_ref()],
+        bump,
+    )]
+    pub depositor_cooldown: Option<Account<'info, ReceiverCooldown>>,
+
+    #[account(address = master.admin)]
+    pub admin_cosigner: Option<Signer<'info>>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + size_of::<DepositRefund>(),
+        seeds = [DEPOSIT_REFUND_SEED.as_bytes(), master.key().as_ref(), deposit_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub deposit_refund: Account<'info, DepositRefund>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for Withdraw instruction.
+#[derive(Accounts)]
+#[instruction(amount: u64)]
+pub struct Withdraw<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `WithdrawToCold` instruction.
+#[derive(Accounts)]
+pub struct WithdrawToCold<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+
+    #[account(mut, address = vault_config.cold_wallet @ Errors::ColdWalletMismatch)]
+    pub cold_wallet: SystemAccount<'info>,
+}
+
+/// Accounts for `SweepToCold` instruction.
+#[derive(Accounts)]
+pub struct SweepToCold<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(mut, address = vault_config.cold_wallet @ Errors::ColdWalletMismatch)]
+    pub cold_wallet: SystemAccount<'info>,
+}
+
+/// Accounts for WithdrawToken instruction.
+#[derive(Accounts)]
+pub struct WithdrawToken<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        constraint = vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        mut,
+        address = vault_token.ata,
+        token::mint = token_mint,
+        token::authority = master,
+    )]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(mut, address = master.admin)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = admin,
+        associated_token::token_program = token_program,
+    )]
+    pub admin_ata: Account<'info, TokenAccount>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts for `RescueToken` instruction.
+#[derive(Accounts)]
+#[instruction(mint: Pubkey)]
+pub struct RescueToken<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    /// CHECK: only checked for existence, the mint is supported if this PDA has been created
+    #[account(
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), mint.as_ref()],
+        bump,
+    )]
+    pub vault_token: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        token::mint = token_mint,
+        token::authority = master,
+    )]
+    pub token_account: Account<'info, TokenAccount>,
+
+    #[account(address = master.admin)]
+    pub admin: Signer<'info>,
+
+    #[account(
+        mut,
+        associated_token::mint = token_mint,
+        associated_token::authority = admin,
+        associated_token::token_program = token_program,
+    )]
+    pub admin_ata: Account<'info, TokenAccount>,
+
+    #[account(address = mint)]
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for TransferBetweenVaults instruction.
+#[derive(Accounts)]
+pub struct TransferBetweenVaults<'info> {
+    #[account(
+        mut,
+        constraint = from_master.key() != to_master.key() @ Errors::SameVault,
+        seeds = [MASTER_SEED.as_bytes(), from_master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub from_master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), from_master.key().as_ref()],
+        bump,
+    )]
+    pub from_vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), to_master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub to_master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), to_master.key().as_ref()],
+        bump,
+    )]
+    pub to_vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        constraint = admin.key() == from_master.admin
+            && admin.key() == to_master.admin @ Errors::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+}
+
+/// Accounts for TransferTokenBetweenVaults instruction.
+#[derive(Accounts)]
+pub struct TransferTokenBetweenVaults<'info> {
+    #[account(
+        constraint = from_master.key() != to_master.key() @ Errors::SameVault,
+        seeds = [MASTER_SEED.as_bytes(), from_master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub from_master: Account<'info, Master>,
+
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), to_master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub to_master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        constraint = from_vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), from_master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub from_vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        mut,
+        constraint = to_vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), to_master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub to_vault_token: Account<'info, VaultToken>,
+
+    #[account(mut, address = from_vault_token.ata)]
+    pub from_master_ata: Account<'info, TokenAccount>,
+
+    #[account(mut, address = to_vault_token.ata)]
+    pub to_master_ata: Account<'info, TokenAccount>,
+
+    #[account(
+        constraint = admin.key() == from_master.admin
+            && admin.key() == to_master.admin @ Errors::Unauthorized,
+    )]
+    pub admin: Signer<'info>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+}
+
+/// Accounts for `WrapSol` instruction.
+#[derive(Accounts)]
+pub struct WrapSol<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        mut,
+        constraint = vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(mut, address = vault_token.ata)]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(
+        constraint = authority.key() == master.operator
+            || authority.key() == master.admin @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
+
+    #[account(address = spl_token::native_mint::ID @ Errors::NotNativeMint)]
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
 }
 
-/// Event of some deposit.
-#[event]
-pub struct DepositEvent {
-    /// User which has deposited something.
-    pub user: Pubkey,
-    /// The account the deposit has been placed to.
-    pub holder: Pubkey,
-    /// Amount of SOL or token.
-    pub amount: u64,
-    /// When does the deposit event has happened.
-    pub time: i64,
-}
+/// Accounts for `UnwrapSol` instruction.
+#[derive(Accounts)]
+pub struct UnwrapSol<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        mut,
+        constraint = vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(mut, address = vault_token.ata)]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(
+        init,
+        payer = authority,
+        token::mint = token_mint,
+        token::authority = master,
+        seeds = [UNWRAP_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub unwrap_account: Account<'info, TokenAccount>,
+
+    #[account(
+        mut,
+        constraint = authority.key() == master.operator
+            || authority.key() == master.admin @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
 
-/// Event of a withdraw.
-#[event]
-pub struct WithdrawEvent {
-    /// User which has withdrawn something.
-    pub user: Pubkey,
-    /// The account the withdraw has been taken tokens from.
-    pub holder: Pubkey,
-    /// Amount of SOL or token.
-    pub amount: u64,
-    /// When does the withdraw event has happened.
-    pub time: i64,
+    #[account(address = spl_token::native_mint::ID @ Errors::NotNativeMint)]
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub system_program: Program<'info, System>,
 }
 
-/// Event of admin withdrawal.
-#[event]
-pub struct AdminWithdrawEvent {
-    /// User which has withdrawn something.
-    pub user: Pubkey,
-    /// The account the withdraw has been taken tokens from.
-    pub holder: Pubkey,
-    /// Amount of SOL or token.
-    pub amount: u64,
-    /// When does the withdraw event has happened.
-    pub time: i64,
+/// Accounts for `SweepUntrackedSol` instruction.
+#[derive(Accounts)]
+pub struct SweepUntrackedSol<'info> {
+    #[account(
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        constraint = authority.key() == master.operator
+            || authority.key() == master.admin @ Errors::Unauthorized,
+    )]
+    pub authority: Signer<'info>,
 }
 
-const MASTER_SIZE: usize = size_of::<Master>() + 8;
-/// `Master` account, which is the main account of the smart contract.
-#[account]
-pub struct Master {
-    /// Solana stored in the smart contract.
-    pub balance: u64,
-    /// Tokens stored into the PDA of the smart contract.
-    pub token_balance: u64,
-    /// Associated token account for the master.
-    pub token_account: Option<Pubkey>,
-    /// Last time some withdraw has happen.
-    pub last_withdraw_time: i64,
-    /// Operator which is allowed to transfer token.
-    pub operator: Pubkey,
-    /// Admin which is allowed to manage the smart contract.
-    pub admin: Pubkey,
+/// Accounts for `EmitStats` instruction.
+#[derive(Accounts)]
+pub struct EmitStats<'info> {
+    #[account(
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub master: Account<'info, Master>,
+
+    #[account(
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), vault_token.mint.as_ref()],
+        bump,
+    )]
+    pub vault_token: Option<Account<'info, VaultToken>>,
 }
 
-/// Accounts for `InitMaster` instruction.
+/// Accounts for SendWithdrawToken instruction.
 #[derive(Accounts)]
-pub struct InitMaster<'info> {
+#[instruction(amount: u64, request_id: Option<u64>)]
+pub struct SendWithdrawToken<'info> {
     #[account(
-        init,
-        payer = payer,
-        space = MASTER_SIZE,//8 + 8 + 32 + 32 + 8,
-        seeds = [MASTER_SEED.as_bytes()],
+        mut,
+        seeds = [MASTER_SEED.as_bytes(), master.vault_id.to_le_bytes().as_ref()],
         bump,
     )]
     pub master: Account<'info, Master>,
 
+    #[account(
+        mut,
+        seeds = [VAULT_CONFIG_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub vault_config: Account<'info, VaultConfig>,
+
+    #[account(
+        seeds = [PROGRAM_CONFIG_SEED.as_bytes()],
+        bump,
+        constraint = !program_config.paused @ Errors::ProgramPaused,
+    )]
+    pub program_config: Account<'info, ProgramConfig>,
+
+    #[account(
+        mut,
+        constraint = vault_token.mint == token_mint.key() @ Errors::MintMismatch,
+        seeds = [VAULT_TOKEN_SEED.as_bytes(), master.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+    )]
+    pub vault_token: Account<'info, VaultToken>,
+
+    #[account(
+        mut,
+        address = vault_token.ata,
+        token::mint = token_mint,
+        token::authority = master,
+    )]
+    pub master_ata: Account<'info, TokenAccount>,
+
+    #[account(mut, address = master.operator)]
+    pub operator: Signer<'info>,
+
+    /// CHECK: token account of the receiver for the mint, checked by the payout. Its ATA is created if it does not
+    /// exist yet.
     #[account(mut)]
-    pub payer: Signer<'info>,
+    pub receiver_ata: UncheckedAccount<'info>,
+
+    #[account(mut)]
+    pub receiver: SystemAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is allowlisted if this PDA has been created
+    #[account(
+        seeds = [RECEIVER_ALLOWLIST_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_allowlist: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is blocked if this PDA has been created
+    #[account(
+        seeds = [BLOCKED_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_blocked: UncheckedAccount<'info>,
+
+    /// CHECK: the receiver is frozen if this PDA has been created and its frozen flag is set
+    #[account(
+        seeds = [USER_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_state: UncheckedAccount<'info>,
+
+    /// CHECK: only checked for existence, the receiver is a canary if this PDA has been created
+    #[account(
+        seeds = [CANARY_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_canary: UncheckedAccount<'info>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::ProductLedgerMismatch,
+        constraint = product_ledger.asset == token_mint.key() @ Errors::ProductLedgerMismatch,
+    )]
+    pub product_ledger: Option<Account<'info, ProductLedger>>,
+
+    #[account(
+        mut,
+        close = operator,
+        has_one = master @ Errors::ReceiverLockMismatch,
+        has_one = receiver @ Errors::ReceiverLockMismatch,
+    )]
+    pub receiver_lock: Option<Account<'info, ReceiverLock>>,
+
+    #[account(
+        init_if_needed,
+        payer = operator,
+        space = 8 + size_of::<ReceiverCooldown>(),
+        seeds = [RECEIVER_COOLDOWN_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub receiver_cooldown: Option<Account<'info, ReceiverCooldown>>,
+
+    #[account(
+        seeds = [LIMIT_EXEMPT_SEED.as_bytes(), master.key().as_ref(), receiver.key().as_ref()],
+        bump,
+    )]
+    pub limit_exempt: Option<Account<'info, LimitExemptReceiver>>,
+
+    #[account(address = master.admin)]
+    pub admin_cosigner: Option<Signer<'info>>,
+
+    #[account(
+        mut,
+        has_one = master @ Errors::WithdrawRequestMismatch,
+        constraint = withdraw_request.user == receiver.key() @ Errors::WithdrawRequestMismatch,
+        constraint = withdraw_request.mint == Some(token_mint.key()) @ Errors::WithdrawRequestMismatch,
+    )]
+    pub withdraw_request: Option<Account<'info, WithdrawRequest>>,
+
+    #[account(
+        mut,
+        seeds = [WITHDRAW_QUEUE_SEED.as_bytes(), master.key().as_ref()],
+        bump,
+    )]
+    pub withdraw_queue: Option<Account<'info, WithdrawQueue>>,
+
+    #[account(
+        init,
+        payer = operator,
+        space = 8 + size_of::<Receipt>(),
+        seeds = [
+            RECEIPT_SEED.as_bytes(),
+            master.key().as_ref(),
+            request_id.unwrap_or_default().to_le_bytes().as_ref(),
+        ],
+        bump,
+    )]
+    pub receipt: Option<Account<'info, Receipt>>,
+
+    pub memo_program: Option<Program<'info, Memo>>,
+
+    /// CHECK: penalty ledger of the operator, netted against the reimbursed ATA rent if it has been created
+    #[account(
+        mut,
+        seeds = [PENALTY_SEED.as_bytes(), master.key().as_ref(), operator.key().as_ref()],
+        bump,
+    )]
+    pub penalty_ledger: UncheckedAccount<'info>,
+
+    pub token_mint: Account<'info, Mint>,
+
+    pub token_program: Program<'info, Token>,
+
+    pub associated_token_program: Program<'info, AssociatedToken>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_account<T>(lamports: u64, data_len: usize, f: impl FnOnce(&AccountInfo) -> T) -> T {
+        let key = Pubkey::new_unique();
+        let mut lamports = lamports;
+        let mut data = vec![0u8; data_len];
+        let account = AccountInfo::new(&key, false, true, &mut lamports, &mut data, &ID, false, 0);
+        f(&account)
+    }
+
+    #[test]
+    fn quote_currency_must_be_uppercase_letters() {
+        let quote = |currency: &[u8; 3]| {
+            Some(FiatQuote {
+                value: 100,
+                currency: *currency,
+            })
+        };
+
+        assert!(require_valid_quote(&None).is_ok());
+        assert!(require_valid_quote(&quote(b"USD")).is_ok());
+        assert!(require_valid_quote(&quote(b"usd")).is_err());
+        assert!(require_valid_quote(&quote(b"US\0")).is_err());
+    }
+
+    fn with_state<T: AccountSerialize>(state: &T, f: impl FnOnce(&AccountInfo)) {
+        let mut data = Vec::new();
+        state.try_serialize(&mut data).unwrap();
+        with_account(1, data.len(), |account| {
+            account
+                .try_borrow_mut_data()
+                .unwrap()
+                .copy_from_slice(&data);
+            f(account)
+        });
+    }
+
+    #[test]
+    fn penalty_is_netted_against_reimbursement() {
+        let mut ledger = PenaltyLedger {
+            operator: Pubkey::new_unique(),
+            outstanding: 150,
+            total_recorded: 150,
+            fault_count: 1,
+            last_fault_time: 0,
+        };
+
+        assert_eq!(ledger.net_against(100), 0);
+        assert_eq!(ledger.outstanding, 50);
+        assert_eq!(ledger.net_against(80), 30);
+        assert_eq!(ledger.outstanding, 0);
+        assert_eq!(ledger.net_against(10), 10);
+        assert_eq!(ledger.total_recorded, 150);
+    }
+
+    #[test]
+    fn penalty_ledger_account_is_netted_against_reimbursement() {
+        with_account(0, 0, |account| {
+            assert_eq!(net_penalty(account, 100).unwrap(), 100);
+        });
+
+        let ledger = PenaltyLedger {
+            operator: Pubkey::new_unique(),
+            outstanding: 150,
+            total_recorded: 150,
+            fault_count: 1,
+            last_fault_time: 0,
+        };
+        with_state(&ledger, |account| {
+            assert_eq!(net_penalty(account, 100).unwrap(), 0);
+            let data = account.try_borrow_data().unwrap();
+            let ledger = PenaltyLedger::try_deserialize(&mut &data[..]).unwrap();
+            assert_eq!(ledger.outstanding, 50);
+        });
+    }
+
+    fn overflow_error() -> Error {
+        Errors::MathUnderflowOrOverflow.into()
+    }
+
+    #[test]
+    fn rate_window_overflow_leaves_window_untouched() {
+        let mut window = RateWindow {
+            start: 100,
+            amount: u64::MAX - 1,
+        };
+
+        assert_eq!(window.record(100, 60, 1).unwrap(), u64::MAX);
+        assert_eq!(window.record(100, 60, 1).unwrap_err(), overflow_error());
+        assert_eq!(window.start, 100);
+        assert_eq!(window.amount, u64::MAX);
+
+        // a new window starts from zero, so a full window does not poison later ones
+        assert_eq!(window.record(160, 60, u64::MAX).unwrap(), u64::MAX);
+        assert_eq!(window.start, 160);
+    }
+
+    #[test]
+    fn circuit_breaker_overflow_fails_without_tripping() {
+        test_sysvars::set_unix_timestamp(1_000);
+        let mut master = Master {
+            outflow: RateWindow {
+                start: 1_000,
+                amount: u64::MAX,
+            },
+            ..Default::default()
+        };
+        let mut vault_config = VaultConfig {
+            outflow_window_secs: 3_600,
+            outflow_limit: u64::MAX,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            trip_circuit_breaker(
+                &mut master,
+                &mut vault_config,
+                None,
+                Pubkey::new_unique(),
+                1
+            )
+            .unwrap_err(),
+            overflow_error()
+        );
+        assert!(!vault_config.withdrawals_paused);
+        assert_eq!(master.outflow.amount, u64::MAX);
+        test_sysvars::reset();
+    }
+
+    #[test]
+    fn penalty_ledger_overflow_leaves_ledger_untouched() {
+        let full = PenaltyLedger {
+            operator: Pubkey::new_unique(),
+            outstanding: 0,
+            total_recorded: u64::MAX,
+            fault_count: 1,
+            last_fault_time: 1,
+        };
+
+        let mut ledger = PenaltyLedger { ..full };
+        assert_eq!(ledger.record_fault(1, 2).unwrap_err(), overflow_error());
+        assert_eq!(ledger.outstanding, 0);
+        assert_eq!(ledger.last_fault_time, 1);
+
+        let mut ledger = PenaltyLedger {
+            total_recorded: 0,
+            fault_count: u32::MAX,
+            ..full
+        };
+        assert_eq!(ledger.record_fault(1, 2).unwrap_err(), overflow_error());
+        assert_eq!(ledger.total_recorded, 0);
+
+        let mut ledger = PenaltyLedger {
+            total_recorded: u64::MAX - 1,
+            ..full
+        };
+        ledger.record_fault(1, 2).unwrap();
+        assert_eq!(ledger.total_recorded, u64::MAX);
+        assert_eq!(ledger.fault_count, 2);
+    }
+
+    #[test]
+    fn net_flow_limits_daily_inflow() {
+        let mut net_flow = NetFlow::default();
+        test_sysvars::set_unix_timestamp(SECONDS_PER_DAY * 10);
+
+        record_inflow(&mut net_flow, 100, 60).unwrap();
+        record_outflow(&mut net_flow, 30).unwrap();
+        record_inflow(&mut net_flow, 100, 70).unwrap();
+        assert_eq!(net_flow.net_inflow, 100);
+        assert_eq!(
+            record_inflow(&mut net_flow, 100, 1).unwrap_err(),
+            Errors::ExposureLimitExceeded.into()
+        );
+        assert!(record_inflow(&mut net_flow, 0, 1_000).is_ok());
+
+        test_sysvars::set_unix_timestamp(SECONDS_PER_DAY * 11);
+        record_inflow(&mut net_flow, 100, 100).unwrap();
+        assert_eq!(net_flow.day, 11);
+
+        record_outflow(&mut net_flow, u64::MAX).unwrap();
+        assert_eq!(net_flow.net_inflow, 100 - i64::MAX);
+        test_sysvars::reset();
+    }
+
+    #[test]
+    fn net_flow_rolls_over_at_midnight() {
+        let mut net_flow = NetFlow::default();
+
+        assert_eq!(net_flow.record(SECONDS_PER_DAY * 11 - 1, 40).unwrap(), 40);
+        assert_eq!(net_flow.day, 10);
+        assert_eq!(net_flow.record(SECONDS_PER_DAY * 11, 5).unwrap(), 5);
+        assert_eq!(net_flow.day, 11);
+    }
+
+    #[test]
+    fn lifetime_stats_saturate() {
+        let mut lifetime = LifetimeStats::default();
+
+        lifetime.record_deposit(70);
+        lifetime.record_deposit(u64::MAX);
+        lifetime.record_withdrawal(30);
+        assert_eq!(lifetime.total_deposited, u64::MAX);
+        assert_eq!(lifetime.deposit_count, 2);
+        assert_eq!(lifetime.total_withdrawn, 30);
+        assert_eq!(lifetime.withdraw_count, 1);
+    }
+
+    #[test]
+    fn ui_amount_applies_decimals() {
+        assert_eq!(ui_amount(1_500_000, 6), "1.5");
+        assert_eq!(ui_amount(2_000_000_000, SOL_DECIMALS), "2");
+        assert_eq!(ui_amount(1, SOL_DECIMALS), "0.000000001");
+    }
+
+    #[test]
+    fn rate_window_rolls_over() {
+        let mut window = RateWindow::default();
+
+        assert_eq!(window.record(100, 60, 10).unwrap(), 10);
+        assert_eq!(window.record(159, 60, 5).unwrap(), 15);
+        assert_eq!(window.record(160, 60, 7).unwrap(), 7);
+        assert_eq!(window.start, 160);
+    }
+
+    #[test]
+    fn circuit_breaker_pauses_payouts_over_limit() {
+        test_sysvars::set_unix_timestamp(1_000);
+        let mut master = Master::default();
+        let mut vault_config = VaultConfig {
+            outflow_window_secs: 3_600,
+            outflow_limit: 100,
+            ..Default::default()
+        };
+        let mut vault_token = VaultToken {
+            mint: Pubkey::new_unique(),
+            ata: Pubkey::new_unique(),
+            enabled: true,
+            ..Default::default()
+        };
+        let operator = Pubkey::new_unique();
+
+        assert!(!trip_circuit_breaker(&mut master, &mut vault_config, None, operator, 60).unwrap());
+        assert!(!trip_circuit_breaker(
+            &mut master,
+            &mut vault_config,
+            Some(&mut vault_token),
+            operator,
+            1_000
+        )
+        .unwrap());
+        assert!(trip_circuit_breaker(&mut master, &mut vault_config, None, operator, 60).unwrap());
+        assert!(vault_config.withdrawals_paused);
+        assert_eq!(master.outflow.amount, 60);
+        test_sysvars::reset();
+    }
+
+    #[test]
+    fn daily_withdraw_limit_rejects_payouts_over_limit() {
+        test_sysvars::set_unix_timestamp(1_000);
+        let mut master = Master::default();
+        let vault_config = VaultConfig {
+            daily_withdraw_limit: 100,
+            ..Default::default()
+        };
+
+        record_daily_withdrawal(&mut master, &vault_config, None, 60).unwrap();
+        assert!(record_daily_withdrawal(&mut master, &vault_config, None, 41).is_err());
+        assert_eq!(master.daily_outflow.amount, 60);
+        record_daily_withdrawal(&mut master, &vault_config, None, 40).unwrap();
+
+        test_sysvars::set_unix_timestamp(1_000 + SECONDS_PER_DAY);
+        record_daily_withdrawal(&mut master, &vault_config, None, 100).unwrap();
+        test_sysvars::reset();
+    }
+
+    #[test]
+    fn token_daily_withdraw_limit_is_kept_per_mint() {
+        test_sysvars::set_unix_timestamp(1_000);
+        let mut master = Master::default();
+        let vault_config = VaultConfig {
+            daily_withdraw_limit: 100,
+            ..Default::default()
+        };
+        let mut vault_token = VaultToken {
+            daily_withdraw_limit: 50,
+            ..Default::default()
+        };
+
+        record_daily_withdrawal(&mut master, &vault_config, Some(&mut vault_token), 50).unwrap();
+        assert!(
+            record_daily_withdrawal(&mut master, &vault_config, Some(&mut vault_token), 1).is_err()
+        );
+        assert_eq!(vault_token.daily_outflow.amount, 50);
+        assert_eq!(master.daily_outflow.amount, 0);
+        record_daily_withdrawal(&mut master, &vault_config, None, 100).unwrap();
+        test_sysvars::reset();
+    }
+
+    #[test]
+    fn deposit_velocity_rejects_deposits() {
+        test_sysvars::set_unix_timestamp(1_000);
+        let mut master = Master::default();
+        let vault_config = VaultConfig {
+            deposit_velocity_window_secs: 60,
+            deposit_velocity_max_count: 2,
+            deposit_velocity_max_volume: 100,
+            ..Default::default()
+        };
+
+        record_deposit_velocity(&mut master, &vault_config, None, 60).unwrap();
+        assert_eq!(
+            record_deposit_velocity(&mut master, &vault_config, None, 41).unwrap_err(),
+            Errors::DepositVelocityExceeded.into()
+        );
+        assert_eq!(master.deposit_volume.amount, 60);
+        assert_eq!(master.deposit_count.amount, 1);
+        let (count, volume, within) =
+            next_deposit_velocity(&master, &vault_config, None, 41).unwrap();
+        assert!(!within);
+        assert_eq!((count.amount, volume.amount), (2, 101));
+
+        record_deposit_velocity(&mut master, &vault_config, None, 40).unwrap();
+        assert!(record_deposit_velocity(&mut master, &vault_config, None, 0).is_err());
 
-    pub admin: SystemAccount<'info>,
+        test_sysvars::set_unix_timestamp(1_060);
+        record_deposit_velocity(&mut master, &vault_config, None, 100).unwrap();
+        test_sysvars::reset();
+    }
 
-    pub operator: SystemAccount<'info>,
+    #[test]
+    fn hot_balance_keeps_reserve_for_cold_wallet() {
+        let mut master = Master {
+            balance: 150,
+            ..Default::default()
+        };
 
-    pub system_program: Program<'info, System>,
-}
+        assert!(draw_hot_balance(&mut master, 100, 101).is_err());
+        draw_hot_balance(&mut master, 100, 100).unwrap();
+        assert_eq!(master.cold_reserve, 50);
 
-/// Accounts for `InitAta` instruction.
-#[derive(Accounts)]
-pub struct InitAta<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+        master.balance = 80;
+        assert!(draw_hot_balance(&mut master, 100, 31).is_err());
+        draw_hot_balance(&mut master, 100, 30).unwrap();
+        assert_eq!(master.cold_reserve, 50);
+    }
 
-    #[account(
-        init,
-        payer = user,
-        associated_token::mint = token_mint,
-        associated_token::authority = master,
-        associated_token::token_program = token_program,
-    )]
-    pub master_ata: Account<'info, TokenAccount>,
+    #[test]
+    fn sol_reserve_never_goes_below_rent_exemption() {
+        test_sysvars::set_rent(Rent::default());
+        let minimum = Rent::default().minimum_balance(MASTER_SIZE);
 
-    pub token_mint: Account<'info, Mint>,
+        with_account(minimum, MASTER_SIZE, |account| {
+            assert!(require_sol_reserve(account, 0, minimum).is_ok());
+            assert!(require_sol_reserve(account, minimum - 1, minimum - 1).is_err());
+            assert!(require_sol_reserve(account, minimum + 5_000, minimum + 5_000).is_ok());
+            assert_eq!(
+                require_sol_reserve(account, minimum + 5_000, minimum + 4_999).unwrap_err(),
+                Errors::SolReserveRequired.into()
+            );
+        });
+        test_sysvars::reset();
+    }
 
-    #[account(mut, address = master.admin)]
-    pub user: Signer<'info>,
+    #[test]
+    fn apply_config_returns_old_value() {
+        let mut vault_config = VaultConfig {
+            min_deposit: 1_000,
+            ..Default::default()
+        };
+        let mut vault_token = VaultToken {
+            min_deposit: 10,
+            ..Default::default()
+        };
 
-    pub token_program: Program<'info, Token>,
+        assert_eq!(
+            vault_config.apply_config(ConfigSetting::MinDeposit(5_000)),
+            Some(ConfigSetting::MinDeposit(1_000))
+        );
+        assert_eq!(vault_config.min_deposit, 5_000);
+        assert_eq!(
+            vault_config.apply_config(ConfigSetting::DepositsPaused(true)),
+            Some(ConfigSetting::DepositsPaused(false))
+        );
+        assert!(vault_config.deposits_paused);
 
-    pub associated_token_program: Program<'info, AssociatedToken>,
+        assert_eq!(
+            vault_config.apply_config(ConfigSetting::TokenMinDeposit(50)),
+            None
+        );
+        assert_eq!(
+            vault_token.apply_config(ConfigSetting::TokenMinDeposit(50)),
+            Some(ConfigSetting::TokenMinDeposit(10))
+        );
+        assert_eq!(vault_token.min_deposit, 50);
+        assert_eq!(
+            vault_token.apply_config(ConfigSetting::MinDeposit(50)),
+            None
+        );
+    }
 
-    pub system_program: Program<'info, System>,
-}
+    #[test]
+    fn change_config_checks_the_value() {
+        test_sysvars::set_unix_timestamp(1_000);
+        let mut master = Master {
+            claim_mode: true,
+            ..Default::default()
+        };
+        let mut vault_config = VaultConfig::default();
+        let admin = Pubkey::new_unique();
+        let change = |master: &mut Master, vault_config: &mut VaultConfig, setting| {
+            change_config(master, vault_config, None, admin, setting)
+        };
 
-/// Accounts for `SetOperator` instruction.
-#[derive(Accounts)]
-pub struct SetOperator<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+        assert!(change(
+            &mut master,
+            &mut vault_config,
+            ConfigSetting::WithdrawalsPaused(false)
+        )
+        .is_err());
+        assert!(change(
+            &mut master,
+            &mut vault_config,
+            ConfigSetting::OutflowWindowSecs(0)
+        )
+        .is_err());
+        assert!(change(
+            &mut master,
+            &mut vault_config,
+            ConfigSetting::HotBalanceThreshold(1)
+        )
+        .is_err());
+        assert!(change(
+            &mut master,
+            &mut vault_config,
+            ConfigSetting::TokenVaultCap(1)
+        )
+        .is_err());
+        assert_eq!(master.config_generation, 0);
 
-    #[account(mut, address = master.admin)]
-    pub admin: Signer<'info>,
+        change(
+            &mut master,
+            &mut vault_config,
+            ConfigSetting::DeadManPeriod(60),
+        )
+        .unwrap();
+        assert_eq!(vault_config.dead_man_period, 60);
+        assert_eq!(master.heartbeat_time, 1_000);
+        assert_eq!(master.config_generation, 1);
+        test_sysvars::reset();
+    }
 
-    pub new_operator: SystemAccount<'info>,
+    #[test]
+    fn change_config_checks_dependent_settings() {
+        test_sysvars::set_unix_timestamp(1_000);
+        let mut master = Master::default();
+        let mut vault_config = VaultConfig {
+            deposit_velocity_window_secs: 60,
+            deposit_velocity_max_count: 10,
+            max_deposit: 100,
+            ..Default::default()
+        };
+        let mut vault_token = VaultToken {
+            min_deposit: 50,
+            ..Default::default()
+        };
+        let admin = Pubkey::new_unique();
 
-    pub system_program: Program<'info, System>,
-}
+        assert_eq!(
+            change_config(
+                &mut master,
+                &mut vault_config,
+                None,
+                admin,
+                ConfigSetting::DepositVelocityWindowSecs(0)
+            )
+            .unwrap_err(),
+            Errors::InvalidWindow.into()
+        );
+        assert_eq!(
+            change_config(
+                &mut master,
+                &mut vault_config,
+                None,
+                admin,
+                ConfigSetting::MinDeposit(101)
+            )
+            .unwrap_err(),
+            Errors::InvalidDepositLimits.into()
+        );
+        assert_eq!(
+            change_config(
+                &mut master,
+                &mut vault_config,
+                Some(&mut vault_token),
+                admin,
+                ConfigSetting::TokenMaxDeposit(49)
+            )
+            .unwrap_err(),
+            Errors::InvalidDepositLimits.into()
+        );
+        assert_eq!(master.config_generation, 0);
 
-/// Accounts for `SetAdmin` instruction.
-#[derive(Accounts)]
-pub struct SetAdmin<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+        for setting in [
+            ConfigSetting::DepositVelocityMaxCount(0),
+            ConfigSetting::DepositVelocityWindowSecs(0),
+            ConfigSetting::MaxDeposit(0),
+            ConfigSetting::MinDeposit(101),
+        ] {
+            change_config(&mut master, &mut vault_config, None, admin, setting).unwrap();
+        }
+        assert_eq!(
+            change_config(
+                &mut master,
+                &mut vault_config,
+                None,
+                admin,
+                ConfigSetting::DepositVelocityMaxVolume(1)
+            )
+            .unwrap_err(),
+            Errors::InvalidWindow.into()
+        );
+        change_config(
+            &mut master,
+            &mut vault_config,
+            Some(&mut vault_token),
+            admin,
+            ConfigSetting::TokenMaxDeposit(50),
+        )
+        .unwrap();
+        assert_eq!(vault_token.max_deposit, 50);
+        assert_eq!(master.config_generation, 5);
+        test_sysvars::reset();
+    }
 
-    #[account(mut, address = master.admin)]
-    pub admin: Signer<'info>,
+    #[test]
+    fn operator_is_silent_after_dead_man_period() {
+        assert_eq!(
+            require_operator_silent(1_000, 100, 1_100).unwrap_err(),
+            Errors::OperatorAlive.into()
+        );
+        assert!(require_operator_silent(1_000, 100, 1_101).is_ok());
+        assert_eq!(
+            require_operator_silent(i64::MIN, 100, 1_000).unwrap_err(),
+            Errors::MathUnderflowOrOverflow.into()
+        );
+    }
 
-    pub new_admin: SystemAccount<'info>,
+    #[test]
+    fn self_withdrawal_is_deducted_from_claim_of_same_root() {
+        let balance_root = [7; 32];
+        let self_withdrawal = SelfWithdrawal {
+            user: Pubkey::new_unique(),
+            balance_root,
+            last_withdraw_time: 0,
+            withdrawn: 40,
+        };
 
-    pub system_program: Program<'info, System>,
-}
+        with_state(&self_withdrawal, |account| {
+            assert_eq!(self_withdrawn_against(account, &balance_root).unwrap(), 40);
+            assert_eq!(self_withdrawn_against(account, &[8; 32]).unwrap(), 0);
+        });
+        with_account(0, 0, |account| {
+            assert_eq!(self_withdrawn_against(account, &balance_root).unwrap(), 0);
+        });
+    }
 
-/// Accounts for `Deposit` instruction.
-#[derive(Accounts)]
-#[instruction(amount: u64)]
-pub struct Deposit<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+    #[test]
+    fn legacy_master_decodes_baseline_layout() {
+        let legacy = LegacyMaster {
+            balance: 5_000_000_000,
+            token_balance: 1_000_000,
+            token_account: Some(Pubkey::new_unique()),
+            last_withdraw_time: 1_700_000_000,
+            operator: Pubkey::new_unique(),
+            admin: Pubkey::new_unique(),
+        };
+        // The baseline account was sized for `size_of` of its struct, so the borsh data is followed by zeros.
+        let mut data = <Master as anchor_lang::Discriminator>::DISCRIMINATOR.to_vec();
+        legacy.serialize(&mut data).unwrap();
+        data.resize(136, 0);
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+        assert_eq!(LegacyMaster::try_from_data(&data).unwrap(), legacy);
 
-    pub system_program: Program<'info, System>,
-}
+        data[0] ^= 1;
+        assert_eq!(
+            LegacyMaster::try_from_data(&data).unwrap_err(),
+            ErrorCode::AccountDiscriminatorMismatch.into()
+        );
+    }
 
-/// Accounts for `DepositToken` instruction.
-#[derive(Accounts)]
-pub struct DepositToken<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+    #[test]
+    fn payout_hours_need_cosign_outside_window() {
+        // 09:00 - 17:59 UTC
+        let office_hours = ((1 << 18) - 1) & !((1 << 9) - 1);
+        let day = 19_000 * SECONDS_PER_DAY;
 
-    #[account(
-        mut,
-        address = master.token_account.expect("token account has not been initialized"),
-        associated_token::mint = token_mint,
-        associated_token::authority = master,
-        associated_token::token_program = token_program,
-    )]
-    pub master_ata: Account<'info, TokenAccount>,
+        assert!(require_payout_hours(0, day + 4 * SECONDS_PER_HOUR, false).is_ok());
+        assert!(require_payout_hours(office_hours, day + 9 * SECONDS_PER_HOUR, false).is_ok());
+        assert!(require_payout_hours(office_hours, day + 18 * SECONDS_PER_HOUR - 1, false).is_ok());
+        assert_eq!(
+            require_payout_hours(office_hours, day + 4 * SECONDS_PER_HOUR, false).unwrap_err(),
+            Errors::OutsidePayoutHours.into()
+        );
+        assert!(require_payout_hours(office_hours, day + 4 * SECONDS_PER_HOUR, true).is_ok());
+    }
 
-    #[account(
-        mut,
-        associated_token::mint = token_mint,
-        associated_token::authority = user,
-        associated_token::token_program = token_program,
-    )]
-    pub from: Account<'info, TokenAccount>,
+    #[test]
+    fn frozen_user_is_rejected() {
+        let user = Pubkey::new_unique();
 
-    #[account(mut)]
-    pub user: Signer<'info>,
+        with_state(&UserState { user, frozen: true }, |account| {
+            assert_eq!(
+                require_not_frozen(account).unwrap_err(),
+                Errors::UserFrozen.into()
+            );
+        });
+        with_state(
+            &UserState {
+                user,
+                frozen: false,
+            },
+            |account| {
+                assert!(require_not_frozen(account).is_ok());
+            },
+        );
+        with_account(0, 0, |account| {
+            assert!(require_not_frozen(account).is_ok());
+        });
+    }
 
-    pub token_mint: Account<'info, Mint>,
+    #[test]
+    fn full_program_config_fits_its_space() {
+        let program_config = ProgramConfig {
+            super_admin: Pubkey::new_unique(),
+            max_fee_bps: MAX_BPS,
+            paused: true,
+            allowed_mints: vec![Pubkey::new_unique(); MAX_ALLOWED_MINTS],
+        };
+        let mut data = Vec::new();
+        program_config.try_serialize(&mut data).unwrap();
 
-    pub token_program: Program<'info, Token>,
+        assert_eq!(data.len(), PROGRAM_CONFIG_SIZE);
+    }
 
-    pub system_program: Program<'info, System>,
-}
+    #[test]
+    fn full_vault_registry_fits_its_space() {
+        let vault_registry = VaultRegistry {
+            vaults: vec![
+                VaultEntry {
+                    vault_id: u64::MAX,
+                    master: Pubkey::new_unique(),
+                    created_at: i64::MAX,
+                };
+                MAX_VAULTS
+            ],
+        };
+        let mut data = Vec::new();
+        vault_registry.try_serialize(&mut data).unwrap();
 
-/// Accounts for `SendWithdraw` instruction.
-#[derive(Accounts)]
-#[instruction(amount: u64)]
-pub struct SendWithdraw<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+        assert_eq!(data.len(), VAULT_REGISTRY_SIZE);
+    }
 
-    #[account(mut, address = master.operator)]
-    pub operator: Signer<'info>,
+    #[test]
+    fn canary_payout_stops_payouts() {
+        test_sysvars::set_unix_timestamp(0);
+        let mut master = Master {
+            operator: Pubkey::new_unique(),
+            ..Master::default()
+        };
+        let receiver = Pubkey::new_unique();
+        let operator = master.operator;
 
-    #[account(mut)]
-    pub receiver: SystemAccount<'info>,
+        let mut vault_config = VaultConfig::default();
 
-    pub system_program: Program<'info, System>,
-}
+        with_account(0, 0, |account| {
+            assert!(!trip_canary(
+                &mut master,
+                &mut vault_config,
+                account,
+                receiver,
+                operator,
+                1
+            )
+            .unwrap());
+        });
+        assert!(!vault_config.withdrawals_paused);
 
-/// Accounts for Withdraw instruction.
-#[derive(Accounts)]
-#[instruction(amount: u64)]
-pub struct Withdraw<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+        with_state(&Canary { receiver }, |account| {
+            assert!(trip_canary(
+                &mut master,
+                &mut vault_config,
+                account,
+                receiver,
+                operator,
+                1
+            )
+            .unwrap());
+        });
+        assert!(vault_config.withdrawals_paused);
+        assert_eq!(master.config_generation, 1);
+        test_sysvars::reset();
+    }
 
-    #[account(mut, address = master.admin)]
-    pub admin: Signer<'info>,
+    #[test]
+    fn deposit_source_is_classified() {
+        // Program ids are keypair addresses, so on the curve like any wallet.
+        let wallet = ID;
+        let (pda, _) = Pubkey::find_program_address(&[USER_SEED.as_bytes()], &ID);
 
-    pub system_program: Program<'info, System>,
-}
+        with_account(0, 0, |account| {
+            assert_eq!(deposit_source(&wallet, account), DepositSource::Wallet);
+            assert_eq!(deposit_source(&pda, account), DepositSource::Pda);
+        });
+        with_state(&ExchangeWallet { address: wallet }, |account| {
+            assert_eq!(deposit_source(&wallet, account), DepositSource::Exchange);
+        });
+    }
 
-/// Accounts for WithdrawToken instruction.
-#[derive(Accounts)]
-pub struct WithdrawToken<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+    #[test]
+    fn ed25519_instruction_must_sign_message() {
+        let signer = Pubkey::new_unique();
+        let message = b"voucher";
+        let instruction = |index: u16| {
+            // offsets, then public key at 16, signature at 48 and message at 112
+            let mut data = vec![1, 0];
+            for value in [48, index, 16, index, 112, message.len() as u16, index] {
+                data.extend_from_slice(&value.to_le_bytes());
+            }
+            data.extend_from_slice(signer.as_ref());
+            data.extend_from_slice(&[0; 64]);
+            data.extend_from_slice(message);
+            data
+        };
 
-    #[account(
-        mut,
-        address = master.token_account.expect("token account has not been initialized"),
-        associated_token::mint = token_mint,
-        associated_token::authority = master,
-        associated_token::token_program = token_program,
-    )]
-    pub master_ata: Account<'info, TokenAccount>,
+        assert!(ed25519_signs(&instruction(u16::MAX), &signer, message));
+        assert!(!ed25519_signs(
+            &instruction(u16::MAX),
+            &Pubkey::new_unique(),
+            message
+        ));
+        assert!(!ed25519_signs(&instruction(u16::MAX), &signer, b"vouchers"));
+        // data pointing into another instruction of the transaction
+        assert!(!ed25519_signs(&instruction(0), &signer, message));
+        assert!(!ed25519_signs(
+            &instruction(u16::MAX)[..100],
+            &signer,
+            message
+        ));
+    }
 
-    #[account(mut, address = master.admin)]
-    pub admin: Signer<'info>,
+    #[test]
+    fn secp256k1_instruction_must_sign_message() {
+        let eth_address = [7; 20];
+        let message = b"voucher";
+        let instruction = |index: u8| {
+            // offsets, then eth address at 12, signature and recovery id at 32 and message at 97
+            let mut data = vec![1];
+            data.extend_from_slice(&32u16.to_le_bytes());
+            data.push(index);
+            data.extend_from_slice(&12u16.to_le_bytes());
+            data.push(index);
+            data.extend_from_slice(&97u16.to_le_bytes());
+            data.extend_from_slice(&(message.len() as u16).to_le_bytes());
+            data.push(index);
+            data.extend_from_slice(&eth_address);
+            data.extend_from_slice(&[0; 65]);
+            data.extend_from_slice(message);
+            data
+        };
 
-    #[account(
-        mut,
-        associated_token::mint = token_mint,
-        associated_token::authority = admin,
-        associated_token::token_program = token_program,
-    )]
-    pub admin_ata: Account<'info, TokenAccount>,
+        assert!(secp256k1_signs(&instruction(0), 0, &eth_address, message));
+        assert!(!secp256k1_signs(&instruction(0), 0, &[8; 20], message));
+        assert!(!secp256k1_signs(
+            &instruction(0),
+            0,
+            &eth_address,
+            b"vouchers"
+        ));
+        // data pointing into another instruction of the transaction
+        assert!(!secp256k1_signs(&instruction(1), 0, &eth_address, message));
+        assert!(!secp256k1_signs(
+            &instruction(0)[..100],
+            0,
+            &eth_address,
+            message
+        ));
+    }
 
-    pub token_mint: Account<'info, Mint>,
+    #[test]
+    fn merkle_proof_reaches_root() {
+        let user = Pubkey::new_unique();
+        let leaves: Vec<[u8; 32]> = (1..=3)
+            .map(|amount| payout_leaf(&user, amount, 3))
+            .collect();
+        let pair = merkle_root(leaves[0], &[leaves[1]]);
+        let root = merkle_root(pair, &[leaves[2]]);
 
-    pub token_program: Program<'info, Token>,
+        assert_eq!(merkle_root(leaves[0], &[leaves[1], leaves[2]]), root);
+        assert_eq!(merkle_root(leaves[1], &[leaves[0], leaves[2]]), root);
+        assert_eq!(merkle_root(leaves[2], &[pair]), root);
+        assert_ne!(merkle_root(leaves[2], &[leaves[0]]), root);
+        assert_ne!(merkle_root(payout_leaf(&user, 3, 4), &[pair]), root);
+    }
 
-    pub system_program: Program<'info, System>,
-}
+    #[test]
+    fn error_code_of_program_and_anchor_errors() {
+        assert_eq!(
+            error_code(&Errors::NotEnoughBalance.into()),
+            u32::from(Errors::NotEnoughBalance)
+        );
+        assert_eq!(
+            error_code(&ErrorCode::AccountNotInitialized.into()),
+            u32::from(ErrorCode::AccountNotInitialized)
+        );
+        assert_eq!(error_code(&ProgramError::Custom(7).into()), 7);
+    }
 
-/// Accounts for SendWithdrawToken instruction.
-#[derive(Accounts)]
-pub struct SendWithdrawToken<'info> {
-    #[account(
-        mut,
-        seeds = [MASTER_SEED.as_bytes()],
-        bump,
-    )]
-    pub master: Account<'info, Master>,
+    #[test]
+    fn express_fee_is_capped() {
+        assert_eq!(express_fee(1_000_000, false, 50, 100).unwrap(), 0);
+        assert_eq!(express_fee(1_000_000, true, 50, 100).unwrap(), 5_000);
+        assert_eq!(express_fee(1_000_000, true, 500, 100).unwrap(), 10_000);
+        assert_eq!(
+            express_fee(u64::MAX, true, MAX_BPS, MAX_BPS).unwrap(),
+            u64::MAX
+        );
+        assert_eq!(express_fee(199, true, 50, 100).unwrap(), 0);
+    }
 
-    #[account(
-        mut,
-        address = master.token_account.expect("token account has not been initialized"),
-        associated_token::mint = token_mint,
-        associated_token::authority = master,
-        associated_token::token_program = token_program,
-    )]
-    pub master_ata: Account<'info, TokenAccount>,
+    #[test]
+    fn payout_cap_covers_batch_total() {
+        assert!(require_within_payout_cap(0, u64::MAX).is_ok());
+        assert!(require_within_payout_cap(100, 100).is_ok());
+        assert!(require_within_payout_cap(100, 101).is_err());
 
-    #[account(mut, address = master.operator)]
-    pub operator: Signer<'info>,
+        let total = batch_total(&[60, 40]).unwrap();
+        assert_eq!(total, 100);
+        assert!(require_within_payout_cap(99, total).is_err());
+        assert!(batch_total(&[u64::MAX, 1]).is_err());
+    }
 
-    #[account(
-        mut,
-        associated_token::mint = token_mint,
-        associated_token::authority = receiver,
-        associated_token::token_program = token_program,
-    )]
-    pub receiver_ata: Account<'info, TokenAccount>,
+    #[test]
+    fn user_deposit_cap_of_user_overrides_default() {
+        assert!(record_user_deposit(None, 0, 100).is_ok());
+        assert_eq!(
+            record_user_deposit(None, 1_000, 100).unwrap_err(),
+            Errors::UserDepositsMissing.into()
+        );
 
-    pub receiver: SystemAccount<'info>,
+        let mut user_deposits = UserDeposits {
+            master: Pubkey::default(),
+            user: Pubkey::default(),
+            asset: Pubkey::default(),
+            deposited: 900,
+            cap: 0,
+        };
+        assert_eq!(
+            record_user_deposit(Some(&mut user_deposits), 1_000, 101).unwrap_err(),
+            Errors::UserDepositCapExceeded.into()
+        );
+        assert!(record_user_deposit(Some(&mut user_deposits), 1_000, 100).is_ok());
 
-    pub token_mint: Account<'info, Mint>,
+        user_deposits.cap = 5_000;
+        assert!(record_user_deposit(Some(&mut user_deposits), 1_000, 4_000).is_ok());
+        assert_eq!(user_deposits.deposited, 5_000);
+    }
 
-    pub token_program: Program<'info, Token>,
+    #[test]
+    fn injected_clock_is_returned() {
+        test_sysvars::set_unix_timestamp(1_700_000_000);
+        assert_eq!(get_clock().unwrap().unix_timestamp, 1_700_000_000);
+        test_sysvars::reset();
+    }
 
-    pub system_program: Program<'info, System>,
+    #[test]
+    fn rent_exempt_at_exact_boundary() {
+        test_sysvars::set_rent(Rent::default());
+        let minimum = Rent::default().minimum_balance(MASTER_SIZE);
+
+        with_account(minimum, MASTER_SIZE, |account| {
+            assert!(require_rent_exempt(account, minimum).is_ok());
+            assert_eq!(
+                require_rent_exempt(account, minimum - 1).unwrap_err(),
+                Errors::NotRentExempt.into()
+            );
+        });
+        test_sysvars::reset();
+    }
 }
@@ -0,0 +1,133 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+
+import { ProBalance } from "../target/types/pro_balance";
+import { GameIntegration } from "../target/types/game_integration";
+import { expect } from "chai";
+
+describe("game-integration", () => {
+  // Configure the client to use the local cluster.
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.ProBalance as Program<ProBalance>;
+  const game = anchor.workspace.GameIntegration as Program<GameIntegration>;
+
+  const provider = anchor.AnchorProvider.env();
+  const player = anchor.web3.Keypair.generate();
+  const LAMPORTS_PER_SOL = 1000000000;
+  const vaultId = new anchor.BN(0);
+
+  const masterAddress = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("master"), vaultId.toArrayLike(Buffer, "le", 8)],
+    program.programId
+  )[0];
+
+  const vaultConfigAddress = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vault_config"), masterAddress.toBuffer()],
+    program.programId
+  )[0];
+
+  const programConfigAddress = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("program_config")],
+    program.programId
+  )[0];
+
+  const blockedAddress = (address: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("blocked"), masterAddress.toBuffer(), address.toBuffer()],
+      program.programId
+    )[0];
+
+  const depositWhitelistAddress = (user: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("deposit_whitelist"),
+        masterAddress.toBuffer(),
+        user.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+  const userStateAddress = (user: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("user"), masterAddress.toBuffer(), user.toBuffer()],
+      program.programId
+    )[0];
+
+  const exchangeWalletAddress = (address: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("exchange_wallet"),
+        masterAddress.toBuffer(),
+        address.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+
+  it("can deposit for a player through CPI", async () => {
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(
+        player.publicKey,
+        2 * LAMPORTS_PER_SOL
+      )
+    );
+
+    const before = await program.account.master.fetch(masterAddress);
+
+    await game.methods
+      .depositForPlayer(new anchor.BN(1000000))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress,
+        programConfig: programConfigAddress,
+        player: player.publicKey,
+        playerBlocked: blockedAddress(player.publicKey),
+        playerWhitelist: depositWhitelistAddress(player.publicKey),
+        playerState: userStateAddress(player.publicKey),
+        playerExchange: exchangeWalletAddress(player.publicKey),
+        proBalanceProgram: program.programId,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([player])
+      .rpc();
+
+    const after = await program.account.master.fetch(masterAddress);
+    expect(after.balance.sub(before.balance).toString()).to.be.eq("1000000");
+  });
+
+  it("can deposit from the game treasury on behalf of a player", async () => {
+    const gameAuthority = provider.wallet.publicKey;
+    const treasury = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("treasury"), gameAuthority.toBuffer()],
+      game.programId
+    )[0];
+
+    await provider.connection.confirmTransaction(
+      await provider.connection.requestAirdrop(treasury, LAMPORTS_PER_SOL)
+    );
+
+    const before = await program.account.master.fetch(masterAddress);
+
+    await game.methods
+      .depositFromTreasury(new anchor.BN(1000000))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress,
+        programConfig: programConfigAddress,
+        treasury,
+        treasuryBlocked: blockedAddress(treasury),
+        treasuryWhitelist: depositWhitelistAddress(treasury),
+        treasuryState: userStateAddress(treasury),
+        treasuryExchange: exchangeWalletAddress(treasury),
+        player: player.publicKey,
+        gameAuthority,
+        proBalanceProgram: program.programId,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const after = await program.account.master.fetch(masterAddress);
+    expect(after.balance.sub(before.balance).toString()).to.be.eq("1000000");
+  });
+});
@@ -15,6 +15,7 @@ import {
 
 import { ProBalance } from "../target/types/pro_balance";
 import { assert, expect } from "chai";
+import { createHash } from "crypto";
 
 describe("pro-balance", () => {
   // Configure the client to use the local cluster.
@@ -28,6 +29,7 @@ describe("pro-balance", () => {
   const user1 = anchor.web3.Keypair.generate();
 
   let masterAddress;
+  const vaultId = new anchor.BN(0);
 
   const depositAmount = new anchor.BN(1000000000);
 
@@ -41,20 +43,168 @@ describe("pro-balance", () => {
   let mintSC;
   let person1ATA;
 
+  const programConfigAddress = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("program_config")],
+    program.programId
+  )[0];
+
+  const vaultConfigAddress = (master: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault_config"), master.toBuffer()],
+      program.programId
+    )[0];
+
+  const vaultRegistryAddress = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("vault_registry")],
+    program.programId
+  )[0];
+
+  const receiverAllowlistAddress = (receiver: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receiver_allowlist"),
+        masterAddress.toBuffer(),
+        receiver.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+  const blockedAddress = (address: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("blocked"), masterAddress.toBuffer(), address.toBuffer()],
+      program.programId
+    )[0];
+
+  const depositWhitelistAddress = (user: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("deposit_whitelist"),
+        masterAddress.toBuffer(),
+        user.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+  const userStateAddress = (user: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("user"), masterAddress.toBuffer(), user.toBuffer()],
+      program.programId
+    )[0];
+
+  const exchangeWalletAddress = (address: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("exchange_wallet"),
+        masterAddress.toBuffer(),
+        address.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+  const canaryAddress = (receiver: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("canary"), masterAddress.toBuffer(), receiver.toBuffer()],
+      program.programId
+    )[0];
+
+  const penaltyLedgerAddress = (operator: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("penalty"), masterAddress.toBuffer(), operator.toBuffer()],
+      program.programId
+    )[0];
+
+  const withdrawQueueAddress = () =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("withdraw_queue"), masterAddress.toBuffer()],
+      program.programId
+    )[0];
+
+  const withdrawRequestAddress = (sequence: anchor.BN) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("withdraw_request"),
+        masterAddress.toBuffer(),
+        sequence.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+  // Queues a withdraw request of the provider wallet, returns its address.
+  const requestWithdraw = async (amount: anchor.BN) => {
+    const queue = await program.account.withdrawQueue.fetchNullable(
+      withdrawQueueAddress()
+    );
+    const withdrawRequest = withdrawRequestAddress(
+      queue ? queue.tail : new anchor.BN(0)
+    );
+
+    await program.methods
+      .requestWithdraw(amount, null)
+      .accounts({
+        master: masterAddress,
+        withdrawQueue: withdrawQueueAddress(),
+        withdrawRequest,
+        user: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    return withdrawRequest;
+  };
+
+  // Label of a vault, both fields zero padded to their fixed length.
+  const vaultMetadata = (name: string, productCode: string) => {
+    const padded = (text: string, length: number) => {
+      const bytes = Buffer.alloc(length);
+      bytes.write(text);
+      return Array.from(bytes);
+    };
+    return { name: padded(name, 32), productCode: padded(productCode, 8) };
+  };
+
+  const receiverLockAddress = (receiver: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receiver_lock"),
+        masterAddress.toBuffer(),
+        receiver.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
   before(async () => {
     masterAddress = (
       await anchor.web3.PublicKey.findProgramAddress(
-        [Buffer.from("master")],
+        [Buffer.from("master"), vaultId.toArrayLike(Buffer, "le", 8)],
         new anchor.web3.PublicKey(
           "8ZwcssGn5vKE1d6oBNNTTjDsFyTDKSuPtoooZQe9MHXb"
         )
       )
     )[0];
 
+    const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+
+    await program.methods
+      .initProgramConfig(1000)
+      .accounts({
+        programConfig: programConfigAddress,
+        authority: anchor.getProvider().publicKey,
+        program: program.programId,
+        programData,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
     const tx1 = await program.methods
-      .initMaster()
+      .initMaster(vaultId, vaultMetadata("Main", "SPORTS"))
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        vaultRegistry: vaultRegistryAddress,
         payer: anchor.getProvider().publicKey,
         admin: admin,
         operator: operator,
@@ -70,28 +220,134 @@ describe("pro-balance", () => {
     expect(masterAcc.lastWithdrawTime.toString()).to.be.eq("0");
     expect(masterAcc.operator.toString()).to.be.eq(operator.toString());
     expect(masterAcc.admin.toString()).to.be.eq(admin.toString());
+    expect(masterAcc.metadata.productCode).to.deep.eq(
+      vaultMetadata("Main", "SPORTS").productCode
+    );
+  });
+
+  it("migrates the master account to the current layout", async () => {
+    await program.methods
+      .migrateMaster(vaultId)
+      .accounts({
+        master: masterAddress,
+        admin: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    const masterVersion = program.idl.constants.find(
+      (constant) => constant.name === "MASTER_VERSION"
+    );
+    expect(masterAcc.version.toString()).to.be.eq(masterVersion.value);
+  });
+
+  it("updates the label of the vault", async () => {
+    const metadata = vaultMetadata("Main EU", "SPORTSEU");
+
+    await program.methods
+      .setVaultMetadata(metadata)
+      .accounts({
+        master: masterAddress,
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(masterAcc.metadata.name).to.deep.eq(metadata.name);
+    expect(masterAcc.metadata.productCode).to.deep.eq(metadata.productCode);
+  });
+
+  it("can host an independent vault under another vault id", async () => {
+    const otherVaultId = new anchor.BN(1);
+    const otherMasterAddress = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("master"), otherVaultId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+
+    await program.methods
+      .initMaster(otherVaultId, vaultMetadata("Other", "CASINO"))
+      .accounts({
+        master: otherMasterAddress,
+        vaultConfig: vaultConfigAddress(otherMasterAddress),
+        programConfig: programConfigAddress,
+        vaultRegistry: vaultRegistryAddress,
+        payer: anchor.getProvider().publicKey,
+        admin: person1.publicKey,
+        operator: person1.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const otherMasterAcc = await program.account.master.fetch(
+      otherMasterAddress
+    );
+    expect(otherMasterAcc.vaultId.toNumber()).to.be.eq(1);
+    expect(otherMasterAcc.admin.toString()).to.be.eq(
+      person1.publicKey.toString()
+    );
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(masterAcc.vaultId.toNumber()).to.be.eq(0);
+    expect(masterAcc.admin.toString()).to.be.eq(admin.toString());
+
+    const registry = await program.account.vaultRegistry.fetch(
+      vaultRegistryAddress
+    );
+    expect(registry.vaults.map((vault) => vault.master.toString())).to.eql([
+      masterAddress.toString(),
+      otherMasterAddress.toString(),
+    ]);
   });
 
   it("can deposit", async () => {
     await program.methods
-      .deposit(depositAmount)
+      .deposit(depositAmount, {
+        value: new anchor.BN(15000),
+        currency: Array.from(Buffer.from("USD")),
+      })
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
         user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
 
     const masterAcc = await program.account.master.fetch(masterAddress);
     expect(masterAcc.balance.toString()).to.be.eq(depositAmount.toString());
+    expect(masterAcc.lifetime.totalDeposited.toString()).to.be.eq(
+      depositAmount.toString()
+    );
+    expect(masterAcc.lifetime.depositCount.toNumber()).to.be.eq(1);
+
+    await program.methods
+      .emitStats()
+      .accounts({ master: masterAddress, vaultToken: null })
+      .rpc();
   });
 
   it("can withdraw", async () => {
     await program.methods
-      .deposit(depositAmount)
+      .deposit(depositAmount, null)
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
         user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
@@ -100,6 +356,7 @@ describe("pro-balance", () => {
       .withdraw(new anchor.BN(100))
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
         admin: anchor.getProvider().publicKey,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
@@ -109,20 +366,43 @@ describe("pro-balance", () => {
 
   it("can sendWithdraw", async () => {
     await program.methods
-      .deposit(depositAmount)
+      .deposit(depositAmount, null)
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
         user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
 
     const tx_send = await program.methods
-      .sendWithdraw(new anchor.BN(1000000))
+      .sendWithdraw(new anchor.BN(1000000), null, false, null, null)
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
         operator: anchor.getProvider().publicKey,
         receiver: user1.publicKey,
+        receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+        receiverBlocked: blockedAddress(user1.publicKey),
+        receiverState: userStateAddress(user1.publicKey),
+        receiverCanary: canaryAddress(user1.publicKey),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
@@ -131,20 +411,43 @@ describe("pro-balance", () => {
 
   it("can sendWithdraw", async () => {
     await program.methods
-      .deposit(depositAmount)
+      .deposit(depositAmount, null)
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
         user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
 
     const tx_send = await program.methods
-      .sendWithdraw(new anchor.BN(1000000))
+      .sendWithdraw(new anchor.BN(1000000), null, false, null, null)
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
         operator: anchor.getProvider().publicKey,
         receiver: user1.publicKey,
+        receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+        receiverBlocked: blockedAddress(user1.publicKey),
+        receiverState: userStateAddress(user1.publicKey),
+        receiverCanary: canaryAddress(user1.publicKey),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
@@ -154,11 +457,26 @@ describe("pro-balance", () => {
   it("cant sendWithdraw with unauthorized user", async () => {
     try {
       await program.methods
-        .sendWithdraw(new anchor.BN(1000000))
+        .sendWithdraw(new anchor.BN(1000000), null, false, null, null)
         .accounts({
           master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
           operator: user1.publicKey,
           receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest: null,
+          withdrawQueue: null,
+          receipt: null,
+          memoProgram: null,
           systemProgram: anchor.web3.SystemProgram.programId,
         })
         .signers([user1])
@@ -174,6 +492,2951 @@ describe("pro-balance", () => {
     }
   });
 
+  it("can withdraw down to the exact rent-exempt minimum", async () => {
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    const masterInfo = await provider.connection.getAccountInfo(masterAddress);
+    const rentExempt =
+      await provider.connection.getMinimumBalanceForRentExemption(
+        masterInfo.data.length
+      );
+
+    try {
+      await program.methods
+        .withdraw(masterAcc.balance.add(new anchor.BN(1)))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: anchor.getProvider().publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "NotEnoughBalance");
+    }
+
+    await program.methods
+      .withdraw(masterAcc.balance)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const lamports = await provider.connection.getBalance(masterAddress);
+    expect(lamports).to.be.eq(rentExempt);
+  });
+
+  it("guardian can revoke the operator and stop payouts", async () => {
+    const guardian = anchor.web3.Keypair.generate();
+
+    await program.methods
+      .setGuardian()
+      .accounts({
+        master: masterAddress,
+        admin: anchor.getProvider().publicKey,
+        newGuardian: guardian.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .revokeAllOperators()
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        authority: guardian.publicKey,
+      })
+      .signers([guardian])
+      .rpc();
+
+    let masterAcc = await program.account.master.fetch(masterAddress);
+    expect(masterAcc.operator.toString()).to.be.eq(
+      anchor.web3.PublicKey.default.toString()
+    );
+    let vaultConfigAcc = await program.account.vaultConfig.fetch(
+      vaultConfigAddress(masterAddress)
+    );
+    expect(vaultConfigAcc.withdrawalsPaused).to.be.eq(true);
+
+    await program.methods
+      .setOperator()
+      .accounts({
+        master: masterAddress,
+        admin: anchor.getProvider().publicKey,
+        newOperator: operator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .setWithdrawalsPaused(false)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        authority: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    masterAcc = await program.account.master.fetch(masterAddress);
+    expect(masterAcc.operator.toString()).to.be.eq(operator.toString());
+    vaultConfigAcc = await program.account.vaultConfig.fetch(
+      vaultConfigAddress(masterAddress)
+    );
+    expect(vaultConfigAcc.withdrawalsPaused).to.be.eq(false);
+  });
+
+  it("can restrict payouts to allowlisted receivers", async () => {
+    await program.methods
+      .setReceiverAllowlistEnabled(true)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .sendWithdraw(new anchor.BN(1000000), null, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          receiver: person1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(person1.publicKey),
+          receiverBlocked: blockedAddress(person1.publicKey),
+          receiverState: userStateAddress(person1.publicKey),
+          receiverCanary: canaryAddress(person1.publicKey),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest: null,
+          withdrawQueue: null,
+          receipt: null,
+          memoProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "ReceiverNotAllowlisted");
+    }
+
+    await program.methods
+      .addAllowedReceiver()
+      .accounts({
+        master: masterAddress,
+        allowedReceiver: receiverAllowlistAddress(person1.publicKey),
+        admin: anchor.getProvider().publicKey,
+        receiver: person1.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .deposit(depositAmount, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .sendWithdraw(new anchor.BN(1000000), null, false, null, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        operator: anchor.getProvider().publicKey,
+        receiver: person1.publicKey,
+        receiverAllowlist: receiverAllowlistAddress(person1.publicKey),
+        receiverBlocked: blockedAddress(person1.publicKey),
+        receiverState: userStateAddress(person1.publicKey),
+        receiverCanary: canaryAddress(person1.publicKey),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .setReceiverAllowlistEnabled(false)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+  });
+
+  it("cant deposit from a blocked address", async () => {
+    const blocked = anchor.getProvider().publicKey;
+
+    await program.methods
+      .blockAddress(blocked)
+      .accounts({
+        master: masterAddress,
+        blocked: blockedAddress(blocked),
+        admin: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .deposit(depositAmount, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user: blocked,
+          userBlocked: blockedAddress(blocked),
+          userWhitelist: depositWhitelistAddress(blocked),
+          userState: userStateAddress(blocked),
+          userExchange: exchangeWalletAddress(blocked),
+          productLedger: null,
+          userDeposits: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "AddressBlocked");
+    }
+
+    await program.methods
+      .unblockAddress(blocked)
+      .accounts({
+        master: masterAddress,
+        blocked: blockedAddress(blocked),
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+  });
+
+  it("only accepts whitelisted depositors in whitelist mode", async () => {
+    await program.methods
+      .setDepositWhitelistEnabled(true)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .deposit(depositAmount, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user: anchor.getProvider().publicKey,
+          userBlocked: blockedAddress(anchor.getProvider().publicKey),
+          userWhitelist: depositWhitelistAddress(
+            anchor.getProvider().publicKey
+          ),
+          userState: userStateAddress(anchor.getProvider().publicKey),
+          userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+          productLedger: null,
+          userDeposits: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "DepositorNotWhitelisted");
+    }
+
+    await program.methods
+      .addWhitelistedDepositor()
+      .accounts({
+        master: masterAddress,
+        whitelistedDepositor: depositWhitelistAddress(
+          anchor.getProvider().publicKey
+        ),
+        authority: anchor.getProvider().publicKey,
+        user: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .deposit(depositAmount, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .setDepositWhitelistEnabled(false)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+  });
+
+  it("cant deposit while deposits are paused", async () => {
+    await program.methods
+      .setDepositsPaused(true)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        authority: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .deposit(depositAmount, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user: anchor.getProvider().publicKey,
+          userBlocked: blockedAddress(anchor.getProvider().publicKey),
+          userWhitelist: depositWhitelistAddress(
+            anchor.getProvider().publicKey
+          ),
+          userState: userStateAddress(anchor.getProvider().publicKey),
+          userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+          productLedger: null,
+          userDeposits: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "DepositsPaused");
+    }
+
+    await program.methods
+      .setDepositsPaused(false)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        authority: anchor.getProvider().publicKey,
+      })
+      .rpc();
+  });
+
+  it("bumps the config generation on every setting change", async () => {
+    const generation = async () =>
+      (await program.account.master.fetch(masterAddress)).configGeneration;
+    const before = await generation();
+
+    for (const paused of [true, false]) {
+      await program.methods
+        .setWithdrawalsPaused(paused)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          authority: anchor.getProvider().publicKey,
+        })
+        .rpc();
+    }
+
+    expect((await generation()).sub(before).toNumber()).to.be.eq(2);
+  });
+
+  it("declares the accounting currency of the vault", async () => {
+    await program.methods
+      .setAccountingCurrency(Array.from(Buffer.from("EUR")), 2)
+      .accounts({
+        master: masterAddress,
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    const currency = Buffer.from(masterAcc.accountingCurrency).toString();
+    expect(currency).to.be.eq("EUR");
+    expect(masterAcc.accountingDecimals).to.be.eq(2);
+
+    try {
+      await program.methods
+        .setAccountingCurrency(Array.from(Buffer.from("eur")), 2)
+        .accounts({
+          master: masterAddress,
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "InvalidCurrencyCode");
+    }
+  });
+
+  it("can donate SOL without crediting a user", async () => {
+    const before = await program.account.master.fetch(masterAddress);
+
+    await program.methods
+      .donate(new anchor.BN(3000000))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        donor: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const after = await program.account.master.fetch(masterAddress);
+    expect(after.balance.sub(before.balance).toNumber()).to.be.eq(3000000);
+  });
+
+  it("sweeps SOL sent directly to the master PDA", async () => {
+    const before = await program.account.master.fetch(masterAddress);
+
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: anchor.getProvider().publicKey,
+          toPubkey: masterAddress,
+          lamports: 5000000,
+        })
+      )
+    );
+
+    await program.methods
+      .sweepUntrackedSol()
+      .accounts({
+        master: masterAddress,
+        authority: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    const after = await program.account.master.fetch(masterAddress);
+    expect(after.balance.sub(before.balance).toNumber()).to.be.eq(5000000);
+
+    try {
+      await program.methods
+        .sweepUntrackedSol()
+        .accounts({
+          master: masterAddress,
+          authority: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "NothingToSweep");
+    }
+  });
+
+  it("cant deposit while the program is paused", async () => {
+    const setProgramPaused = (paused: boolean) =>
+      program.methods
+        .setProgramPaused(paused)
+        .accounts({
+          programConfig: programConfigAddress,
+          superAdmin: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+    await setProgramPaused(true);
+
+    try {
+      await program.methods
+        .deposit(depositAmount, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user: anchor.getProvider().publicKey,
+          userBlocked: blockedAddress(anchor.getProvider().publicKey),
+          userWhitelist: depositWhitelistAddress(
+            anchor.getProvider().publicKey
+          ),
+          userState: userStateAddress(anchor.getProvider().publicKey),
+          userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+          productLedger: null,
+          userDeposits: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "ProgramPaused");
+    }
+
+    await setProgramPaused(false);
+  });
+
+  it("can record operator faults in the penalty ledger", async () => {
+    for (const amount of [100, 250]) {
+      await program.methods
+        .recordOperatorFault(new anchor.BN(amount), "paid wrong receiver")
+        .accounts({
+          master: masterAddress,
+          penaltyLedger: penaltyLedgerAddress(operator),
+          admin: anchor.getProvider().publicKey,
+          operator: operator,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    const ledger = await program.account.penaltyLedger.fetch(
+      penaltyLedgerAddress(operator)
+    );
+    expect(ledger.outstanding.toNumber()).to.be.eq(350);
+    expect(ledger.faultCount).to.be.eq(2);
+  });
+
+  it("can transfer SOL between vaults of the same admin", async () => {
+    const masterOf = (id: anchor.BN) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("master"), id.toArrayLike(Buffer, "le", 8)],
+        program.programId
+      )[0];
+    const otherVaultId = new anchor.BN(2);
+
+    await program.methods
+      .initMaster(otherVaultId, vaultMetadata("Other", "CASINO"))
+      .accounts({
+        master: masterOf(otherVaultId),
+        vaultConfig: vaultConfigAddress(masterOf(otherVaultId)),
+        programConfig: programConfigAddress,
+        vaultRegistry: vaultRegistryAddress,
+        payer: anchor.getProvider().publicKey,
+        admin: admin,
+        operator: operator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .deposit(depositAmount, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const before = await program.account.master.fetch(masterAddress);
+
+    await program.methods
+      .transferBetweenVaults(depositAmount)
+      .accounts({
+        fromMaster: masterAddress,
+        fromVaultConfig: vaultConfigAddress(masterAddress),
+        toMaster: masterOf(otherVaultId),
+        toVaultConfig: vaultConfigAddress(masterOf(otherVaultId)),
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    const after = await program.account.master.fetch(masterAddress);
+    const other = await program.account.master.fetch(masterOf(otherVaultId));
+    expect(before.balance.sub(after.balance).toString()).to.be.eq(
+      depositAmount.toString()
+    );
+    expect(other.balance.toString()).to.be.eq(depositAmount.toString());
+
+    // the vault cap of the destination applies
+    const setOtherVaultCap = (cap: number) =>
+      program.methods
+        .setVaultCap(new anchor.BN(cap))
+        .accounts({
+          master: masterOf(otherVaultId),
+          vaultConfig: vaultConfigAddress(masterOf(otherVaultId)),
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc();
+    await setOtherVaultCap(1);
+    try {
+      await program.methods
+        .transferBetweenVaults(new anchor.BN(1))
+        .accounts({
+          fromMaster: masterAddress,
+          fromVaultConfig: vaultConfigAddress(masterAddress),
+          toMaster: masterOf(otherVaultId),
+          toVaultConfig: vaultConfigAddress(masterOf(otherVaultId)),
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "VaultCapReached");
+    }
+    await setOtherVaultCap(0);
+
+    // vault 1 has another admin
+    try {
+      await program.methods
+        .transferBetweenVaults(depositAmount)
+        .accounts({
+          fromMaster: masterOf(otherVaultId),
+          fromVaultConfig: vaultConfigAddress(masterOf(otherVaultId)),
+          toMaster: masterOf(new anchor.BN(1)),
+          toVaultConfig: vaultConfigAddress(masterOf(new anchor.BN(1))),
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "Unauthorized");
+    }
+  });
+
+  it("books tagged deposits and payouts to a product", async () => {
+    const productId = new anchor.BN(7);
+    const productLedger = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("product"),
+        masterAddress.toBuffer(),
+        productId.toArrayLike(Buffer, "le", 8),
+        anchor.web3.PublicKey.default.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+    await program.methods
+      .initProductLedger(productId, anchor.web3.PublicKey.default)
+      .accounts({
+        master: masterAddress,
+        productLedger,
+        admin: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .deposit(depositAmount, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger,
+        userDeposits: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const sendWithdraw = (amount: anchor.BN) =>
+      program.methods
+        .sendWithdraw(amount, null, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          productLedger,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest: null,
+          withdrawQueue: null,
+          receipt: null,
+          memoProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    try {
+      await sendWithdraw(depositAmount.addn(1));
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "NotEnoughBalance");
+    }
+
+    await sendWithdraw(new anchor.BN(400000000));
+
+    const ledger = await program.account.productLedger.fetch(productLedger);
+    expect(ledger.balance.toString()).to.be.eq("600000000");
+    expect(ledger.totalDeposited.toString()).to.be.eq(depositAmount.toString());
+    expect(ledger.totalPaidOut.toString()).to.be.eq("400000000");
+  });
+
+  it("tags and untags an exchange hot wallet", async () => {
+    const exchange = anchor.web3.Keypair.generate().publicKey;
+    const exchangeWallet = exchangeWalletAddress(exchange);
+
+    await program.methods
+      .addExchangeWallet(exchange)
+      .accounts({
+        master: masterAddress,
+        exchangeWallet,
+        admin: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const tag = await program.account.exchangeWallet.fetch(exchangeWallet);
+    expect(tag.address.toBase58()).to.be.eq(exchange.toBase58());
+
+    await program.methods
+      .removeExchangeWallet(exchange)
+      .accounts({
+        master: masterAddress,
+        exchangeWallet,
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(exchangeWallet));
+  });
+
+  it("manages the address lookup table of the vault", async () => {
+    const lookupTableAuthority = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("lookup_table"), masterAddress.toBuffer()],
+      program.programId
+    )[0];
+    const recentSlot = await provider.connection.getSlot("finalized");
+    const [, lookupTable] =
+      anchor.web3.AddressLookupTableProgram.createLookupTable({
+        authority: lookupTableAuthority,
+        payer: anchor.getProvider().publicKey,
+        recentSlot,
+      });
+    const accounts = {
+      master: masterAddress,
+      lookupTableAuthority,
+      lookupTable,
+      admin: anchor.getProvider().publicKey,
+      addressLookupTableProgram:
+        anchor.web3.AddressLookupTableProgram.programId,
+      systemProgram: anchor.web3.SystemProgram.programId,
+    };
+
+    await program.methods
+      .createLookupTable(new anchor.BN(recentSlot))
+      .accounts(accounts)
+      .rpc();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(masterAcc.lookupTable.toBase58()).to.be.eq(lookupTable.toBase58());
+
+    await program.methods
+      .extendLookupTable([masterAddress, programConfigAddress])
+      .accounts(accounts)
+      .rpc();
+
+    const table = (await provider.connection.getAddressLookupTable(lookupTable))
+      .value;
+    expect(table.state.addresses.map((a) => a.toBase58())).to.deep.eq([
+      masterAddress.toBase58(),
+      programConfigAddress.toBase58(),
+    ]);
+
+    await program.methods.deactivateLookupTable().accounts(accounts).rpc();
+  });
+
+  it("locks a receiver until its payout completes", async () => {
+    const receiverLock = receiverLockAddress(user1.publicKey);
+    const lockReceiver = () =>
+      program.methods
+        .lockReceiver(user1.publicKey)
+        .accounts({
+          master: masterAddress,
+          receiverLock,
+          operator: anchor.getProvider().publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    await lockReceiver();
+
+    const lock = await program.account.receiverLock.fetch(receiverLock);
+    expect(lock.receiver.toBase58()).to.be.eq(user1.publicKey.toBase58());
+
+    // A second operator instance cannot take the lock while it is held.
+    try {
+      await lockReceiver();
+
+      assert.ok(false);
+    } catch (err) {
+      assert.include(err.toString(), "already in use");
+    }
+
+    await program.methods
+      .sendWithdraw(new anchor.BN(1000000), null, false, null, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        operator: anchor.getProvider().publicKey,
+        receiver: user1.publicKey,
+        receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+        receiverBlocked: blockedAddress(user1.publicKey),
+        receiverState: userStateAddress(user1.publicKey),
+        receiverCanary: canaryAddress(user1.publicKey),
+        productLedger: null,
+        receiverLock,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // The payout released the lock.
+    const info = await provider.connection.getAccountInfo(receiverLock);
+    assert.isNull(info);
+
+    await lockReceiver();
+    await program.methods
+      .unlockReceiver(user1.publicKey)
+      .accounts({
+        master: masterAddress,
+        receiverLock,
+        authority: anchor.getProvider().publicKey,
+        operator: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(receiverLock));
+  });
+
+  it("fulfills an on-chain withdraw request", async () => {
+    const user = anchor.getProvider().publicKey;
+    const amount = new anchor.BN(2000000);
+    const withdrawRequest = await requestWithdraw(amount);
+
+    const request = await program.account.withdrawRequest.fetch(
+      withdrawRequest
+    );
+    expect(request.amount.toString()).to.be.eq(amount.toString());
+    assert.isNull(request.mint);
+
+    const sendWithdraw = (amount: anchor.BN) =>
+      program.methods
+        .sendWithdraw(amount, null, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          receiver: user,
+          receiverAllowlist: receiverAllowlistAddress(user),
+          receiverBlocked: blockedAddress(user),
+          receiverState: userStateAddress(user),
+          receiverCanary: canaryAddress(user),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest,
+          withdrawQueue: withdrawQueueAddress(),
+          receipt: null,
+          memoProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    try {
+      await sendWithdraw(amount.addn(1));
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "WithdrawRequestMismatch");
+    }
+
+    const setDisputed = (disputed: boolean) =>
+      program.methods
+        .setRequestDisputed(disputed)
+        .accounts({
+          master: masterAddress,
+          withdrawRequest,
+          authority: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+    await setDisputed(true);
+    try {
+      await sendWithdraw(amount);
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "WithdrawRequestDisputed");
+    }
+    await setDisputed(false);
+
+    await sendWithdraw(amount);
+
+    // The payout closed the request and moved the queue on.
+    assert.isNull(await provider.connection.getAccountInfo(withdrawRequest));
+    const queue = await program.account.withdrawQueue.fetch(
+      withdrawQueueAddress()
+    );
+    expect(queue.head.toString()).to.be.eq(queue.tail.toString());
+  });
+
+  it("pays a backend request id only once", async () => {
+    const requestId = new anchor.BN(4242);
+    const receipt = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receipt"),
+        masterAddress.toBuffer(),
+        requestId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+    const sendWithdraw = () =>
+      program.methods
+        .sendWithdraw(new anchor.BN(1000000), requestId, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest: null,
+          withdrawQueue: null,
+          receipt,
+          memoProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    await sendWithdraw();
+
+    const receiptAcc = await program.account.receipt.fetch(receipt);
+    expect(receiptAcc.requestId.toString()).to.be.eq(requestId.toString());
+    expect(receiptAcc.receiver.toString()).to.be.eq(user1.publicKey.toString());
+    expect(receiptAcc.amount.toNumber()).to.be.eq(1000000);
+    assert.isNull(receiptAcc.mint);
+
+    // A retry of the same request fails instead of paying twice.
+    try {
+      await sendWithdraw();
+
+      assert.ok(false);
+    } catch (err) {
+      assert.include(err.toString(), "already in use");
+    }
+
+    try {
+      await program.methods
+        .closeReceipt()
+        .accounts({
+          master: masterAddress,
+          receipt,
+          operator: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "ReceiptRetained");
+    }
+  });
+
+  it("cancels a pending withdraw request", async () => {
+    const user = anchor.getProvider().publicKey;
+    const withdrawRequest = await requestWithdraw(new anchor.BN(3000000));
+
+    try {
+      await program.methods
+        .cancelWithdrawRequest()
+        .accounts({
+          master: masterAddress,
+          withdrawRequest,
+          user: user1.publicKey,
+        })
+        .signers([user1])
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "WithdrawRequestMismatch");
+    }
+
+    try {
+      await program.methods
+        .expireRequest()
+        .accounts({ master: masterAddress, withdrawRequest, user })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(
+        err.error.errorCode.code,
+        "WithdrawRequestNotExpired"
+      );
+    }
+
+    await program.methods
+      .cancelWithdrawRequest()
+      .accounts({ master: masterAddress, withdrawRequest, user })
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(withdrawRequest));
+
+    // The cancelled request no longer holds up the queue.
+    await program.methods
+      .skipClosedRequest()
+      .accounts({
+        master: masterAddress,
+        withdrawQueue: withdrawQueueAddress(),
+        headRequest: withdrawRequest,
+      })
+      .rpc();
+
+    const queue = await program.account.withdrawQueue.fetch(
+      withdrawQueueAddress()
+    );
+    expect(queue.head.toString()).to.be.eq(queue.tail.toString());
+  });
+
+  it("pays a prioritized request ahead of the queue", async () => {
+    const user = anchor.getProvider().publicKey;
+    const amount = new anchor.BN(1000000);
+    const headRequest = await requestWithdraw(amount);
+    const vipRequest = await requestWithdraw(amount);
+
+    const sendWithdraw = () =>
+      program.methods
+        .sendWithdraw(amount, null, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: user,
+          receiver: user,
+          receiverAllowlist: receiverAllowlistAddress(user),
+          receiverBlocked: blockedAddress(user),
+          receiverState: userStateAddress(user),
+          receiverCanary: canaryAddress(user),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest: vipRequest,
+          withdrawQueue: withdrawQueueAddress(),
+          receipt: null,
+          memoProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    try {
+      await sendWithdraw();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "NotQueueHead");
+    }
+
+    await program.methods
+      .setRequestPriority(1)
+      .accounts({
+        master: masterAddress,
+        withdrawRequest: vipRequest,
+        operator: user,
+      })
+      .rpc();
+    await sendWithdraw();
+    assert.isNull(await provider.connection.getAccountInfo(vipRequest));
+
+    // The head stays at the older request until it is closed and skipped.
+    await program.methods
+      .cancelWithdrawRequest()
+      .accounts({ master: masterAddress, withdrawRequest: headRequest, user })
+      .rpc();
+    for (const headRequestAddress of [headRequest, vipRequest]) {
+      await program.methods
+        .skipClosedRequest()
+        .accounts({
+          master: masterAddress,
+          withdrawQueue: withdrawQueueAddress(),
+          headRequest: headRequestAddress,
+        })
+        .rpc();
+    }
+
+    const queue = await program.account.withdrawQueue.fetch(
+      withdrawQueueAddress()
+    );
+    expect(queue.head.toString()).to.be.eq(queue.tail.toString());
+  });
+
+  it("redeems an operator signed voucher", async () => {
+    const voucher = {
+      master: masterAddress,
+      receiver: user1.publicKey,
+      amount: new anchor.BN(1000000),
+      nonce: new anchor.BN(4343),
+      expiry: new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+    };
+    const receipt = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receipt"),
+        masterAddress.toBuffer(),
+        voucher.nonce.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+    // The provider wallet is the operator of the vault.
+    const operator = (provider.wallet as anchor.Wallet).payer;
+    const redeem = (signer: anchor.web3.Keypair) =>
+      program.methods
+        .redeemVoucher(voucher)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          receiverCooldown: null,
+          adminCosigner: null,
+          receipt,
+          instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([
+          anchor.web3.Ed25519Program.createInstructionWithPrivateKey({
+            privateKey: signer.secretKey,
+            message: program.coder.types.encode("Voucher", voucher),
+          }),
+        ])
+        .signers([user1])
+        .rpc();
+
+    try {
+      await redeem(anchor.web3.Keypair.generate());
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "InvalidVoucherSignature");
+    }
+
+    const balanceBefore = (await program.account.master.fetch(masterAddress))
+      .balance;
+    await redeem(operator);
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(balanceBefore.sub(masterAcc.balance).toNumber()).to.be.eq(1000000);
+    const receiptAcc = await program.account.receipt.fetch(receipt);
+    expect(receiptAcc.requestId.toString()).to.be.eq(voucher.nonce.toString());
+    expect(receiptAcc.receiver.toString()).to.be.eq(user1.publicKey.toString());
+
+    // The voucher is paid only once.
+    try {
+      await redeem(operator);
+
+      assert.ok(false);
+    } catch (err) {
+      assert.include(err.toString(), "already in use");
+    }
+  });
+
+  it("redeems a voucher signed by the eth signer", async () => {
+    const voucher = {
+      master: masterAddress,
+      receiver: user1.publicKey,
+      amount: new anchor.BN(1000000),
+      nonce: new anchor.BN(4444),
+      expiry: new anchor.BN(Math.floor(Date.now() / 1000) + 3600),
+    };
+    const message = program.coder.types.encode("Voucher", voucher);
+
+    // The instruction carries the eth address derived from the key at 12.
+    const privateKey = anchor.web3.Keypair.generate().secretKey.slice(0, 32);
+    const secp256k1Ix = () =>
+      anchor.web3.Secp256k1Program.createInstructionWithPrivateKey({
+        privateKey,
+        message,
+      });
+    const ethSigner = Array.from(secp256k1Ix().data.subarray(12, 32));
+
+    const redeem = () =>
+      program.methods
+        .redeemVoucher(voucher)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          receiverCooldown: null,
+          adminCosigner: null,
+          receipt: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("receipt"),
+              masterAddress.toBuffer(),
+              voucher.nonce.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+          )[0],
+          instructions: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .preInstructions([secp256k1Ix()])
+        .signers([user1])
+        .rpc();
+
+    try {
+      await redeem();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "InvalidVoucherSignature");
+    }
+
+    await program.methods
+      .setVoucherEthSigner(ethSigner)
+      .accounts({
+        master: masterAddress,
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    const balanceBefore = (await program.account.master.fetch(masterAddress))
+      .balance;
+    await redeem();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(balanceBefore.sub(masterAcc.balance).toNumber()).to.be.eq(1000000);
+
+    await program.methods
+      .setVoucherEthSigner(new Array(20).fill(0))
+      .accounts({
+        master: masterAddress,
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+  });
+
+  it("lets users claim merkle payouts once", async () => {
+    const epoch = new anchor.BN(1);
+    const sha256 = (...parts: Buffer[]) =>
+      createHash("sha256").update(Buffer.concat(parts)).digest();
+    const leaf = (user: anchor.web3.PublicKey, amount: anchor.BN) =>
+      sha256(
+        Buffer.from([0]),
+        user.toBuffer(),
+        amount.toArrayLike(Buffer, "le", 8),
+        epoch.toArrayLike(Buffer, "le", 8)
+      );
+
+    const amount = new anchor.BN(1000000);
+    const userLeaf = leaf(user1.publicKey, amount);
+    const otherLeaf = leaf(anchor.getProvider().publicKey, amount);
+    const [left, right] = [userLeaf, otherLeaf].sort(Buffer.compare);
+    const root = sha256(Buffer.from([1]), left, right);
+
+    const payoutRoot = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("payout_root"),
+        masterAddress.toBuffer(),
+        epoch.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+
+    await program.methods
+      .postPayoutRoot(epoch, Array.from(root), amount.muln(2))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        payoutRoot,
+        operator: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const claim = (claimed: anchor.BN) =>
+      program.methods
+        .claim(claimed, [Array.from(otherLeaf)])
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          payoutRoot,
+          claim: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("claim"),
+              payoutRoot.toBuffer(),
+              user1.publicKey.toBuffer(),
+            ],
+            program.programId
+          )[0],
+          user: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          selfWithdrawal: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("self_withdrawal"),
+              masterAddress.toBuffer(),
+              user1.publicKey.toBuffer(),
+            ],
+            program.programId
+          )[0],
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+
+    try {
+      await claim(amount.muln(2));
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "InvalidMerkleProof");
+    }
+
+    await claim(amount);
+
+    const payoutRootAcc = await program.account.payoutRoot.fetch(payoutRoot);
+    expect(payoutRootAcc.claimed.toString()).to.be.eq(amount.toString());
+
+    try {
+      await claim(amount);
+
+      assert.ok(false);
+    } catch (err) {
+      assert.include(err.toString(), "already in use");
+    }
+  });
+
+  it("keeps the claim mode epoch for the dead man switch", async () => {
+    const claimModeEpoch = new anchor.BN(Buffer.alloc(8, 0xff), "le");
+    const root = Array.from(Buffer.alloc(32, 1));
+
+    try {
+      await program.methods
+        .postPayoutRoot(claimModeEpoch, root, new anchor.BN(0))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          payoutRoot: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("payout_root"),
+              masterAddress.toBuffer(),
+              Buffer.alloc(8, 0xff),
+            ],
+            program.programId
+          )[0],
+          operator: anchor.getProvider().publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "EpochReserved");
+    }
+  });
+
+  it("pays several receivers in one batch", async () => {
+    const receivers = [
+      user1.publicKey,
+      anchor.web3.Keypair.generate().publicKey,
+    ];
+    const amounts = [new anchor.BN(1000000), new anchor.BN(2000000)];
+    const remainingAccounts = receivers.flatMap((receiver) =>
+      [
+        receiver,
+        receiverAllowlistAddress(receiver),
+        blockedAddress(receiver),
+        userStateAddress(receiver),
+        canaryAddress(receiver),
+      ].map((pubkey, i) => ({ pubkey, isSigner: false, isWritable: i === 0 }))
+    );
+
+    // The plan is announced first and capped at what the batch pays.
+    const batchHash = createHash("sha256").update("batch-1").digest();
+    const announcedBatch = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("announced_batch"), masterAddress.toBuffer(), batchHash],
+      program.programId
+    )[0];
+    await program.methods
+      .announceBatch(Array.from(batchHash), new anchor.BN(3000000))
+      .accounts({
+        master: masterAddress,
+        announcedBatch,
+        mint: null,
+        operator: anchor.getProvider().publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const balanceBefore = (await program.account.master.fetch(masterAddress))
+      .balance;
+    await program.methods
+      .sendWithdrawBatch(amounts)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        operator: anchor.getProvider().publicKey,
+        announcedBatch,
+        adminCosigner: null,
+      })
+      .remainingAccounts(remainingAccounts)
+      .rpc();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(balanceBefore.sub(masterAcc.balance).toNumber()).to.be.eq(3000000);
+    expect(await provider.connection.getBalance(receivers[1])).to.be.eq(
+      2000000
+    );
+
+    try {
+      await program.methods
+        .sendWithdrawBatch([amounts[0]])
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          announcedBatch,
+          adminCosigner: null,
+        })
+        .remainingAccounts(remainingAccounts.slice(0, 5))
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "AnnouncedBatchExceeded");
+    }
+
+    try {
+      await program.methods
+        .sendWithdrawBatch(amounts)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          announcedBatch: null,
+          adminCosigner: null,
+        })
+        .remainingAccounts(remainingAccounts.slice(0, 5))
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "BatchAccountsMismatch");
+    }
+  });
+
+  it("executes a scheduled payout once it is due", async () => {
+    const amount = new anchor.BN(1000000);
+    const now = Math.floor(Date.now() / 1000);
+    const scheduledPayoutAddress = (id: anchor.BN) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("scheduled_payout"),
+          masterAddress.toBuffer(),
+          id.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+
+    const schedule = (id: anchor.BN, executeAfter: number) =>
+      program.methods
+        .schedulePayout(
+          id,
+          user1.publicKey,
+          amount,
+          new anchor.BN(executeAfter)
+        )
+        .accounts({
+          master: masterAddress,
+          scheduledPayout: scheduledPayoutAddress(id),
+          receiverCooldown: null,
+          operator: anchor.getProvider().publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // Executed by the user, the operator only authorizes the payout.
+    const execute = (id: anchor.BN) =>
+      program.methods
+        .executeScheduledPayout()
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          scheduledPayout: scheduledPayoutAddress(id),
+          operator: anchor.getProvider().publicKey,
+          receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          receiverCooldown: null,
+          adminCosigner: null,
+        })
+        .signers([user1])
+        .rpc();
+
+    await schedule(new anchor.BN(1), now + 3600);
+    try {
+      await execute(new anchor.BN(1));
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "PayoutNotDue");
+    }
+
+    await program.methods
+      .cancelScheduledPayout()
+      .accounts({
+        master: masterAddress,
+        scheduledPayout: scheduledPayoutAddress(new anchor.BN(1)),
+        operator: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    await schedule(new anchor.BN(2), now - 60);
+    const balanceBefore = (await program.account.master.fetch(masterAddress))
+      .balance;
+    await execute(new anchor.BN(2));
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(balanceBefore.sub(masterAcc.balance).toNumber()).to.be.eq(1000000);
+    assert.isNull(
+      await provider.connection.getAccountInfo(
+        scheduledPayoutAddress(new anchor.BN(2))
+      )
+    );
+  });
+
+  it("retains the express fee of an express payout", async () => {
+    const setExpressFeeBps = (feeBps: number) =>
+      program.methods
+        .setExpressFeeBps(feeBps)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+    try {
+      await setExpressFeeBps(1001);
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "FeeTooHigh");
+    }
+
+    await setExpressFeeBps(100);
+
+    const balanceBefore = (await program.account.master.fetch(masterAddress))
+      .balance;
+    const receiverBefore = await provider.connection.getBalance(
+      user1.publicKey
+    );
+    await program.methods
+      .sendWithdraw(new anchor.BN(1000000), null, true, null, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        operator: anchor.getProvider().publicKey,
+        receiver: user1.publicKey,
+        receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+        receiverBlocked: blockedAddress(user1.publicKey),
+        receiverState: userStateAddress(user1.publicKey),
+        receiverCanary: canaryAddress(user1.publicKey),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // 1% of the payout stays in the vault.
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(balanceBefore.sub(masterAcc.balance).toNumber()).to.be.eq(990000);
+    expect(
+      (await provider.connection.getBalance(user1.publicKey)) - receiverBefore
+    ).to.be.eq(990000);
+
+    await setExpressFeeBps(0);
+  });
+
+  it("posts the memo and reference of a payout", async () => {
+    const memo = "TAG-1234";
+    const reference = Array.from(Buffer.alloc(32, 7));
+    const sig = await program.methods
+      .sendWithdraw(new anchor.BN(1000000), null, false, memo, reference)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        operator: anchor.getProvider().publicKey,
+        receiver: user1.publicKey,
+        receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+        receiverBlocked: blockedAddress(user1.publicKey),
+        receiverState: userStateAddress(user1.publicKey),
+        receiverCanary: canaryAddress(user1.publicKey),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: new anchor.web3.PublicKey(
+          "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr"
+        ),
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    assert.isTrue(
+      tx.meta.logMessages.some((log) => log.includes(`Memo (len 8): "${memo}"`))
+    );
+
+    const events = Array.from(
+      new anchor.EventParser(program.programId, program.coder).parseLogs(
+        tx.meta.logMessages
+      )
+    );
+    const withdrawEvent = events.find(
+      (event) => event.name === "WithdrawEvent"
+    );
+    expect(withdrawEvent.data.reference).to.deep.eq(reference);
+  });
+
+  it("emits the old and new value of a config change", async () => {
+    const updateConfig = (setting: any) =>
+      program.methods
+        .updateConfig(setting)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          vaultToken: null,
+          programConfig: programConfigAddress,
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc({ commitment: "confirmed" });
+    const configChange = async (sig: string) => {
+      const tx = await provider.connection.getTransaction(sig, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      });
+      return Array.from(
+        new anchor.EventParser(program.programId, program.coder).parseLogs(
+          tx.meta.logMessages
+        )
+      ).find((event) => event.name === "ConfigChangedEvent");
+    };
+
+    const sig = await updateConfig({
+      vaultCap: { 0: new anchor.BN(1000000000000) },
+    });
+    const vaultConfigAcc = await program.account.vaultConfig.fetch(
+      vaultConfigAddress(masterAddress)
+    );
+    expect(vaultConfigAcc.vaultCap.toString()).to.be.eq("1000000000000");
+
+    const event = await configChange(sig);
+    expect(event.data.old.vaultCap[0].toString()).to.be.eq("0");
+    expect(event.data.new.vaultCap[0].toString()).to.be.eq("1000000000000");
+    expect(event.data.mint).to.be.null;
+
+    // The dedicated setters emit the same event.
+    const setterEvent = await configChange(
+      await program.methods
+        .setVaultCap(new anchor.BN(0))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc({ commitment: "confirmed" })
+    );
+    expect(setterEvent.data.old.vaultCap[0].toString()).to.be.eq(
+      "1000000000000"
+    );
+    expect(setterEvent.data.new.vaultCap[0].toString()).to.be.eq("0");
+  });
+
+  it("refunds a deposit once", async () => {
+    const depositId = new anchor.BN(77);
+    const refund = () =>
+      program.methods
+        .refundDeposit(depositId, new anchor.BN(1000000))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          depositor: user1.publicKey,
+          depositorBlocked: blockedAddress(user1.publicKey),
+          depositorState: userStateAddress(user1.publicKey),
+          depositorCanary: canaryAddress(user1.publicKey),
+          depositorCooldown: null,
+          adminCosigner: null,
+          depositRefund: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("deposit_refund"),
+              masterAddress.toBuffer(),
+              depositId.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+          )[0],
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    const balanceBefore = (await program.account.master.fetch(masterAddress))
+      .balance;
+    await refund();
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(balanceBefore.sub(masterAcc.balance).toNumber()).to.be.eq(1000000);
+
+    try {
+      await refund();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.include(_err.toString(), "already in use");
+    }
+  });
+
+  it("holds escrowed deposits until released or rejected", async () => {
+    const user = anchor.getProvider().publicKey;
+    const setWindow = (windowSecs: number) =>
+      program.methods
+        .setDepositEscrowWindow(new anchor.BN(windowSecs))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: user,
+        })
+        .rpc();
+    const escrowAddress = (escrowId: anchor.BN) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("deposit_escrow"),
+          masterAddress.toBuffer(),
+          user.toBuffer(),
+          escrowId.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+    const depositEscrowed = (escrowId: anchor.BN) =>
+      program.methods
+        .depositEscrowed(escrowId, new anchor.BN(1000000))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user,
+          userBlocked: blockedAddress(user),
+          userWhitelist: depositWhitelistAddress(user),
+          userState: userStateAddress(user),
+          userExchange: exchangeWalletAddress(user),
+          escrow: escrowAddress(escrowId),
+          userDeposits: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    await setWindow(2);
+    await depositEscrowed(new anchor.BN(1));
+    await depositEscrowed(new anchor.BN(2));
+
+    await program.methods
+      .rejectDeposit()
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        operator: user,
+        user,
+        escrow: escrowAddress(new anchor.BN(1)),
+        userDeposits: null,
+      })
+      .rpc();
+    assert.isNull(
+      await provider.connection.getAccountInfo(escrowAddress(new anchor.BN(1)))
+    );
+
+    const release = () =>
+      program.methods
+        .releaseDeposit()
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: user,
+          user,
+          escrow: escrowAddress(new anchor.BN(2)),
+        })
+        .rpc();
+    try {
+      await release();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "DepositInEscrowWindow");
+    }
+
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    const balanceBefore = (await program.account.master.fetch(masterAddress))
+      .balance;
+    await release();
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(masterAcc.balance.sub(balanceBefore).toNumber()).to.be.eq(1000000);
+
+    await setWindow(0);
+  });
+
+  it("switches a silent vault to claim mode", async () => {
+    // Own vault, claim mode cannot be left.
+    const deadVaultId = new anchor.BN(3);
+    const deadMaster = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("master"), deadVaultId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const pdaOf = (prefix: string, address: anchor.web3.PublicKey) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from(prefix), deadMaster.toBuffer(), address.toBuffer()],
+        program.programId
+      )[0];
+    const payer = anchor.getProvider().publicKey;
+
+    await program.methods
+      .initMaster(deadVaultId, vaultMetadata("Dead", "CLAIM"))
+      .accounts({
+        master: deadMaster,
+        vaultConfig: vaultConfigAddress(deadMaster),
+        programConfig: programConfigAddress,
+        vaultRegistry: vaultRegistryAddress,
+        payer,
+        admin: payer,
+        operator: payer,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .deposit(new anchor.BN(1000000), null)
+      .accounts({
+        master: deadMaster,
+        vaultConfig: vaultConfigAddress(deadMaster),
+        programConfig: programConfigAddress,
+        user: payer,
+        userBlocked: pdaOf("blocked", payer),
+        userWhitelist: pdaOf("deposit_whitelist", payer),
+        userState: pdaOf("user", payer),
+        userExchange: pdaOf("exchange_wallet", payer),
+        productLedger: null,
+        userDeposits: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    // Single entry, so the root is the leaf of user1 and the proof is empty.
+    const amount = new anchor.BN(1000000);
+    const balanceRoot = createHash("sha256")
+      .update(
+        Buffer.concat([
+          Buffer.from([0]),
+          user1.publicKey.toBuffer(),
+          amount.toArrayLike(Buffer, "le", 8),
+          Buffer.alloc(8, 0xff),
+        ])
+      )
+      .digest();
+    await program.methods
+      .heartbeat(Array.from(balanceRoot))
+      .accounts({ master: deadMaster, operator: payer })
+      .rpc();
+    // Part of the balance is self-withdrawn, the claim pays only the rest.
+    const selfWithdrawn = new anchor.BN(400000);
+    await program.methods
+      .setSelfWithdrawLimits(selfWithdrawn, new anchor.BN(0))
+      .accounts({
+        master: deadMaster,
+        vaultConfig: vaultConfigAddress(deadMaster),
+        admin: payer,
+      })
+      .rpc();
+    await program.methods
+      .selfWithdraw(selfWithdrawn, amount, [])
+      .accounts({
+        master: deadMaster,
+        vaultConfig: vaultConfigAddress(deadMaster),
+        programConfig: programConfigAddress,
+        selfWithdrawal: pdaOf("self_withdrawal", user1.publicKey),
+        user: user1.publicKey,
+        receiverAllowlist: pdaOf("receiver_allowlist", user1.publicKey),
+        receiverBlocked: pdaOf("blocked", user1.publicKey),
+        receiverState: pdaOf("user", user1.publicKey),
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+    await program.methods
+      .setDeadManPeriod(new anchor.BN(1))
+      .accounts({
+        master: deadMaster,
+        vaultConfig: vaultConfigAddress(deadMaster),
+        admin: payer,
+      })
+      .rpc();
+
+    const payoutRoot = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("payout_root"),
+        deadMaster.toBuffer(),
+        Buffer.alloc(8, 0xff),
+      ],
+      program.programId
+    )[0];
+    await new Promise((resolve) => setTimeout(resolve, 3000));
+    await program.methods
+      .triggerClaimMode()
+      .accounts({
+        master: deadMaster,
+        vaultConfig: vaultConfigAddress(deadMaster),
+        payoutRoot,
+        payer,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const deadMasterAcc = await program.account.master.fetch(deadMaster);
+    assert.isTrue(deadMasterAcc.claimMode);
+    const deadVaultConfigAcc = await program.account.vaultConfig.fetch(
+      vaultConfigAddress(deadMaster)
+    );
+    assert.isTrue(deadVaultConfigAcc.withdrawalsPaused);
+
+    await program.methods
+      .claim(amount, [])
+      .accounts({
+        master: deadMaster,
+        vaultConfig: vaultConfigAddress(deadMaster),
+        programConfig: programConfigAddress,
+        payoutRoot,
+        claim: anchor.web3.PublicKey.findProgramAddressSync(
+          [
+            Buffer.from("claim"),
+            payoutRoot.toBuffer(),
+            user1.publicKey.toBuffer(),
+          ],
+          program.programId
+        )[0],
+        user: user1.publicKey,
+        receiverAllowlist: pdaOf("receiver_allowlist", user1.publicKey),
+        receiverBlocked: pdaOf("blocked", user1.publicKey),
+        receiverState: pdaOf("user", user1.publicKey),
+        selfWithdrawal: pdaOf("self_withdrawal", user1.publicKey),
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user1])
+      .rpc();
+
+    const claimedAcc = await program.account.master.fetch(deadMaster);
+    expect(claimedAcc.balance.toNumber()).to.be.eq(0);
+  });
+
+  it("lets users withdraw small amounts themselves", async () => {
+    const operator = anchor.getProvider().publicKey;
+    const balance = new anchor.BN(5000000);
+    // Single entry, so the root is the leaf of user1 and the proof is empty.
+    const balanceRoot = createHash("sha256")
+      .update(
+        Buffer.concat([
+          Buffer.from([0]),
+          user1.publicKey.toBuffer(),
+          balance.toArrayLike(Buffer, "le", 8),
+          Buffer.alloc(8, 0xff),
+        ])
+      )
+      .digest();
+    await program.methods
+      .heartbeat(Array.from(balanceRoot))
+      .accounts({ master: masterAddress, operator })
+      .rpc();
+    const setLimits = (threshold: number) =>
+      program.methods
+        .setSelfWithdrawLimits(new anchor.BN(threshold), new anchor.BN(0))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: operator,
+        })
+        .rpc();
+    await setLimits(2000000);
+
+    const selfWithdraw = (amount: number) =>
+      program.methods
+        .selfWithdraw(new anchor.BN(amount), balance, [])
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          selfWithdrawal: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("self_withdrawal"),
+              masterAddress.toBuffer(),
+              user1.publicKey.toBuffer(),
+            ],
+            program.programId
+          )[0],
+          user: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([user1])
+        .rpc();
+
+    const expectError = async (amount: number, code: string) => {
+      try {
+        await selfWithdraw(amount);
+
+        assert.ok(false);
+      } catch (_err) {
+        assert.isTrue(_err instanceof AnchorError);
+        const err: AnchorError = _err;
+        assert.strictEqual(err.error.errorCode.code, code);
+      }
+    };
+
+    await expectError(3000000, "SelfWithdrawTooLarge");
+    const balanceBefore = (await program.account.master.fetch(masterAddress))
+      .balance;
+    await selfWithdraw(1000000);
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(balanceBefore.sub(masterAcc.balance).toNumber()).to.be.eq(1000000);
+    // The root has not been republished since.
+    await expectError(1000000, "SelfWithdrawCooldown");
+
+    await setLimits(0);
+  });
+
+  it("caps the SOL of a single payout", async () => {
+    const setCap = (cap: number) =>
+      program.methods
+        .setMaxSolPerPayout(new anchor.BN(cap))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc();
+
+    await setCap(500000);
+    try {
+      await program.methods
+        .sendWithdraw(new anchor.BN(1000000), null, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest: null,
+          withdrawQueue: null,
+          receipt: null,
+          memoProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "PayoutCapExceeded");
+    }
+    await setCap(0);
+  });
+
+  it("lets limit exempt receivers bypass the payout cap", async () => {
+    const admin = anchor.getProvider().publicKey;
+    const limitExempt = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("limit_exempt"),
+        masterAddress.toBuffer(),
+        user1.publicKey.toBuffer(),
+      ],
+      program.programId
+    )[0];
+    const setCap = (cap: number) =>
+      program.methods
+        .setMaxSolPerPayout(new anchor.BN(cap))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin,
+        })
+        .rpc();
+
+    await program.methods
+      .addLimitExemptReceiver()
+      .accounts({
+        master: masterAddress,
+        limitExempt,
+        admin,
+        receiver: user1.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await setCap(500);
+    await program.methods
+      .sendWithdraw(new anchor.BN(1000), null, false, null, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        operator: admin,
+        receiver: user1.publicKey,
+        receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+        receiverBlocked: blockedAddress(user1.publicKey),
+        receiverState: userStateAddress(user1.publicKey),
+        receiverCanary: canaryAddress(user1.publicKey),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await setCap(0);
+
+    await program.methods
+      .removeLimitExemptReceiver()
+      .accounts({
+        master: masterAddress,
+        limitExempt,
+        admin,
+        receiver: user1.publicKey,
+      })
+      .rpc();
+  });
+
+  it("needs an admin co-sign outside the payout hours", async () => {
+    const admin = anchor.getProvider().publicKey;
+    const setPayoutHours = (payoutHours: number) =>
+      program.methods
+        .setPayoutHours(payoutHours)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin,
+        })
+        .rpc();
+    const sendWithdraw = (adminCosigner: anchor.web3.PublicKey | null) =>
+      program.methods
+        .sendWithdraw(new anchor.BN(1000), null, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: admin,
+          receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner,
+          withdrawRequest: null,
+          withdrawQueue: null,
+          receipt: null,
+          memoProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // Every hour but the current one, so the payout is out of hours.
+    const hour = new Date().getUTCHours();
+    await setPayoutHours(0xffffff & ~(1 << hour));
+    try {
+      await sendWithdraw(null);
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "OutsidePayoutHours");
+    }
+    await sendWithdraw(admin);
+
+    // Refunds are operator payouts as well.
+    const depositId = new anchor.BN(78);
+    try {
+      await program.methods
+        .refundDeposit(depositId, new anchor.BN(1000))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: admin,
+          depositor: user1.publicKey,
+          depositorBlocked: blockedAddress(user1.publicKey),
+          depositorState: userStateAddress(user1.publicKey),
+          depositorCanary: canaryAddress(user1.publicKey),
+          depositorCooldown: null,
+          adminCosigner: null,
+          depositRefund: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("deposit_refund"),
+              masterAddress.toBuffer(),
+              depositId.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+          )[0],
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "OutsidePayoutHours");
+    }
+    await setPayoutHours(0);
+  });
+
+  it("rejects deposits outside the allowed size", async () => {
+    const user = anchor.getProvider().publicKey;
+    const setMinDeposit = (minDeposit: number) =>
+      program.methods
+        .setMinDeposit(new anchor.BN(minDeposit))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: user,
+        })
+        .rpc();
+    const deposit = (amount: number) =>
+      program.methods
+        .deposit(new anchor.BN(amount), null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user,
+          userBlocked: blockedAddress(user),
+          userWhitelist: depositWhitelistAddress(user),
+          userState: userStateAddress(user),
+          userExchange: exchangeWalletAddress(user),
+          productLedger: null,
+          userDeposits: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    await setMinDeposit(1000);
+    for (const amount of [0, 999]) {
+      try {
+        await deposit(amount);
+
+        assert.ok(false);
+      } catch (_err) {
+        assert.isTrue(_err instanceof AnchorError);
+        const err: AnchorError = _err;
+        assert.strictEqual(err.error.errorCode.code, "DepositTooSmall");
+      }
+    }
+    await deposit(1000);
+    await setMinDeposit(0);
+
+    await program.methods
+      .setMaxDeposit(new anchor.BN(5000))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: user,
+      })
+      .rpc();
+    try {
+      await deposit(5001);
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "DepositTooLarge");
+    }
+    await program.methods
+      .setMaxDeposit(new anchor.BN(0))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: user,
+      })
+      .rpc();
+  });
+
+  it("caps lifetime deposits per user", async () => {
+    const user = anchor.getProvider().publicKey;
+    const asset = anchor.web3.PublicKey.default;
+    const userDepositsAddress = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("user_deposits"),
+        masterAddress.toBuffer(),
+        user.toBuffer(),
+        asset.toBuffer(),
+      ],
+      program.programId
+    )[0];
+    const setDefaultCap = (cap: number) =>
+      program.methods
+        .setDefaultUserDepositCap(new anchor.BN(cap))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: user,
+        })
+        .rpc();
+    const deposit = (userDeposits: anchor.web3.PublicKey | null) =>
+      program.methods
+        .deposit(new anchor.BN(3000), null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user,
+          userBlocked: blockedAddress(user),
+          userWhitelist: depositWhitelistAddress(user),
+          userState: userStateAddress(user),
+          userExchange: exchangeWalletAddress(user),
+          productLedger: null,
+          userDeposits,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    const expectError = async (
+      promise: Promise<string>,
+      code: string
+    ) => {
+      try {
+        await promise;
+
+        assert.ok(false);
+      } catch (_err) {
+        assert.isTrue(_err instanceof AnchorError);
+        const err: AnchorError = _err;
+        assert.strictEqual(err.error.errorCode.code, code);
+      }
+    };
+
+    await setDefaultCap(5000);
+    await expectError(deposit(null), "UserDepositsMissing");
+
+    await program.methods
+      .openUserDeposits(asset)
+      .accounts({
+        master: masterAddress,
+        userDeposits: userDepositsAddress,
+        user,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await deposit(userDepositsAddress);
+    await expectError(deposit(userDepositsAddress), "UserDepositCapExceeded");
+
+    // Enhanced KYC raises the cap of the user above the default.
+    await program.methods
+      .setUserDepositCap(user, asset, new anchor.BN(10000))
+      .accounts({
+        master: masterAddress,
+        userDeposits: userDepositsAddress,
+        admin: user,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await deposit(userDepositsAddress);
+
+    const userDeposits = await program.account.userDeposits.fetch(
+      userDepositsAddress
+    );
+    expect(userDeposits.deposited.toString()).to.be.eq("6000");
+
+    // A rejected escrowed deposit no longer counts against the cap.
+    const escrowId = new anchor.BN(100);
+    const escrow = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("deposit_escrow"),
+        masterAddress.toBuffer(),
+        user.toBuffer(),
+        escrowId.toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+    await program.methods
+      .setDepositEscrowWindow(new anchor.BN(3600))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: user,
+      })
+      .rpc();
+    await program.methods
+      .depositEscrowed(escrowId, new anchor.BN(3000))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        user,
+        userBlocked: blockedAddress(user),
+        userWhitelist: depositWhitelistAddress(user),
+        userState: userStateAddress(user),
+        userExchange: exchangeWalletAddress(user),
+        escrow,
+        userDeposits: userDepositsAddress,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    await program.methods
+      .rejectDeposit()
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        operator: user,
+        user,
+        escrow,
+        userDeposits: userDepositsAddress,
+      })
+      .rpc();
+    await program.methods
+      .setDepositEscrowWindow(new anchor.BN(0))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin: user,
+      })
+      .rpc();
+
+    const rejected = await program.account.userDeposits.fetch(
+      userDepositsAddress
+    );
+    expect(rejected.deposited.toString()).to.be.eq("6000");
+    await setDefaultCap(0);
+  });
+
+  it("rejects deposits above the vault cap", async () => {
+    const user = anchor.getProvider().publicKey;
+    const setVaultCap = (cap: anchor.BN) =>
+      program.methods
+        .setVaultCap(cap)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: user,
+        })
+        .rpc();
+    const deposit = (amount: number) =>
+      program.methods
+        .deposit(new anchor.BN(amount), null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user,
+          userBlocked: blockedAddress(user),
+          userWhitelist: depositWhitelistAddress(user),
+          userState: userStateAddress(user),
+          userExchange: exchangeWalletAddress(user),
+          productLedger: null,
+          userDeposits: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    await setVaultCap(masterAcc.balance.add(new anchor.BN(1000)));
+    try {
+      await deposit(1001);
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "VaultCapReached");
+    }
+    await deposit(1000);
+
+    // Donations count against the cap as well.
+    try {
+      await program.methods
+        .donate(new anchor.BN(1))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          donor: user,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "VaultCapReached");
+    }
+    await setVaultCap(new anchor.BN(0));
+  });
+
+  it("rejects deposits arriving too fast", async () => {
+    const user = anchor.getProvider().publicKey;
+    const setLimits = (windowSecs: number, maxCount: number) =>
+      program.methods
+        .setDepositVelocityLimits(
+          new anchor.BN(windowSecs),
+          new anchor.BN(maxCount),
+          new anchor.BN(0)
+        )
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: user,
+        })
+        .rpc();
+    const deposit = () =>
+      program.methods
+        .deposit(new anchor.BN(1000), null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          user,
+          userBlocked: blockedAddress(user),
+          userWhitelist: depositWhitelistAddress(user),
+          userState: userStateAddress(user),
+          userExchange: exchangeWalletAddress(user),
+          productLedger: null,
+          userDeposits: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    const trip = () =>
+      program.methods
+        .tripDepositVelocity(user, new anchor.BN(1000))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          vaultToken: null,
+          operator: user,
+        })
+        .rpc();
+
+    await setLimits(3600, 1);
+    try {
+      await trip();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(
+        err.error.errorCode.code,
+        "DepositVelocityWithinLimits"
+      );
+    }
+    await deposit();
+    const before = await program.account.master.fetch(masterAddress);
+    try {
+      await deposit();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "DepositVelocityExceeded");
+    }
+    await trip();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    const vaultConfigAcc = await program.account.vaultConfig.fetch(
+      vaultConfigAddress(masterAddress)
+    );
+    expect(vaultConfigAcc.depositsPaused).to.be.true;
+    expect(masterAcc.balance.toString()).to.be.eq(before.balance.toString());
+
+    await setLimits(0, 0);
+    await program.methods
+      .setDepositsPaused(false)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        authority: user,
+      })
+      .rpc();
+  });
+
+  it("reserves the balance above the hot threshold", async () => {
+    const admin = anchor.getProvider().publicKey;
+    const coldWallet = user1.publicKey;
+    const setColdWallet = (threshold: anchor.BN) =>
+      program.methods
+        .setColdWallet(coldWallet, threshold)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin,
+        })
+        .rpc();
+
+    const before = await program.account.master.fetch(masterAddress);
+    await setColdWallet(before.balance.sub(new anchor.BN(1000)));
+    try {
+      await program.methods
+        .withdraw(before.balance)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "HotBalanceExceeded");
+    }
+
+    await program.methods
+      .withdrawToCold(new anchor.BN(1000))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin,
+        coldWallet,
+      })
+      .rpc();
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(masterAcc.balance.toString()).to.be.eq(
+      before.balance.sub(new anchor.BN(1000)).toString()
+    );
+    await setColdWallet(new anchor.BN(0));
+  });
+
+  it("sweeps the balance above the hot threshold to cold", async () => {
+    const admin = anchor.getProvider().publicKey;
+    const coldWallet = user1.publicKey;
+    const before = await program.account.master.fetch(masterAddress);
+    const threshold = before.balance.sub(new anchor.BN(500));
+
+    await program.methods
+      .setColdWallet(coldWallet, threshold)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin,
+      })
+      .rpc();
+    await program.methods
+      .sweepToCold()
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        coldWallet,
+      })
+      .rpc();
+
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    expect(masterAcc.balance.toString()).to.be.eq(threshold.toString());
+    expect(masterAcc.coldReserve.toString()).to.be.eq("0");
+
+    await program.methods
+      .setColdWallet(coldWallet, new anchor.BN(0))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin,
+      })
+      .rpc();
+  });
+
+  it("enforces the payout cooldown per receiver", async () => {
+    const setPayoutCooldown = (cooldownSecs: number) =>
+      program.methods
+        .setPayoutCooldown(new anchor.BN(cooldownSecs))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin: anchor.getProvider().publicKey,
+        })
+        .rpc();
+    const sendWithdraw = (receiverCooldown: anchor.web3.PublicKey | null) =>
+      program.methods
+        .sendWithdraw(new anchor.BN(1000), null, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          receiver: user1.publicKey,
+          receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+          receiverBlocked: blockedAddress(user1.publicKey),
+          receiverState: userStateAddress(user1.publicKey),
+          receiverCanary: canaryAddress(user1.publicKey),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest: null,
+          withdrawQueue: null,
+          receipt: null,
+          memoProgram: null,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    const expectError = async (promise: Promise<string>, code: string) => {
+      try {
+        await promise;
+
+        assert.ok(false);
+      } catch (_err) {
+        assert.isTrue(_err instanceof AnchorError);
+        const err: AnchorError = _err;
+        assert.strictEqual(err.error.errorCode.code, code);
+      }
+    };
+    const receiverCooldown = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receiver_cooldown"),
+        masterAddress.toBuffer(),
+        user1.publicKey.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+    await setPayoutCooldown(3600);
+    await expectError(sendWithdraw(null), "ReceiverCooldownMissing");
+    await sendWithdraw(receiverCooldown);
+    await expectError(sendWithdraw(receiverCooldown), "ReceiverInCooldown");
+
+    // Refunds count as payouts to the depositor.
+    const depositId = new anchor.BN(79);
+    await expectError(
+      program.methods
+        .refundDeposit(depositId, new anchor.BN(1000))
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          depositor: user1.publicKey,
+          depositorBlocked: blockedAddress(user1.publicKey),
+          depositorState: userStateAddress(user1.publicKey),
+          depositorCanary: canaryAddress(user1.publicKey),
+          depositorCooldown: receiverCooldown,
+          adminCosigner: null,
+          depositRefund: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("deposit_refund"),
+              masterAddress.toBuffer(),
+              depositId.toArrayLike(Buffer, "le", 8),
+            ],
+            program.programId
+          )[0],
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc(),
+      "ReceiverInCooldown"
+    );
+
+    // Batches cannot pass the cooldown PDAs and are rejected.
+    await expectError(
+      program.methods
+        .sendWithdrawBatch([new anchor.BN(1000)])
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          operator: anchor.getProvider().publicKey,
+          announcedBatch: null,
+          adminCosigner: null,
+        })
+        .remainingAccounts(
+          [
+            user1.publicKey,
+            receiverAllowlistAddress(user1.publicKey),
+            blockedAddress(user1.publicKey),
+            userStateAddress(user1.publicKey),
+            canaryAddress(user1.publicKey),
+          ].map((pubkey, i) => ({
+            pubkey,
+            isSigner: false,
+            isWritable: i === 0,
+          }))
+        )
+        .rpc(),
+      "ReceiverCooldownMissing"
+    );
+    await setPayoutCooldown(0);
+  });
+
   it("cant init same ATA twice", async () => {
     await provider.connection.confirmTransaction(
       await provider.connection.requestAirdrop(
@@ -0,0 +1,283 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+
+import { ProBalance } from "../target/types/pro_balance";
+import { assert } from "chai";
+
+// Long-running soak test, run with `anchor run soak` against a local validator
+// the program has been deployed to. It runs randomized deposit, payout and
+// admin cycles on its own vault and checks invariants after every cycle.
+//
+// SOAK_ITERATIONS sets the number of cycles (default 1000), SOAK_SEED the
+// seed of the random choices so a failing run can be replayed.
+describe("soak", () => {
+  // Configure the client to use the local cluster.
+  anchor.setProvider(anchor.AnchorProvider.env());
+
+  const program = anchor.workspace.ProBalance as Program<ProBalance>;
+
+  const provider = anchor.AnchorProvider.env();
+  const admin = provider.publicKey;
+  const LAMPORTS_PER_SOL = 1000000000;
+
+  const iterations = Number(process.env.SOAK_ITERATIONS || 1000);
+  const seed = Number(process.env.SOAK_SEED || Date.now());
+
+  // Separate vault, so the soak test never touches the state of other tests.
+  const vaultId = new anchor.BN(9001);
+  const masterAddress = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("master"), vaultId.toArrayLike(Buffer, "le", 8)],
+    program.programId
+  )[0];
+
+  const programConfigAddress = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("program_config")],
+    program.programId
+  )[0];
+
+  const vaultConfigAddress = (master: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault_config"), master.toBuffer()],
+      program.programId
+    )[0];
+
+  // Label of a vault, both fields zero padded to their fixed length.
+  const vaultMetadata = (name: string, productCode: string) => {
+    const padded = (text: string, length: number) => {
+      const bytes = Buffer.alloc(length);
+      bytes.write(text);
+      return Array.from(bytes);
+    };
+    return { name: padded(name, 32), productCode: padded(productCode, 8) };
+  };
+
+  const users = Array.from({ length: 8 }, () =>
+    anchor.web3.Keypair.generate()
+  );
+
+  const pda = (prefix: string, address: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from(prefix), masterAddress.toBuffer(), address.toBuffer()],
+      program.programId
+    )[0];
+
+  // mulberry32, small seeded PRNG so runs are reproducible
+  let state = seed;
+  const random = () => {
+    state = (state + 0x6d2b79f5) | 0;
+    let t = Math.imul(state ^ (state >>> 15), 1 | state);
+    t = (t + Math.imul(t ^ (t >>> 7), 61 | t)) ^ t;
+    return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+  };
+  const pick = <T>(items: T[]): T => items[Math.floor(random() * items.length)];
+  const lamports = () =>
+    new anchor.BN(1000000 + Math.floor(random() * 9000000));
+
+  // Highest compute units seen per instruction during the warm-up cycles.
+  const WARM_UP = 100;
+  const cuBaseline = new Map<string, number>();
+
+  const send = async (name: string, builder: any) => {
+    const sig = await builder.rpc({ commitment: "confirmed" });
+    const tx = await provider.connection.getTransaction(sig, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    return { name, cu: tx.meta.computeUnitsConsumed };
+  };
+
+  let expectedBalance = new anchor.BN(0);
+  let depositsPaused = false;
+
+  const deposit = async () => {
+    const user = pick(users);
+    const amount = lamports();
+
+    const builder = program.methods
+      .deposit(amount, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        user: user.publicKey,
+        userBlocked: pda("blocked", user.publicKey),
+        userWhitelist: pda("deposit_whitelist", user.publicKey),
+        userState: pda("user", user.publicKey),
+        userExchange: pda("exchange_wallet", user.publicKey),
+        productLedger: null,
+        userDeposits: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([user]);
+
+    if (depositsPaused) {
+      try {
+        await builder.rpc();
+        assert.fail("deposit went through while deposits are paused");
+      } catch (err) {
+        assert.include(err.toString(), "DepositsPaused");
+      }
+      return null;
+    }
+
+    const result = await send("deposit", builder);
+    expectedBalance = expectedBalance.add(amount);
+    return result;
+  };
+
+  const sendWithdraw = async () => {
+    const receiver = pick(users).publicKey;
+    const amount = anchor.BN.min(lamports(), expectedBalance);
+
+    const result = await send(
+      "sendWithdraw",
+      program.methods.sendWithdraw(amount, null, false, null, null).accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        operator: admin,
+        receiver,
+        receiverAllowlist: pda("receiver_allowlist", receiver),
+        receiverBlocked: pda("blocked", receiver),
+        receiverState: pda("user", receiver),
+        receiverCanary: pda("canary", receiver),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+    );
+    expectedBalance = expectedBalance.sub(amount);
+    return result;
+  };
+
+  const withdraw = async () => {
+    const amount = anchor.BN.min(lamports(), expectedBalance);
+
+    const result = await send(
+      "withdraw",
+      program.methods.withdraw(amount).accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+    );
+    expectedBalance = expectedBalance.sub(amount);
+    return result;
+  };
+
+  const toggleDeposits = async () => {
+    depositsPaused = !depositsPaused;
+
+    return send(
+      "setDepositsPaused",
+      program.methods.setDepositsPaused(depositsPaused).accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        authority: admin,
+      })
+    );
+  };
+
+  const checkInvariants = async (cycle: number) => {
+    const masterAcc = await program.account.master.fetch(masterAddress);
+    const info = await provider.connection.getAccountInfo(masterAddress);
+    const rent = await provider.connection.getMinimumBalanceForRentExemption(
+      info.data.length
+    );
+
+    assert.strictEqual(
+      masterAcc.balance.toString(),
+      expectedBalance.toString(),
+      `tracked balance drifted at cycle ${cycle}`
+    );
+    assert.strictEqual(
+      info.lamports,
+      rent + masterAcc.balance.toNumber(),
+      `lamports are not rent plus balance at cycle ${cycle}`
+    );
+    const vaultConfigAcc = await program.account.vaultConfig.fetch(
+      vaultConfigAddress(masterAddress)
+    );
+    assert.strictEqual(vaultConfigAcc.depositsPaused, depositsPaused);
+  };
+
+  before(async () => {
+    console.log(`soak: ${iterations} cycles, SOAK_SEED=${seed}`);
+
+    await program.methods
+      .initMaster(vaultId, vaultMetadata("Soak", "SOAK"))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        vaultRegistry: anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("vault_registry")],
+          program.programId
+        )[0],
+        payer: admin,
+        admin,
+        operator: admin,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    for (const user of users) {
+      await provider.connection.confirmTransaction(
+        await provider.connection.requestAirdrop(
+          user.publicKey,
+          100 * LAMPORTS_PER_SOL
+        )
+      );
+    }
+  });
+
+  it("keeps invariants over randomized cycles", async () => {
+    const heapStart = process.memoryUsage().heapUsed;
+
+    for (let cycle = 0; cycle < iterations; cycle++) {
+      const roll = random();
+      const result =
+        depositsPaused && expectedBalance.isZero()
+          ? await toggleDeposits()
+          : roll < 0.5 || expectedBalance.isZero()
+          ? await deposit()
+          : roll < 0.8
+          ? await sendWithdraw()
+          : roll < 0.95
+          ? await withdraw()
+          : await toggleDeposits();
+
+      if (result) {
+        const baseline = cuBaseline.get(result.name) || 0;
+        if (cycle < WARM_UP) {
+          cuBaseline.set(result.name, Math.max(baseline, result.cu));
+        } else if (baseline > 0) {
+          assert.isAtMost(
+            result.cu,
+            Math.ceil(baseline * 1.2),
+            `${result.name} compute units grew at cycle ${cycle}`
+          );
+        }
+      }
+
+      await checkInvariants(cycle);
+
+      if (cycle % 100 === 99) {
+        const heapMb =
+          (process.memoryUsage().heapUsed - heapStart) / 1024 / 1024;
+        console.log(
+          `soak: cycle ${cycle + 1}, balance ${expectedBalance.toString()}, ` +
+            `heap +${heapMb.toFixed(1)} MB`
+        );
+      }
+    }
+  });
+});
@@ -11,10 +11,12 @@ import {
   TOKEN_PROGRAM_ID,
   getAssociatedTokenAddress,
   ASSOCIATED_TOKEN_PROGRAM_ID,
+  NATIVE_MINT,
 } from "@solana/spl-token";
 
 import { ProBalance } from "../target/types/pro_balance";
 import { assert, expect } from "chai";
+import { createHash } from "crypto";
 
 describe("tokenActions", () => {
   // Configure the client to use the local cluster.
@@ -28,6 +30,18 @@ describe("tokenActions", () => {
   const user1 = anchor.web3.Keypair.generate();
 
   let masterAddress;
+  const vaultId = new anchor.BN(0);
+
+  const programConfigAddress = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("program_config")],
+    program.programId
+  )[0];
+
+  const vaultConfigAddress = (master: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault_config"), master.toBuffer()],
+      program.programId
+    )[0];
 
   const depositAmount = new anchor.BN(1000000000);
 
@@ -41,10 +55,70 @@ describe("tokenActions", () => {
   let mintSC;
   let person1ATA;
 
+  const receiverAllowlistAddress = (receiver: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("receiver_allowlist"),
+        masterAddress.toBuffer(),
+        receiver.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+  const blockedAddress = (address: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("blocked"), masterAddress.toBuffer(), address.toBuffer()],
+      program.programId
+    )[0];
+
+  const depositWhitelistAddress = (user: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("deposit_whitelist"),
+        masterAddress.toBuffer(),
+        user.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+  const userStateAddress = (user: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("user"), masterAddress.toBuffer(), user.toBuffer()],
+      program.programId
+    )[0];
+
+  const exchangeWalletAddress = (address: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("exchange_wallet"),
+        masterAddress.toBuffer(),
+        address.toBuffer(),
+      ],
+      program.programId
+    )[0];
+
+  const canaryAddress = (receiver: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("canary"), masterAddress.toBuffer(), receiver.toBuffer()],
+      program.programId
+    )[0];
+
+  const penaltyLedgerAddress = (operator: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("penalty"), masterAddress.toBuffer(), operator.toBuffer()],
+      program.programId
+    )[0];
+
+  const vaultTokenAddress = (mint: anchor.web3.PublicKey) =>
+    anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("vault_token"), masterAddress.toBuffer(), mint.toBuffer()],
+      program.programId
+    )[0];
+
   it("can deposit token", async () => {
     masterAddress = (
       await anchor.web3.PublicKey.findProgramAddress(
-        [Buffer.from("master")],
+        [Buffer.from("master"), vaultId.toArrayLike(Buffer, "le", 8)],
         new anchor.web3.PublicKey(
           "8ZwcssGn5vKE1d6oBNNTTjDsFyTDKSuPtoooZQe9MHXb"
         )
@@ -86,13 +160,28 @@ describe("tokenActions", () => {
       masterAddress,
       true
     );
+    const programConfig = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("program_config")],
+      program.programId
+    )[0];
+
     await program.methods
-      .initAta()
+      .addAllowedMint(mintSC)
+      .accounts({
+        programConfig,
+        superAdmin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    await program.methods
+      .addSupportedMint()
       .accounts({
         master: masterAddress,
+        programConfig,
+        vaultToken: vaultTokenAddress(mintSC),
         masterAta: masterAta,
         tokenMint: mintSC,
-        user: anchor.getProvider().publicKey,
+        admin: anchor.getProvider().publicKey,
         tokenProgram: TOKEN_PROGRAM_ID,
         associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
@@ -166,15 +255,24 @@ describe("tokenActions", () => {
     //  assert.equal(programTokenBalance, 0);
 
     await program.methods
-      .depositToken(new anchor.BN(10))
+      .depositToken(new anchor.BN(10), null)
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         from: person1ATA,
+        vaultToken: vaultTokenAddress(mintSC),
         masterAta: masterAta,
         tokenMint: mintSC,
         user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
       })
       .rpc();
 
@@ -192,15 +290,24 @@ describe("tokenActions", () => {
     assert.equal(programTokenBalance, 10);
 
     await program.methods
-      .depositToken(new anchor.BN(30))
+      .depositToken(new anchor.BN(30), null)
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
         tokenProgram: TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
         from: user1ATA,
+        vaultToken: vaultTokenAddress(mintSC),
         masterAta: masterAta,
         tokenMint: mintSC,
         user: user1.publicKey,
+        userBlocked: blockedAddress(user1.publicKey),
+        userWhitelist: depositWhitelistAddress(user1.publicKey),
+        userState: userStateAddress(user1.publicKey),
+        userExchange: exchangeWalletAddress(user1.publicKey),
+        productLedger: null,
+        userDeposits: null,
       })
       .signers([user1])
       .rpc();
@@ -223,6 +330,7 @@ describe("tokenActions", () => {
       .withdrawToken(new anchor.BN(30))
       .accounts({
         master: masterAddress,
+        vaultToken: vaultTokenAddress(mintSC),
         masterAta: masterAta,
         admin: anchor.getProvider().publicKey,
         adminAta: adminATA,
@@ -246,15 +354,33 @@ describe("tokenActions", () => {
     assert.equal(adminTokenBalance, 120);
 
     await program.methods
-      .sendWithdrawToken(new anchor.BN(10))
+      .sendWithdrawToken(new anchor.BN(10), null, false, null, null)
       .accounts({
         master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        vaultToken: vaultTokenAddress(mintSC),
         masterAta: masterAta,
         operator: anchor.getProvider().publicKey,
         receiverAta: user1ATA,
         receiver: user1.publicKey,
+        receiverAllowlist: receiverAllowlistAddress(user1.publicKey),
+        receiverBlocked: blockedAddress(user1.publicKey),
+        receiverState: userStateAddress(user1.publicKey),
+        receiverCanary: canaryAddress(user1.publicKey),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
+        penaltyLedger: penaltyLedgerAddress(operator),
         tokenMint: mintSC,
         tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
         systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
@@ -264,5 +390,882 @@ describe("tokenActions", () => {
     ).value.data.parsed.info.tokenAmount.amount;
 
     assert.equal(programTokenBalance, 0);
+
+    const vaultToken = await program.account.vaultToken.fetch(
+      vaultTokenAddress(mintSC)
+    );
+    expect(vaultToken.balance.toNumber()).to.be.eq(0);
+  });
+
+  it("cant deposit token with a different mint", async () => {
+    const otherMint = await createMint(
+      provider.connection,
+      PaYeR,
+      mintAuthSC.publicKey,
+      null,
+      10
+    );
+
+    try {
+      await program.methods
+        .depositToken(new anchor.BN(10), null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          from: person1ATA,
+          vaultToken: vaultTokenAddress(mintSC),
+          masterAta: await getAssociatedTokenAddress(
+            mintSC,
+            masterAddress,
+            true
+          ),
+          tokenMint: otherMint,
+          user: anchor.getProvider().publicKey,
+          userBlocked: blockedAddress(anchor.getProvider().publicKey),
+          userWhitelist: depositWhitelistAddress(
+            anchor.getProvider().publicKey
+          ),
+          userState: userStateAddress(anchor.getProvider().publicKey),
+          userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+          productLedger: null,
+          userDeposits: null,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "MintMismatch");
+    }
+  });
+
+  it("leaves no partial state when an inner step fails", async () => {
+    const programConfig = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("program_config")],
+      program.programId
+    )[0];
+    const otherMasterAddress = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("master"), new anchor.BN(2).toArrayLike(Buffer, "le", 8)],
+      program.programId
+    )[0];
+    const otherVaultToken = anchor.web3.PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("vault_token"),
+        otherMasterAddress.toBuffer(),
+        mintSC.toBuffer(),
+      ],
+      program.programId
+    )[0];
+    const otherMasterAta = await getAssociatedTokenAddress(
+      mintSC,
+      otherMasterAddress,
+      true
+    );
+
+    // vault token PDA and master ATA are created together or not at all
+    const unallowedMint = await createMint(
+      provider.connection,
+      PaYeR,
+      mintAuthSC.publicKey,
+      null,
+      10
+    );
+    try {
+      await program.methods
+        .addSupportedMint()
+        .accounts({
+          master: masterAddress,
+          programConfig,
+          vaultToken: vaultTokenAddress(unallowedMint),
+          masterAta: await getAssociatedTokenAddress(
+            unallowedMint,
+            masterAddress,
+            true
+          ),
+          tokenMint: unallowedMint,
+          admin: anchor.getProvider().publicKey,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "MintNotAllowed");
+    }
+    assert.isNull(
+      await provider.connection.getAccountInfo(vaultTokenAddress(unallowedMint))
+    );
+    assert.isNull(
+      await provider.connection.getAccountInfo(
+        await getAssociatedTokenAddress(unallowedMint, masterAddress, true)
+      )
+    );
+
+    // a failing token transfer leaves both vault balances as they were
+    await program.methods
+      .addSupportedMint()
+      .accounts({
+        master: otherMasterAddress,
+        programConfig,
+        vaultToken: otherVaultToken,
+        masterAta: otherMasterAta,
+        tokenMint: mintSC,
+        admin: anchor.getProvider().publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .transferTokenBetweenVaults(new anchor.BN(1000))
+        .accounts({
+          fromMaster: masterAddress,
+          toMaster: otherMasterAddress,
+          fromVaultToken: vaultTokenAddress(mintSC),
+          toVaultToken: otherVaultToken,
+          fromMasterAta: await getAssociatedTokenAddress(
+            mintSC,
+            masterAddress,
+            true
+          ),
+          toMasterAta: otherMasterAta,
+          admin: anchor.getProvider().publicKey,
+          tokenMint: mintSC,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isFalse(_err instanceof AnchorError);
+    }
+
+    const from = await program.account.vaultToken.fetch(
+      vaultTokenAddress(mintSC)
+    );
+    const to = await program.account.vaultToken.fetch(otherVaultToken);
+    expect(from.balance.toNumber()).to.be.eq(0);
+    expect(to.balance.toNumber()).to.be.eq(0);
+  });
+
+  it("can donate token without crediting a user", async () => {
+    const vaultToken = vaultTokenAddress(mintSC);
+    const masterAta = await getAssociatedTokenAddress(
+      mintSC,
+      masterAddress,
+      true
+    );
+    const before = await program.account.vaultToken.fetch(vaultToken);
+
+    await program.methods
+      .donateToken(new anchor.BN(25))
+      .accounts({
+        master: masterAddress,
+        vaultToken,
+        masterAta,
+        from: person1ATA,
+        donor: anchor.getProvider().publicKey,
+        tokenMint: mintSC,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const after = await program.account.vaultToken.fetch(vaultToken);
+    expect(after.balance.sub(before.balance).toNumber()).to.be.eq(25);
+  });
+
+  it("changes a limit of a mint with update_config", async () => {
+    const vaultToken = vaultTokenAddress(mintSC);
+    const updateConfig = (
+      setting: any,
+      vaultToken: anchor.web3.PublicKey | null
+    ) =>
+      program.methods
+        .updateConfig(setting)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          vaultToken,
+          programConfig: programConfigAddress,
+          admin,
+        })
+        .rpc();
+    const setting = { tokenVaultCap: { 0: new anchor.BN(1000) } };
+
+    try {
+      await updateConfig(setting, null);
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "VaultTokenMissing");
+    }
+
+    await updateConfig(setting, vaultToken);
+    let vaultTokenAcc = await program.account.vaultToken.fetch(vaultToken);
+    expect(vaultTokenAcc.vaultCap.toNumber()).to.be.eq(1000);
+
+    await updateConfig({ tokenVaultCap: { 0: new anchor.BN(0) } }, vaultToken);
+    vaultTokenAcc = await program.account.vaultToken.fetch(vaultToken);
+    expect(vaultTokenAcc.vaultCap.toNumber()).to.be.eq(0);
+  });
+
+  it("pays a token batch and skips failing payouts", async () => {
+    const vaultToken = vaultTokenAddress(mintSC);
+    const masterAta = await getAssociatedTokenAddress(
+      mintSC,
+      masterAddress,
+      true
+    );
+    // The second receiver has no ATA of the mint.
+    const receivers = [
+      person1.publicKey,
+      anchor.web3.Keypair.generate().publicKey,
+    ];
+    const remainingAccounts = [];
+    for (const receiver of receivers) {
+      const accounts = [
+        receiver,
+        await getAssociatedTokenAddress(mintSC, receiver),
+        receiverAllowlistAddress(receiver),
+        blockedAddress(receiver),
+        userStateAddress(receiver),
+        canaryAddress(receiver),
+      ];
+      accounts.forEach((pubkey, i) =>
+        remainingAccounts.push({ pubkey, isSigner: false, isWritable: i === 1 })
+      );
+    }
+
+    const sendBatch = (skipOnError: boolean) =>
+      program.methods
+        .sendWithdrawTokenBatch(
+          [new anchor.BN(10), new anchor.BN(10)],
+          skipOnError
+        )
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          vaultToken,
+          masterAta,
+          operator,
+          announcedBatch: null,
+          adminCosigner: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .remainingAccounts(remainingAccounts)
+        .rpc();
+
+    try {
+      await sendBatch(false);
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "AccountNotInitialized");
+    }
+
+    const before = await program.account.vaultToken.fetch(vaultToken);
+    await sendBatch(true);
+
+    const after = await program.account.vaultToken.fetch(vaultToken);
+    expect(before.balance.sub(after.balance).toNumber()).to.be.eq(10);
+  });
+
+  it("skips a token batch payout over the announced amount", async () => {
+    const vaultToken = vaultTokenAddress(mintSC);
+    const masterAta = await getAssociatedTokenAddress(
+      mintSC,
+      masterAddress,
+      true
+    );
+    const receiverAccounts = [
+      person1.publicKey,
+      await getAssociatedTokenAddress(mintSC, person1.publicKey),
+      receiverAllowlistAddress(person1.publicKey),
+      blockedAddress(person1.publicKey),
+      userStateAddress(person1.publicKey),
+      canaryAddress(person1.publicKey),
+    ].map((pubkey, i) => ({ pubkey, isSigner: false, isWritable: i === 1 }));
+
+    // The circuit breaker is on, so the token outflow window counts payouts.
+    await program.methods
+      .setCircuitBreaker(new anchor.BN(3600), new anchor.BN(0))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin,
+      })
+      .rpc();
+    await program.methods
+      .setTokenOutflowLimit(mintSC, new anchor.BN(1000000))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        vaultToken,
+        admin,
+      })
+      .rpc();
+
+    const batchHash = createHash("sha256").update("token-batch-1").digest();
+    const announcedBatch = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("announced_batch"), masterAddress.toBuffer(), batchHash],
+      program.programId
+    )[0];
+    await program.methods
+      .announceBatch(Array.from(batchHash), new anchor.BN(10))
+      .accounts({
+        master: masterAddress,
+        announcedBatch,
+        mint: mintSC,
+        operator,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const before = await program.account.vaultToken.fetch(vaultToken);
+    await program.methods
+      .sendWithdrawTokenBatch([new anchor.BN(10), new anchor.BN(10)], true)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        vaultToken,
+        masterAta,
+        operator,
+        announcedBatch,
+        adminCosigner: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts([...receiverAccounts, ...receiverAccounts])
+      .rpc();
+
+    // Only the first payout fits the batch, the skipped one is not counted.
+    const after = await program.account.vaultToken.fetch(vaultToken);
+    expect(before.balance.sub(after.balance).toNumber()).to.be.eq(10);
+    expect(after.outflow.amount.sub(before.outflow.amount).toNumber()).to.be.eq(
+      10
+    );
+    const batchAcc = await program.account.announcedBatch.fetch(announcedBatch);
+    expect(batchAcc.paid.toNumber()).to.be.eq(10);
+
+    await program.methods
+      .setTokenOutflowLimit(mintSC, new anchor.BN(0))
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        vaultToken,
+        admin,
+      })
+      .rpc();
+  });
+
+  it("creates the receiver ATA and reimburses its rent", async () => {
+    const receiver = anchor.web3.Keypair.generate().publicKey;
+    const receiverAta = await getAssociatedTokenAddress(mintSC, receiver);
+    const penaltyLedger = penaltyLedgerAddress(operator);
+
+    await program.methods
+      .setReimburseAtaRent(true)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin,
+      })
+      .rpc();
+
+    // The rent is paid from the SOL balance, less the operator's penalty.
+    const covered = async () => {
+      const masterAcc = await program.account.master.fetch(masterAddress);
+      const ledger = await program.account.penaltyLedger.fetchNullable(
+        penaltyLedger
+      );
+      const outstanding = ledger ? ledger.outstanding : new anchor.BN(0);
+      return masterAcc.balance.add(outstanding);
+    };
+    const coveredBefore = await covered();
+
+    await program.methods
+      .sendWithdrawToken(new anchor.BN(5), null, false, null, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        vaultToken: vaultTokenAddress(mintSC),
+        masterAta: await getAssociatedTokenAddress(mintSC, masterAddress, true),
+        operator,
+        receiverAta,
+        receiver,
+        receiverAllowlist: receiverAllowlistAddress(receiver),
+        receiverBlocked: blockedAddress(receiver),
+        receiverState: userStateAddress(receiver),
+        receiverCanary: canaryAddress(receiver),
+        productLedger: null,
+        receiverLock: null,
+        receiverCooldown: null,
+        limitExempt: null,
+        adminCosigner: null,
+        withdrawRequest: null,
+        withdrawQueue: null,
+        receipt: null,
+        memoProgram: null,
+        penaltyLedger,
+        tokenMint: mintSC,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const ata = await getAccount(provider.connection, receiverAta);
+    assert.equal(Number(ata.amount), 5);
+    const rent = (await provider.connection.getAccountInfo(receiverAta))
+      .lamports;
+    expect(coveredBefore.sub(await covered()).toNumber()).to.be.eq(rent);
+
+    await program.methods
+      .setReimburseAtaRent(false)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        admin,
+      })
+      .rpc();
+  });
+
+  it("pays to a non-associated token account of the receiver", async () => {
+    const receiver = anchor.web3.Keypair.generate().publicKey;
+    const tokenAccount = await createAccount(
+      provider.connection,
+      PaYeR,
+      mintSC,
+      receiver,
+      anchor.web3.Keypair.generate()
+    );
+    const vaultTokenAta = await getAssociatedTokenAddress(
+      mintSC,
+      masterAddress,
+      true
+    );
+
+    const sendWithdrawToken = (receiverAta: anchor.web3.PublicKey) =>
+      program.methods
+        .sendWithdrawToken(new anchor.BN(5), null, false, null, null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          vaultToken: vaultTokenAddress(mintSC),
+          masterAta: vaultTokenAta,
+          operator,
+          receiverAta,
+          receiver,
+          receiverAllowlist: receiverAllowlistAddress(receiver),
+          receiverBlocked: blockedAddress(receiver),
+          receiverState: userStateAddress(receiver),
+          receiverCanary: canaryAddress(receiver),
+          productLedger: null,
+          receiverLock: null,
+          receiverCooldown: null,
+          limitExempt: null,
+          adminCosigner: null,
+          withdrawRequest: null,
+          withdrawQueue: null,
+          receipt: null,
+          memoProgram: null,
+          penaltyLedger: penaltyLedgerAddress(operator),
+          tokenMint: mintSC,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // A token account of someone else is refused.
+    try {
+      await sendWithdrawToken(person1ATA);
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(
+        err.error.errorCode.code,
+        "ReceiverTokenAccountMismatch"
+      );
+    }
+
+    await sendWithdrawToken(tokenAccount);
+
+    const account = await getAccount(provider.connection, tokenAccount);
+    assert.equal(Number(account.amount), 5);
+  });
+
+  it("can disable and remove a mint", async () => {
+    const masterAta = await getAssociatedTokenAddress(
+      mintSC,
+      masterAddress,
+      true
+    );
+
+    await program.methods
+      .disableMint(mintSC)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        vaultToken: vaultTokenAddress(mintSC),
+        admin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    try {
+      await program.methods
+        .depositToken(new anchor.BN(10), null)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          programConfig: programConfigAddress,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          from: person1ATA,
+          vaultToken: vaultTokenAddress(mintSC),
+          masterAta: masterAta,
+          tokenMint: mintSC,
+          user: anchor.getProvider().publicKey,
+          userBlocked: blockedAddress(anchor.getProvider().publicKey),
+          userWhitelist: depositWhitelistAddress(
+            anchor.getProvider().publicKey
+          ),
+          userState: userStateAddress(anchor.getProvider().publicKey),
+          userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+          productLedger: null,
+          userDeposits: null,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "MintDisabled");
+    }
+
+    await program.methods
+      .removeMint(mintSC)
+      .accounts({
+        master: masterAddress,
+        vaultToken: vaultTokenAddress(mintSC),
+        masterAta: masterAta,
+        admin: anchor.getProvider().publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    assert.isNull(
+      await provider.connection.getAccountInfo(vaultTokenAddress(mintSC))
+    );
+    assert.isNull(await provider.connection.getAccountInfo(masterAta));
+  });
+
+  it("can wrap and unwrap the SOL of a vault", async () => {
+    const programConfig = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("program_config")],
+      program.programId
+    )[0];
+    const vaultToken = vaultTokenAddress(NATIVE_MINT);
+    const masterAta = await getAssociatedTokenAddress(
+      NATIVE_MINT,
+      masterAddress,
+      true
+    );
+    const amount = new anchor.BN(500000000);
+
+    await program.methods
+      .addAllowedMint(NATIVE_MINT)
+      .accounts({
+        programConfig,
+        superAdmin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    await program.methods
+      .addSupportedMint()
+      .accounts({
+        master: masterAddress,
+        programConfig,
+        vaultToken,
+        masterAta,
+        tokenMint: NATIVE_MINT,
+        admin: anchor.getProvider().publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    await program.methods
+      .deposit(amount, null)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const before = await program.account.master.fetch(masterAddress);
+    const wrapSol = () =>
+      program.methods
+        .wrapSol(amount)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          vaultToken,
+          masterAta,
+          authority: anchor.getProvider().publicKey,
+          tokenMint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+    const setHotThreshold = (threshold: anchor.BN) =>
+      program.methods
+        .setColdWallet(user1.publicKey, threshold)
+        .accounts({
+          master: masterAddress,
+          vaultConfig: vaultConfigAddress(masterAddress),
+          admin,
+        })
+        .rpc();
+
+    // The SOL reserved for the cold wallet cannot be wrapped.
+    await setHotThreshold(amount.sub(new anchor.BN(1)));
+    try {
+      await wrapSol();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "HotBalanceExceeded");
+    }
+    await setHotThreshold(new anchor.BN(0));
+
+    await wrapSol();
+
+    const wrapped = await getAccount(provider.connection, masterAta);
+    expect(wrapped.amount.toString()).to.be.eq(amount.toString());
+    const vault = await program.account.vaultToken.fetch(vaultToken);
+    expect(vault.balance.toString()).to.be.eq(amount.toString());
+    expect(vault.decimals).to.be.eq(9);
+
+    await program.methods
+      .unwrapSol(amount)
+      .accounts({
+        master: masterAddress,
+        vaultToken,
+        masterAta,
+        unwrapAccount: anchor.web3.PublicKey.findProgramAddressSync(
+          [Buffer.from("unwrap"), masterAddress.toBuffer()],
+          program.programId
+        )[0],
+        authority: anchor.getProvider().publicKey,
+        tokenMint: NATIVE_MINT,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const after = await program.account.master.fetch(masterAddress);
+    expect(after.balance.toString()).to.be.eq(before.balance.toString());
+    const unwrapped = await getAccount(provider.connection, masterAta);
+    expect(unwrapped.amount.toString()).to.be.eq("0");
+  });
+
+  it("can deposit SOL wrapped into wSOL", async () => {
+    const vaultToken = vaultTokenAddress(NATIVE_MINT);
+    const masterAta = await getAssociatedTokenAddress(
+      NATIVE_MINT,
+      masterAddress,
+      true
+    );
+    const amount = new anchor.BN(200000000);
+
+    const before = await program.account.vaultToken.fetch(vaultToken);
+
+    await program.methods
+      .depositWrapped(amount)
+      .accounts({
+        master: masterAddress,
+        vaultConfig: vaultConfigAddress(masterAddress),
+        programConfig: programConfigAddress,
+        vaultToken,
+        masterAta,
+        user: anchor.getProvider().publicKey,
+        userBlocked: blockedAddress(anchor.getProvider().publicKey),
+        userWhitelist: depositWhitelistAddress(anchor.getProvider().publicKey),
+        userState: userStateAddress(anchor.getProvider().publicKey),
+        userExchange: exchangeWalletAddress(anchor.getProvider().publicKey),
+        productLedger: null,
+        userDeposits: null,
+        tokenMint: NATIVE_MINT,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const after = await program.account.vaultToken.fetch(vaultToken);
+    expect(after.balance.sub(before.balance).toString()).to.be.eq(
+      amount.toString()
+    );
+    const wrapped = await getAccount(provider.connection, masterAta);
+    expect(wrapped.amount.toString()).to.be.eq(after.balance.toString());
+  });
+
+  it("can register a non-associated token account of a mint", async () => {
+    const custodyMint = await createMint(
+      provider.connection,
+      PaYeR,
+      mintAuthSC.publicKey,
+      null,
+      6
+    );
+    const tokenAccount = await createAccount(
+      provider.connection,
+      PaYeR,
+      custodyMint,
+      masterAddress,
+      anchor.web3.Keypair.generate()
+    );
+
+    await program.methods
+      .addAllowedMint(custodyMint)
+      .accounts({
+        programConfig: programConfigAddress,
+        superAdmin: anchor.getProvider().publicKey,
+      })
+      .rpc();
+
+    await program.methods
+      .addSupportedMintAccount()
+      .accounts({
+        master: masterAddress,
+        programConfig: programConfigAddress,
+        vaultToken: vaultTokenAddress(custodyMint),
+        tokenAccount,
+        tokenMint: custodyMint,
+        admin: anchor.getProvider().publicKey,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const vaultToken = await program.account.vaultToken.fetch(
+      vaultTokenAddress(custodyMint)
+    );
+    expect(vaultToken.ata.toBase58()).to.be.eq(tokenAccount.toBase58());
+    expect(vaultToken.enabled).to.be.eq(true);
+  });
+
+  it("can rescue stray tokens sent to the vault", async () => {
+    const strayMint = await createMint(
+      provider.connection,
+      PaYeR,
+      mintAuthSC.publicKey,
+      null,
+      6
+    );
+    const masterAta = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      PaYeR,
+      strayMint,
+      masterAddress,
+      true
+    );
+    const adminAta = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      PaYeR,
+      strayMint,
+      anchor.getProvider().publicKey
+    );
+    await mintTo(
+      provider.connection,
+      PaYeR,
+      strayMint,
+      masterAta.address,
+      mintAuthSC,
+      500
+    );
+
+    await program.methods
+      .rescueToken(strayMint)
+      .accounts({
+        master: masterAddress,
+        vaultToken: vaultTokenAddress(strayMint),
+        tokenAccount: masterAta.address,
+        admin: anchor.getProvider().publicKey,
+        adminAta: adminAta.address,
+        tokenMint: strayMint,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc();
+
+    const rescued = await getAccount(provider.connection, adminAta.address);
+    expect(rescued.amount.toString()).to.be.eq("500");
+    const left = await getAccount(provider.connection, masterAta.address);
+    expect(left.amount.toString()).to.be.eq("0");
+  });
+
+  it("cant rescue a supported mint", async () => {
+    const adminAta = await getOrCreateAssociatedTokenAccount(
+      provider.connection,
+      PaYeR,
+      NATIVE_MINT,
+      anchor.getProvider().publicKey
+    );
+
+    try {
+      await program.methods
+        .rescueToken(NATIVE_MINT)
+        .accounts({
+          master: masterAddress,
+          vaultToken: vaultTokenAddress(NATIVE_MINT),
+          tokenAccount: await getAssociatedTokenAddress(
+            NATIVE_MINT,
+            masterAddress,
+            true
+          ),
+          admin: anchor.getProvider().publicKey,
+          adminAta: adminAta.address,
+          tokenMint: NATIVE_MINT,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc();
+
+      assert.ok(false);
+    } catch (_err) {
+      assert.isTrue(_err instanceof AnchorError);
+      const err: AnchorError = _err;
+      assert.strictEqual(err.error.errorCode.code, "MintSupported");
+    }
   });
 });
@@ -0,0 +1,252 @@
+//! # Account fixtures
+//!
+//! Deterministic pro-balance accounts serialized the way they are stored on chain (discriminator included).
+//! They are written to `tests/fixtures` by `cargo run -p fixtures` so every decoder tests against identical state.
+//!
+//! Keys are fixed byte patterns instead of random keys, so the output only changes when an account layout does.
+
+use anchor_lang::{prelude::*, Discriminator};
+use pro_balance::{
+    LegacyMaster, Master, PenaltyLedger, ProgramConfig, RateWindow, UserState, VaultConfig,
+    VaultEntry, VaultRegistry, VaultToken, WithdrawQueue, MASTER_VERSION, MAX_ALLOWED_MINTS,
+    MAX_BPS, MAX_VAULTS,
+};
+
+/// Directory of the fixtures, relative to the workspace root.
+pub const FIXTURES_DIR: &str = "tests/fixtures";
+
+/// Size of the master account deployed before vaults, `size_of` of its struct and the discriminator.
+const LEGACY_MASTER_SIZE: usize = 136;
+
+/// Returns a fixed key made of `byte` repeated.
+pub fn key(byte: u8) -> Pubkey {
+    Pubkey::new_from_array([byte; 32])
+}
+
+fn serialize<T: AccountSerialize>(account: &T) -> Vec<u8> {
+    let mut data = Vec::new();
+    account
+        .try_serialize(&mut data)
+        .expect("fixture accounts always serialize");
+    data
+}
+
+fn master() -> Master {
+    Master {
+        balance: 5_000_000_000,
+        last_withdraw_time: 1_700_000_000,
+        operator: key(2),
+        admin: key(3),
+        guardian: key(4),
+        version: MASTER_VERSION,
+        ..Default::default()
+    }
+}
+
+/// Serializes a master of the layout deployed before vaults, padded to the size that program allocated for it.
+fn legacy_master() -> Vec<u8> {
+    let mut data = <Master as Discriminator>::DISCRIMINATOR.to_vec();
+    LegacyMaster {
+        balance: 5_000_000_000,
+        token_balance: 1_000_000,
+        token_account: Some(key(6)),
+        last_withdraw_time: 1_700_000_000,
+        operator: key(2),
+        admin: key(3),
+    }
+    .serialize(&mut data)
+    .expect("fixture accounts always serialize");
+    data.resize(LEGACY_MASTER_SIZE, 0);
+    data
+}
+
+fn vault_config() -> VaultConfig {
+    VaultConfig {
+        master: key(8),
+        ..Default::default()
+    }
+}
+
+fn vault_token(balance: u64, enabled: bool) -> VaultToken {
+    VaultToken {
+        mint: key(5),
+        ata: key(6),
+        balance,
+        enabled,
+        decimals: 6,
+        ..Default::default()
+    }
+}
+
+/// Returns every fixture as its file name and account data.
+pub fn fixtures() -> Vec<(&'static str, Vec<u8>)> {
+    vec![
+        ("master_default.bin", serialize(&Master::default())),
+        ("master_active.bin", serialize(&master())),
+        (
+            "master_revoked.bin",
+            serialize(&Master {
+                operator: Pubkey::default(),
+                ..master()
+            }),
+        ),
+        (
+            "master_outflow_at_limit.bin",
+            serialize(&Master {
+                outflow: RateWindow {
+                    start: 1_700_000_000,
+                    amount: 1_000_000_000,
+                },
+                ..master()
+            }),
+        ),
+        ("legacy_master.bin", legacy_master()),
+        ("vault_config_default.bin", serialize(&vault_config())),
+        (
+            "vault_config_paused.bin",
+            serialize(&VaultConfig {
+                deposits_paused: true,
+                withdrawals_paused: true,
+                ..vault_config()
+            }),
+        ),
+        (
+            "vault_config_restricted.bin",
+            serialize(&VaultConfig {
+                receiver_allowlist_enabled: true,
+                deposit_whitelist_enabled: true,
+                ..vault_config()
+            }),
+        ),
+        (
+            "vault_config_outflow_limit.bin",
+            serialize(&VaultConfig {
+                outflow_window_secs: 3_600,
+                outflow_limit: 1_000_000_000,
+                ..vault_config()
+            }),
+        ),
+        ("vault_token_empty.bin", serialize(&vault_token(0, true))),
+        (
+            "vault_token_max_balance.bin",
+            serialize(&vault_token(u64::MAX, true)),
+        ),
+        (
+            "vault_token_disabled.bin",
+            serialize(&vault_token(1, false)),
+        ),
+        (
+            "program_config_full.bin",
+            serialize(&ProgramConfig {
+                super_admin: key(1),
+                max_fee_bps: MAX_BPS,
+                paused: true,
+                allowed_mints: (0..MAX_ALLOWED_MINTS as u8)
+                    .map(|i| key(0x80 + i))
+                    .collect(),
+            }),
+        ),
+        (
+            "vault_registry_full.bin",
+            serialize(&VaultRegistry {
+                vaults: (0..MAX_VAULTS as u64)
+                    .map(|vault_id| VaultEntry {
+                        vault_id,
+                        master: key(0x40 + vault_id as u8),
+                        created_at: 1_700_000_000 + vault_id as i64,
+                    })
+                    .collect(),
+            }),
+        ),
+        (
+            "user_state_frozen.bin",
+            serialize(&UserState {
+                user: key(7),
+                frozen: true,
+            }),
+        ),
+        (
+            "penalty_ledger.bin",
+            serialize(&PenaltyLedger {
+                operator: key(2),
+                outstanding: 250,
+                total_recorded: 1_250,
+                fault_count: 3,
+                last_fault_time: 1_700_000_000,
+            }),
+        ),
+        (
+            "withdraw_queue_full.bin",
+            serialize(&WithdrawQueue {
+                head: 0,
+                tail: u64::MAX,
+            }),
+        ),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn checked_in_fixtures_are_up_to_date() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("../..")
+            .join(FIXTURES_DIR);
+
+        for (name, data) in fixtures() {
+            let stored = std::fs::read(dir.join(name))
+                .unwrap_or_else(|_| panic!("{name} is missing, run `cargo run -p fixtures`"));
+            assert_eq!(stored, data, "{name} is stale, run `cargo run -p fixtures`");
+        }
+    }
+
+    #[test]
+    fn max_balance_fixture_keeps_its_value() {
+        let (_, data) = fixtures()
+            .into_iter()
+            .find(|(name, _)| *name == "vault_token_max_balance.bin")
+            .unwrap();
+        let vault_token = VaultToken::try_deserialize(&mut data.as_slice()).unwrap();
+
+        assert_eq!(vault_token.balance, u64::MAX);
+        assert!(vault_token.balance.checked_add(1).is_none());
+    }
+
+    #[test]
+    fn full_withdraw_queue_fixture_has_no_sequence_left() {
+        let (_, data) = fixtures()
+            .into_iter()
+            .find(|(name, _)| *name == "withdraw_queue_full.bin")
+            .unwrap();
+        let withdraw_queue = WithdrawQueue::try_deserialize(&mut data.as_slice()).unwrap();
+
+        assert!(withdraw_queue.tail.checked_add(1).is_none());
+    }
+
+    #[test]
+    fn fixtures_decode_as_their_account() {
+        for (name, data) in fixtures() {
+            let data = &mut data.as_slice();
+            let decoded = match name.split('_').next().unwrap() {
+                "master" => Master::try_deserialize(data).is_ok(),
+                "legacy" => LegacyMaster::try_from_data(data).is_ok(),
+                "vault" if name.starts_with("vault_registry") => {
+                    VaultRegistry::try_deserialize(data).is_ok()
+                }
+                "vault" if name.starts_with("vault_config") => {
+                    VaultConfig::try_deserialize(data).is_ok()
+                }
+                "vault" => VaultToken::try_deserialize(data).is_ok(),
+                "program" => ProgramConfig::try_deserialize(data).is_ok(),
+                "user" => UserState::try_deserialize(data).is_ok(),
+                "penalty" => PenaltyLedger::try_deserialize(data).is_ok(),
+                "withdraw" => WithdrawQueue::try_deserialize(data).is_ok(),
+                _ => false,
+            };
+            assert!(decoded, "{name} does not decode");
+        }
+    }
+}
@@ -0,0 +1,22 @@
+//! Writes the account fixtures to `tests/fixtures`, or to the directory given as the first argument.
+
+use std::path::PathBuf;
+
+fn main() -> std::io::Result<()> {
+    let dir = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| {
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("../..")
+                .join(fixtures::FIXTURES_DIR)
+        });
+    std::fs::create_dir_all(&dir)?;
+
+    for (name, data) in fixtures::fixtures() {
+        std::fs::write(dir.join(name), data)?;
+        println!("wrote {name}");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,174 @@
+//! # IDL diff
+//!
+//! Compares the IDL of the deployed program with the IDL of the local build and lists the instructions, account
+//! layouts and types which have been added, removed or changed.
+//!
+//! Additions keep the interface compatible. A removed or changed instruction breaks existing clients, and a removed or
+//! changed layout can no longer decode the accounts already on chain, so both count as breaking.
+
+use serde_json::Value;
+use std::fmt;
+
+/// Sections of the IDL which are compared, each holding a list of items with a `name`.
+pub const SECTIONS: [&str; 3] = ["instructions", "accounts", "types"];
+
+/// What has happened to an item between the two IDLs.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ChangeKind {
+    /// Only in the local IDL.
+    Added,
+    /// Only in the deployed IDL.
+    Removed,
+    /// In both IDLs, with a different definition.
+    Changed,
+}
+
+/// One item which differs between the two IDLs.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Change {
+    /// IDL section of the item, one of `SECTIONS`.
+    pub section: &'static str,
+    /// Name of the item.
+    pub name: String,
+    /// What has happened to the item.
+    pub kind: ChangeKind,
+}
+
+impl Change {
+    /// Whether the change makes the local build incompatible with the deployed interface.
+    pub fn is_breaking(&self) -> bool {
+        self.kind != ChangeKind::Added
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let sign = match self.kind {
+            ChangeKind::Added => '+',
+            ChangeKind::Removed => '-',
+            ChangeKind::Changed => '~',
+        };
+        write!(f, "{sign} {} {}", self.section, self.name)
+    }
+}
+
+fn items<'a>(idl: &'a Value, section: &str) -> Vec<(&'a str, &'a Value)> {
+    idl[section]
+        .as_array()
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|item| Some((item["name"].as_str()?, item)))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Returns the changes from the `deployed` IDL to the `local` one, section by section in IDL order.
+pub fn diff(deployed: &Value, local: &Value) -> Vec<Change> {
+    let mut changes = Vec::new();
+
+    for section in SECTIONS {
+        let old = items(deployed, section);
+        let new = items(local, section);
+
+        for (name, item) in &old {
+            let kind = match new.iter().find(|(other, _)| other == name) {
+                None => ChangeKind::Removed,
+                Some((_, other)) if other != item => ChangeKind::Changed,
+                Some(_) => continue,
+            };
+            changes.push(Change {
+                section,
+                name: name.to_string(),
+                kind,
+            });
+        }
+        for (name, _) in &new {
+            if !old.iter().any(|(other, _)| other == name) {
+                changes.push(Change {
+                    section,
+                    name: name.to_string(),
+                    kind: ChangeKind::Added,
+                });
+            }
+        }
+    }
+
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn idl(instructions: Value, accounts: Value) -> Value {
+        json!({ "instructions": instructions, "accounts": accounts, "types": [] })
+    }
+
+    #[test]
+    fn identical_idls_have_no_changes() {
+        let deployed = idl(
+            json!([{ "name": "deposit", "args": [{ "name": "amount", "type": "u64" }] }]),
+            json!([{ "name": "Master", "type": { "kind": "struct", "fields": [] } }]),
+        );
+
+        assert!(diff(&deployed, &deployed.clone()).is_empty());
+    }
+
+    #[test]
+    fn only_additions_are_compatible() {
+        let deployed = idl(
+            json!([
+                { "name": "deposit", "args": [{ "name": "amount", "type": "u64" }] },
+                { "name": "withdraw", "args": [] },
+            ]),
+            json!([{ "name": "Master", "type": { "kind": "struct", "fields": [] } }]),
+        );
+        let local = idl(
+            json!([
+                { "name": "deposit", "args": [{ "name": "amount", "type": "u32" }] },
+                { "name": "pause", "args": [] },
+            ]),
+            json!([{ "name": "Master", "type": { "kind": "struct", "fields": [] } }]),
+        );
+
+        let changes = diff(&deployed, &local);
+        let summary: Vec<String> = changes.iter().map(ToString::to_string).collect();
+
+        assert_eq!(
+            summary,
+            [
+                "~ instructions deposit",
+                "- instructions withdraw",
+                "+ instructions pause"
+            ]
+        );
+        assert_eq!(
+            changes.iter().filter(|change| change.is_breaking()).count(),
+            2
+        );
+    }
+
+    #[test]
+    fn changed_account_layout_is_breaking() {
+        let deployed = idl(
+            json!([]),
+            json!([{ "name": "Master", "type": { "kind": "struct", "fields": [] } }]),
+        );
+        let local = idl(
+            json!([]),
+            json!([{
+                "name": "Master",
+                "type": { "kind": "struct", "fields": [{ "name": "balance", "type": "u64" }] },
+            }]),
+        );
+
+        let changes = diff(&deployed, &local);
+
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].kind, ChangeKind::Changed);
+        assert!(changes[0].is_breaking());
+    }
+}
@@ -0,0 +1,47 @@
+//! Prints the changes from the deployed IDL (first argument) to the local one (second argument, by default
+//! `target/idl/pro_balance.json`) and exits with status 1 if any of them is breaking.
+
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+fn read(path: &Path) -> Result<serde_json::Value, String> {
+    let data =
+        std::fs::read(path).map_err(|err| format!("cannot read {}: {err}", path.display()))?;
+    serde_json::from_slice(&data).map_err(|err| format!("cannot parse {}: {err}", path.display()))
+}
+
+fn run() -> Result<bool, String> {
+    let mut args = std::env::args().skip(1);
+    let deployed = args
+        .next()
+        .map(PathBuf::from)
+        .ok_or("usage: idl-diff <deployed idl> [local idl]")?;
+    let local = args.next().map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("../../target/idl/pro_balance.json")
+    });
+
+    let changes = idl_diff::diff(&read(&deployed)?, &read(&local)?);
+    for change in &changes {
+        println!("{change}");
+    }
+
+    let breaking = changes.iter().filter(|change| change.is_breaking()).count();
+    if breaking > 0 {
+        println!("{breaking} breaking change(s)");
+    } else {
+        println!("compatible");
+    }
+
+    Ok(breaking == 0)
+}
+
+fn main() -> ExitCode {
+    match run() {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(err) => {
+            eprintln!("{err}");
+            ExitCode::from(2)
+        }
+    }
+}